@@ -1,7 +1,8 @@
 use pufferfish::assets::Assets;
 use pufferfish::graphics::{Color, Graphics};
 use pufferfish::input::{Input, KeyCode};
-use pufferfish::App;
+use pufferfish::time::Time;
+use pufferfish::{App, RunError};
 
 struct Player {
     x: f32,
@@ -13,7 +14,7 @@ struct State {
     player: Player,
 }
 
-fn main() {
+fn main() -> Result<(), RunError> {
     App::new()
         .with_title("Hello World")
         .with_size(500, 500)
@@ -23,11 +24,12 @@ fn main() {
         })
         .add_frame_callback(process_input)
         .add_frame_callback(draw)
-        .run();
+        .run()?;
+    Ok(())
 }
 
-fn process_input(state: &mut State, input: &Input) {
-    state.t += 0.001;
+fn process_input(state: &mut State, input: &Input, time: &Time) {
+    state.t += time.delta();
 
     if input.is_key_down(KeyCode::D) || input.is_key_down(KeyCode::Right) {
         state.player.x += 3.;
@@ -45,7 +47,6 @@ fn process_input(state: &mut State, input: &Input) {
 
 fn draw(state: &State, g: &mut Graphics, assets: &mut Assets) {
     g.clear(Color::BLACK);
-    g.begin();
     g.draw_sprite(
         state.player.x - 16.,
         state.player.y - 16.,
@@ -53,5 +54,4 @@ fn draw(state: &State, g: &mut Graphics, assets: &mut Assets) {
     );
     g.draw_text(10., 10., "Hello, world!\nThis is a pufferfish example.")
         .color(Color::RED);
-    g.end();
 }
@@ -0,0 +1,50 @@
+use pufferfish::graphics::{Color, Graphics};
+use pufferfish::input::{Input, KeyCode};
+use pufferfish::{App, Plugin, RunError};
+
+struct DebugOverlay {
+    visible: bool,
+}
+
+impl Plugin for DebugOverlay {
+    fn build(self, app: App) -> App {
+        app.add_state(self)
+            .add_frame_callback(toggle_overlay)
+            .add_frame_callback(draw_overlay)
+    }
+}
+
+fn toggle_overlay(overlay: &mut DebugOverlay, input: &Input) {
+    if input.is_key_pressed(KeyCode::Tab) {
+        overlay.visible = !overlay.visible;
+    }
+}
+
+fn draw_overlay(overlay: &DebugOverlay, input: &Input, g: &mut Graphics) {
+    if !overlay.visible {
+        return;
+    }
+    let keys = input
+        .get_keys_down()
+        .map(|key| format!("{:?}", key))
+        .collect::<Vec<_>>()
+        .join(", ");
+    g.draw_rect(0., 0., 500., 40.)
+        .color(Color::from_rgba(0., 0., 0., 0.5));
+    g.draw_text(10., 10., &format!("Keys down: {}", keys));
+}
+
+fn main() -> Result<(), RunError> {
+    App::new()
+        .with_title("Plugin")
+        .with_size(500, 500)
+        .add_frame_callback(draw)
+        .add_plugin(DebugOverlay { visible: true })
+        .run()?;
+    Ok(())
+}
+
+fn draw(g: &mut Graphics) {
+    g.clear(Color::BLACK);
+    g.draw_text(10., 250., "Press Tab to toggle the debug overlay.");
+}
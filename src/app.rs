@@ -1,17 +1,29 @@
 use std::any::{Any, TypeId};
 use std::borrow::Cow;
+use std::cell::Cell;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::panic::{self, AssertUnwindSafe};
 use std::ptr::NonNull;
 use std::rc::Rc;
+use std::time::Duration;
 
-use fugu::Context;
 use hashbrown::HashMap;
 
 use crate::assets::{Assets, ResourceManager};
-use crate::graphics::{Graphics, Sprite};
-use crate::input::Input;
+use crate::graphics::{Context, Gl, Graphics, Sprite};
+use crate::input::{Gamepads, Input};
+use crate::time::Time;
 use crate::util::{replace_with, type_name};
+use crate::window::{Window, WindowEvents};
 
+#[cfg(feature = "null-backend")]
+mod null;
+#[cfg(feature = "null-backend")]
+use self::null as backend;
+#[cfg(not(feature = "null-backend"))]
 mod sdl;
+#[cfg(not(feature = "null-backend"))]
 use self::sdl as backend;
 
 struct ArgDesc {
@@ -25,9 +37,24 @@ trait Argable {
     fn desc() -> ArgDesc;
 }
 
+/// Panics because a callback requires state of type `T` that does not exist.
+///
+/// State can be added after callbacks that use it, so this can only be checked
+/// once the callback is called.
+#[cold]
+fn missing_state<T>() -> ! {
+    panic!(
+        "callback requires state of type {} which was never added",
+        type_name::<T>()
+    )
+}
+
 impl<T: 'static> Argable for &T {
     unsafe fn get(args: *mut TypeMap) -> Self {
-        args.as_mut().unwrap_unchecked().get::<T>().unwrap()
+        args.as_mut()
+            .unwrap_unchecked()
+            .get::<T>()
+            .unwrap_or_else(|| missing_state::<T>())
     }
 
     fn desc() -> ArgDesc {
@@ -41,7 +68,10 @@ impl<T: 'static> Argable for &T {
 
 impl<T: 'static> Argable for &mut T {
     unsafe fn get(args: *mut TypeMap) -> Self {
-        args.as_mut().unwrap_unchecked().get_mut::<T>().unwrap()
+        args.as_mut()
+            .unwrap_unchecked()
+            .get_mut::<T>()
+            .unwrap_or_else(|| missing_state::<T>())
     }
 
     fn desc() -> ArgDesc {
@@ -53,10 +83,37 @@ impl<T: 'static> Argable for &mut T {
     }
 }
 
+impl<T: 'static> Argable for Option<&T> {
+    unsafe fn get(args: *mut TypeMap) -> Self {
+        args.as_mut().unwrap_unchecked().get::<T>()
+    }
+
+    fn desc() -> ArgDesc {
+        <&T>::desc()
+    }
+}
+
+impl<T: 'static> Argable for Option<&mut T> {
+    unsafe fn get(args: *mut TypeMap) -> Self {
+        args.as_mut().unwrap_unchecked().get_mut::<T>()
+    }
+
+    fn desc() -> ArgDesc {
+        <&mut T>::desc()
+    }
+}
+
 /// A heterogeneous collection that can store one value of each type.
 #[derive(Default)]
 pub struct TypeMap {
-    inner: HashMap<TypeId, NonNull<dyn Any>>,
+    inner: HashMap<TypeId, Entry>,
+}
+
+struct Entry {
+    value: NonNull<dyn Any>,
+    /// The number of active borrows through [`TypeMap::try_get`], or `-1` if
+    /// the value is borrowed mutably through [`TypeMap::try_get_mut`].
+    flag: Cell<isize>,
 }
 
 impl TypeMap {
@@ -74,13 +131,91 @@ impl TypeMap {
         let type_id = TypeId::of::<T>();
         if let Some(v) = self.inner.remove(&type_id) {
             unsafe {
-                drop(Box::from_raw(v.as_ptr()));
+                drop(Box::from_raw(v.value.as_ptr()));
             }
         }
-        // SAFETY: The pointer returned by Box::into_raw is guaranteed to be non-null.
-        self.inner.insert(TypeId::of::<T>(), unsafe {
-            NonNull::new_unchecked(Box::into_raw(Box::new(v)))
-        });
+        self.inner.insert(
+            TypeId::of::<T>(),
+            Entry {
+                // SAFETY: The pointer returned by Box::into_raw is guaranteed to be non-null.
+                value: unsafe { NonNull::new_unchecked(Box::into_raw(Box::new(v))) },
+                flag: Cell::new(0),
+            },
+        );
+    }
+
+    /// Removes the value of type `T` from the type map and returns it, if it
+    /// exists.
+    pub fn remove<T: 'static>(&mut self) -> Option<T> {
+        let entry = self.inner.remove(&TypeId::of::<T>())?;
+        // SAFETY: The pointer was created by Box::into_raw in `insert` and has
+        // just been unlinked, so it is not freed again on drop.
+        let value = unsafe { Box::from_raw(entry.value.as_ptr()) };
+        // SAFETY: The types are guaranteed to match.
+        Some(*unsafe { value.downcast::<T>().unwrap_unchecked() })
+    }
+
+    /// Returns true if the type map contains a value of type `T`.
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.inner.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Returns a shared reference to the value of type `T` if it exists.
+    ///
+    /// Unlike [`get`](Self::get), this tracks borrows at runtime like a
+    /// [`RefCell`](std::cell::RefCell), so it is safe to call.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is currently borrowed mutably through
+    /// [`try_get_mut`](Self::try_get_mut).
+    pub fn try_get<T: 'static>(&self) -> Option<TypeMapRef<T>> {
+        let entry = self.inner.get(&TypeId::of::<T>())?;
+        let flag = entry.flag.get();
+        assert!(
+            flag >= 0,
+            "{} is already borrowed mutably",
+            type_name::<T>()
+        );
+        entry.flag.set(flag + 1);
+        Some(TypeMapRef {
+            // SAFETY: The types are guaranteed to match, and the flag ensures
+            // that there are no mutable borrows.
+            value: unsafe { entry.value.as_ref().downcast_ref::<T>().unwrap_unchecked() },
+            flag: &entry.flag,
+        })
+    }
+
+    /// Returns a mutable reference to the value of type `T` if it exists.
+    ///
+    /// Unlike [`get_mut`](Self::get_mut), this tracks borrows at runtime like a
+    /// [`RefCell`](std::cell::RefCell), so it is safe to call.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is currently borrowed through
+    /// [`try_get`](Self::try_get) or [`try_get_mut`](Self::try_get_mut).
+    pub fn try_get_mut<T: 'static>(&self) -> Option<TypeMapRefMut<T>> {
+        let entry = self.inner.get(&TypeId::of::<T>())?;
+        assert!(
+            entry.flag.get() == 0,
+            "{} is already borrowed",
+            type_name::<T>()
+        );
+        entry.flag.set(-1);
+        Some(TypeMapRefMut {
+            // SAFETY: The types are guaranteed to match, and the flag ensures
+            // that there are no other borrows.
+            value: unsafe {
+                entry
+                    .value
+                    .as_ptr()
+                    .as_mut()
+                    .and_then(|e| e.downcast_mut::<T>())
+                    .unwrap_unchecked()
+            },
+            flag: &entry.flag,
+        })
     }
 
     /// Returns a reference to the value of type `T` if it exists.
@@ -91,7 +226,7 @@ impl TypeMap {
     pub unsafe fn get<T: 'static>(&self) -> Option<&T> {
         self.inner.get(&TypeId::of::<T>()).map(|v| {
             // SAFETY: The types are guaranteed to match.
-            v.as_ref().downcast_ref::<T>().unwrap_unchecked()
+            v.value.as_ref().downcast_ref::<T>().unwrap_unchecked()
         })
     }
 
@@ -103,7 +238,8 @@ impl TypeMap {
     pub unsafe fn get_mut<T: 'static>(&self) -> Option<&mut T> {
         self.inner.get(&TypeId::of::<T>()).map(|v| {
             // SAFETY: The pointer is guaranteed to be non-null with matching types.
-            v.as_ptr()
+            v.value
+                .as_ptr()
                 .as_mut()
                 .and_then(|e| e.downcast_mut::<T>())
                 .unwrap_unchecked()
@@ -115,16 +251,65 @@ impl Drop for TypeMap {
     fn drop(&mut self) {
         for (_, v) in self.inner.drain() {
             unsafe {
-                drop(Box::from_raw(v.as_ptr()));
+                drop(Box::from_raw(v.value.as_ptr()));
             }
         }
     }
 }
 
+/// A shared reference to a value in a [`TypeMap`]. Returned by
+/// [`TypeMap::try_get`].
+pub struct TypeMapRef<'a, T> {
+    value: &'a T,
+    flag: &'a Cell<isize>,
+}
+
+impl<T> Deref for TypeMapRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T> Drop for TypeMapRef<'_, T> {
+    fn drop(&mut self) {
+        self.flag.set(self.flag.get() - 1);
+    }
+}
+
+/// A mutable reference to a value in a [`TypeMap`]. Returned by
+/// [`TypeMap::try_get_mut`].
+pub struct TypeMapRefMut<'a, T> {
+    value: &'a mut T,
+    flag: &'a Cell<isize>,
+}
+
+impl<T> Deref for TypeMapRefMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T> DerefMut for TypeMapRefMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value
+    }
+}
+
+impl<T> Drop for TypeMapRefMut<'_, T> {
+    fn drop(&mut self) {
+        self.flag.set(0);
+    }
+}
+
 /// An interface for callbacks.
 ///
 /// Callbacks may borrow arbitrary state from the application through their type
-/// signature.
+/// signature. Arguments of type `Option<&T>` or `Option<&mut T>` receive `None`
+/// if the application has no state of type `T`, instead of causing a panic.
 pub trait Callback<Args, Output> {
     /// Calls the callback with the given state and returns its output.
     ///
@@ -170,6 +355,146 @@ macro_rules! impl_callback {
 
 impl_callback!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z);
 
+/// The error returned by [`App::try_add_state`] when the application already
+/// has state of the same type.
+#[derive(Debug)]
+pub struct DuplicateStateError {
+    type_name: &'static str,
+}
+
+impl DuplicateStateError {
+    /// Returns the name of the type of the duplicate state.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+impl fmt::Display for DuplicateStateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "state of type {} already exists", self.type_name)
+    }
+}
+
+impl std::error::Error for DuplicateStateError {}
+
+/// An opaque identifier of a frame callback, returned by
+/// [`App::add_frame_callback_with_id`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct CallbackId(u64);
+
+/// An interface for enabling, disabling, and removing frame callbacks at
+/// runtime, e.g. to switch between the update logic of different scenes.
+/// Accessible from [`App`] by default.
+///
+/// Changes requested through [`Callbacks`] are applied before the frame
+/// callbacks of the next frame are executed. Requests for callbacks that have
+/// been removed are ignored.
+pub struct Callbacks {
+    changes: Vec<(CallbackId, CallbackChange)>,
+}
+
+#[derive(Clone, Copy)]
+enum CallbackChange {
+    Enable,
+    Disable,
+    Remove,
+}
+
+impl Callbacks {
+    fn new() -> Callbacks {
+        Callbacks {
+            changes: Vec::new(),
+        }
+    }
+
+    /// Enables the frame callback with the given id, so that it is executed
+    /// every frame again.
+    pub fn enable(&mut self, id: CallbackId) {
+        self.changes.push((id, CallbackChange::Enable));
+    }
+
+    /// Disables the frame callback with the given id, so that it is skipped
+    /// until it is enabled again.
+    pub fn disable(&mut self, id: CallbackId) {
+        self.changes.push((id, CallbackChange::Disable));
+    }
+
+    /// Removes the frame callback with the given id permanently.
+    pub fn remove(&mut self, id: CallbackId) {
+        self.changes.push((id, CallbackChange::Remove));
+    }
+}
+
+struct FrameCallback {
+    id: CallbackId,
+    enabled: bool,
+    callback: Box<dyn Fn(&mut TypeMap)>,
+}
+
+/// Why the application stopped running. Returned by [`App::run`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExitReason {
+    /// The window was closed, and none of the
+    /// [quit callbacks](App::add_quit_callback) cancelled it.
+    Closed,
+    /// Quitting was requested through [`Window::request_quit`], and none of
+    /// the [quit callbacks](App::add_quit_callback) cancelled it.
+    Quit,
+    /// The requested number of frames was run by [`App::run_headless`].
+    Frames,
+}
+
+/// The error returned by [`App::run`] when the application fails to start,
+/// e.g. because no display is available.
+#[derive(Debug)]
+pub struct RunError {
+    message: String,
+}
+
+impl RunError {
+    fn new(message: impl fmt::Display) -> Self {
+        RunError {
+            message: message.to_string(),
+        }
+    }
+
+    /// Returns a description of the failure, as reported by the backend.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "failed to start application: {}", self.message)
+    }
+}
+
+impl std::error::Error for RunError {}
+
+/// The kind of OpenGL context to request. See [`App::with_gl_version`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GlProfile {
+    /// A desktop OpenGL core profile context.
+    Core,
+    /// A desktop OpenGL compatibility profile context.
+    Compatibility,
+    /// An OpenGL ES context.
+    Es,
+}
+
+/// A bundle of state and callbacks that can be added to an [`App`] at once.
+///
+/// Plugins make it possible to split up a large application into modules that
+/// each register their own state and callbacks through [`add_plugin`].
+///
+/// [`add_plugin`]: App::add_plugin
+pub trait Plugin {
+    /// Adds the plugin's state and callbacks to the given [`App`] and returns
+    /// it.
+    fn build(self, app: App) -> App;
+}
+
 /// A `pufferfish` application.
 ///
 /// The `App` stores the state of the application as well as any callbacks that
@@ -178,10 +503,19 @@ pub struct App {
     title: Cow<'static, str>,
     size: (u32, u32),
     vsync: bool,
+    target_fps: u32,
     resizable: bool,
+    srgb: bool,
+    gl_version: (u8, u8, GlProfile),
+    premultiply: bool,
+    fixed_timestep: f64,
     state: TypeMap,
-    frame_callbacks: Box<dyn Fn(&mut TypeMap)>,
+    setup_callbacks: Vec<Box<dyn FnOnce(App) -> App>>,
+    frame_callbacks: Vec<FrameCallback>,
+    next_callback_id: u64,
+    fixed_callbacks: Box<dyn Fn(&mut TypeMap)>,
     init_callbacks: Box<dyn Fn(&mut TypeMap)>,
+    quit_callbacks: Box<dyn Fn(&mut TypeMap) -> bool>,
 }
 
 impl Default for App {
@@ -190,10 +524,19 @@ impl Default for App {
             title: "Pufferfish".into(),
             size: (800, 600),
             vsync: true,
+            target_fps: 0,
             resizable: true,
+            srgb: false,
+            gl_version: (3, 3, GlProfile::Core),
+            premultiply: false,
+            fixed_timestep: 60.,
             state: TypeMap::new(),
-            frame_callbacks: Box::new(|_| {}),
+            setup_callbacks: Vec::new(),
+            frame_callbacks: Vec::new(),
+            next_callback_id: 0,
+            fixed_callbacks: Box::new(|_| {}),
             init_callbacks: Box::new(|_| {}),
+            quit_callbacks: Box::new(|_| true),
         }
     }
 }
@@ -228,6 +571,38 @@ impl App {
         self
     }
 
+    /// Sets the maximum number of frames per second, or `0` for no limit.
+    ///
+    /// This caps the frame rate when vsync is disabled or unavailable. If vsync
+    /// is enabled, the frame rate is limited by whichever is lower.
+    ///
+    /// The default value is `0`.
+    pub fn with_target_fps(mut self, fps: u32) -> Self {
+        self.target_fps = fps;
+        self
+    }
+
+    /// Sets the rate in hertz at which fixed callbacks are executed.
+    ///
+    /// See [`add_fixed_callback`](Self::add_fixed_callback) for more
+    /// information.
+    ///
+    /// The default value is `60.0`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `hz` is not positive and finite, or so large that a fixed
+    /// timestep would be shorter than a nanosecond.
+    pub fn with_fixed_timestep(mut self, hz: f64) -> Self {
+        assert!(
+            hz > 0. && hz.is_finite() && Duration::from_secs_f64(1. / hz) > Duration::ZERO,
+            "invalid fixed timestep {}",
+            hz
+        );
+        self.fixed_timestep = hz;
+        self
+    }
+
     /// Sets whether or not the application window should be resizable.
     ///
     /// The default value is `true`.
@@ -236,6 +611,64 @@ impl App {
         self
     }
 
+    /// Requests whether or not the default framebuffer should be
+    /// sRGB-capable.
+    ///
+    /// [`Color`]s are linear, so with this enabled, the GPU gamma-encodes
+    /// everything drawn to the screen and colors appear as intended (e.g.
+    /// `Color::from_rgb(0.5, 0.5, 0.5)` appears as mid-gray).
+    ///
+    /// Note that sprites are currently always sampled as linear data. Since
+    /// most images are stored as sRGB, sprites will appear washed out with this
+    /// enabled unless their data is converted to linear beforehand.
+    ///
+    /// The default value is `false`.
+    pub fn with_srgb(mut self, srgb: bool) -> Self {
+        self.srgb = srgb;
+        self
+    }
+
+    /// Sets the version and profile of the OpenGL context to request.
+    ///
+    /// Desktop OpenGL 3.0 or later and OpenGL ES 3.0 or later are supported.
+    /// The shaders used for drawing are adjusted to match the version of the
+    /// context that is actually created.
+    ///
+    /// The default value is `(3, 3, GlProfile::Core)`.
+    pub fn with_gl_version(mut self, major: u8, minor: u8, profile: GlProfile) -> Self {
+        self.gl_version = (major, minor, profile);
+        self
+    }
+
+    /// Sets whether or not sprites loaded by the built-in loaders should have
+    /// their alpha premultiplied.
+    ///
+    /// See [`premultiply_alpha`](crate::graphics::premultiply_alpha) for more
+    /// information.
+    ///
+    /// The default value is `false`.
+    pub fn with_premultiplied_sprites(mut self, premultiply: bool) -> Self {
+        self.premultiply = premultiply;
+        self
+    }
+
+    /// Adds a callback that is executed when [`run`] is called, before the
+    /// window is opened.
+    ///
+    /// The callback receives the application and returns it, so it can use any
+    /// of the `with_` methods to configure the window based on information
+    /// that is only available at run time, e.g. a settings file. Note that
+    /// [`Assets`] is not available yet at this point, so files need to be read
+    /// directly.
+    ///
+    /// Setup callbacks are executed in the order they are added.
+    ///
+    /// [`run`]: Self::run
+    pub fn with_setup(mut self, callback: impl FnOnce(App) -> App + 'static) -> Self {
+        self.setup_callbacks.push(Box::new(callback));
+        self
+    }
+
     /// Adds new state to the application.
     ///
     /// If multiple values of the same type are added, the last one added will
@@ -245,6 +678,23 @@ impl App {
         self
     }
 
+    /// Adds new state to the application, failing if state of the same type
+    /// has already been added.
+    ///
+    /// Unlike [`add_state`], this never replaces existing state, which makes
+    /// accidentally adding the same type twice easy to notice.
+    ///
+    /// [`add_state`]: Self::add_state
+    pub fn try_add_state<T: 'static>(mut self, state: T) -> Result<Self, DuplicateStateError> {
+        if self.state.contains::<T>() {
+            return Err(DuplicateStateError {
+                type_name: type_name::<T>(),
+            });
+        }
+        self.state.insert(state);
+        Ok(self)
+    }
+
     /// Adds new state to the application based on preexisting state.
     ///
     /// Closures registered via this function are executed in the order they are
@@ -270,13 +720,49 @@ impl App {
 
     /// Adds a callback that is executed every frame.
     ///
-    /// Frame callbacks are executed in the order they are added.
-    pub fn add_frame_callback<Args, F: Callback<Args, ()> + 'static>(
+    /// Frame callbacks are executed in the order they are added. Drawing is
+    /// begun before the first frame callback and ended after the last one, so
+    /// calling [`Graphics::begin`] and [`Graphics::end`] is not necessary.
+    pub fn add_frame_callback<Args, F: Callback<Args, ()> + 'static>(self, callback: F) -> Self {
+        self.add_frame_callback_with_id(callback).0
+    }
+
+    /// Adds a callback that is executed every frame, like
+    /// [`add_frame_callback`](Self::add_frame_callback), and returns an id
+    /// that can be used to enable, disable, or remove it at runtime through
+    /// [`Callbacks`].
+    pub fn add_frame_callback_with_id<Args, F: Callback<Args, ()> + 'static>(
+        mut self,
+        callback: F,
+    ) -> (Self, CallbackId) {
+        F::assert_legal();
+        let id = CallbackId(self.next_callback_id);
+        self.next_callback_id += 1;
+        self.frame_callbacks.push(FrameCallback {
+            id,
+            enabled: true,
+            callback: Box::new(move |args| unsafe { callback.call(args) }),
+        });
+        (self, id)
+    }
+
+    /// Adds a callback that is executed at a fixed rate, independent of the
+    /// frame rate, e.g. for deterministic physics.
+    ///
+    /// Before the frame callbacks of each frame, fixed callbacks are executed
+    /// zero or more times, once for each fixed timestep that has elapsed since
+    /// they were last executed. The rate is set by
+    /// [`with_fixed_timestep`](Self::with_fixed_timestep). See
+    /// [`Time::fixed_delta`] and [`Time::alpha`] for smoothing rendering
+    /// between fixed timesteps.
+    ///
+    /// Fixed callbacks are executed in the order they are added.
+    pub fn add_fixed_callback<Args, F: Callback<Args, ()> + 'static>(
         mut self,
         callback: F,
     ) -> Self {
         F::assert_legal();
-        replace_with(&mut self.frame_callbacks, |cbs| {
+        replace_with(&mut self.fixed_callbacks, |cbs| {
             Box::new(move |args| unsafe {
                 cbs(args);
                 callback.call(args);
@@ -301,25 +787,156 @@ impl App {
         self
     }
 
-    /// Runs the application, executing any init callbacks, opening a window,
-    /// and starting the event loop.
-    pub fn run(self) {
-        backend::run(self);
+    /// Adds a callback that is executed when the user requests the
+    /// application to quit, e.g. by closing the window or through
+    /// [`Window::request_quit`].
+    ///
+    /// The application only quits if the callback returns `true`. Returning
+    /// `false` cancels the request, allowing the application to e.g. ask for
+    /// confirmation first.
+    ///
+    /// Quit callbacks are executed in the order they are added, stopping at the
+    /// first callback that returns `false`.
+    pub fn add_quit_callback<Args, F: Callback<Args, bool> + 'static>(
+        mut self,
+        callback: F,
+    ) -> Self {
+        F::assert_legal();
+        replace_with(&mut self.quit_callbacks, |cbs| {
+            Box::new(move |args| unsafe { cbs(args) && callback.call(args) })
+        });
+        self
+    }
+
+    /// Adds a [`Plugin`] to the application.
+    ///
+    /// Any state and callbacks registered by the plugin are added in place, as
+    /// if they were added directly at this point of the method chain.
+    pub fn add_plugin(self, plugin: impl Plugin) -> Self {
+        plugin.build(self)
+    }
+
+    /// Runs the application, executing any setup callbacks, opening a window,
+    /// executing any init callbacks, and starting the event loop.
+    ///
+    /// Returns why the application stopped once the event loop ends, or an
+    /// error if the window or graphics context could not be created.
+    pub fn run(self) -> Result<ExitReason, RunError> {
+        backend::run(self.setup(), None)
+    }
+
+    /// Runs the application like [`run`](Self::run), but with a hidden window,
+    /// quitting after the given number of frames, e.g. for automated tests or
+    /// generating images on a server.
+    ///
+    /// No input is processed, so [`Input`] and [`Gamepads`] report nothing
+    /// unless changed by the callbacks themselves. Each frame advances
+    /// [`Time`] by exactly one frame at the [target frame
+    /// rate](Self::with_target_fps), or at 60 frames per second if there is
+    /// none, regardless of how long it actually took. Use
+    /// [`Graphics::render_to_image`] to read back rendered images.
+    ///
+    /// An OpenGL context is still required. On machines without a display,
+    /// e.g. in CI, a virtual display such as Xvfb can provide one, or the
+    /// `null-backend` feature can replace rendering entirely, as described in
+    /// the documentation of the `graphics::null` module.
+    pub fn run_headless(self, frames: u32) -> Result<ExitReason, RunError> {
+        backend::run(self.setup(), Some(frames))
+    }
+
+    /// Executes the setup callbacks.
+    fn setup(mut self) -> Self {
+        // Setup callbacks may add further setup callbacks.
+        while !self.setup_callbacks.is_empty() {
+            for callback in std::mem::take(&mut self.setup_callbacks) {
+                self = callback(self);
+            }
+        }
+        self
+    }
+
+    fn run_frame_callbacks(&mut self) {
+        // SAFETY: We are guaranteed to have `Callbacks`
+        let callbacks = unsafe { self.state.get_mut::<Callbacks>().unwrap_unchecked() };
+        for (id, change) in callbacks.changes.drain(..) {
+            if let Some(index) = self.frame_callbacks.iter().position(|cb| cb.id == id) {
+                match change {
+                    CallbackChange::Enable => self.frame_callbacks[index].enabled = true,
+                    CallbackChange::Disable => self.frame_callbacks[index].enabled = false,
+                    CallbackChange::Remove => {
+                        self.frame_callbacks.remove(index);
+                    }
+                }
+            }
+        }
+
+        // SAFETY: We are guaranteed to have `Graphics`
+        unsafe { self.state.get_mut::<Graphics>().unwrap_unchecked() }.begin();
+
+        let frame_callbacks = &self.frame_callbacks;
+        let state = &mut self.state;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            for cb in frame_callbacks {
+                if cb.enabled {
+                    (cb.callback)(state);
+                }
+            }
+        }));
+
+        // End drawing even if a callback panicked, so that no draws are left
+        // pending.
+        // SAFETY: We are guaranteed to have `Graphics`
+        unsafe { self.state.get_mut::<Graphics>().unwrap_unchecked() }.end();
+
+        if let Err(payload) = result {
+            panic::resume_unwind(payload);
+        }
+    }
+
+    /// Returns true if quitting was requested through [`Window::request_quit`]
+    /// and not cancelled by the quit callbacks. A cancelled request is
+    /// cleared.
+    fn quit_requested(&mut self) -> bool {
+        // SAFETY: We are guaranteed to have `Window`
+        let window_state = unsafe { self.state.get_mut::<Window>().unwrap_unchecked() };
+        if !window_state.quit_requested {
+            return false;
+        }
+        if (self.quit_callbacks.as_ref())(&mut self.state) {
+            return true;
+        }
+        // SAFETY: We are guaranteed to have `Window`
+        let window_state = unsafe { self.state.get_mut::<Window>().unwrap_unchecked() };
+        window_state.quit_requested = false;
+        false
     }
 
-    fn init(&mut self, ctx: &Rc<Context>, resource_manager: &ResourceManager) {
+    fn init(&mut self, ctx: &Rc<Context>, gl: Gl, resource_manager: &ResourceManager) {
         self.state.insert(resource_manager.clone());
-        self.state.insert(Graphics::new(ctx, resource_manager));
+        let graphics = Graphics::new(ctx, gl, resource_manager);
+        graphics.set_srgb(self.srgb);
+        self.state.insert(graphics);
         self.state.insert(Input::new());
+        self.state.insert(Gamepads::new());
+        self.state
+            .insert(Time::new(Duration::from_secs_f64(1. / self.fixed_timestep)));
+        self.state.insert(Window::new());
+        self.state.insert(WindowEvents::new());
+        self.state.insert(Callbacks::new());
 
         let mut assets = Assets::new(resource_manager);
 
         #[cfg(feature = "png-decoder")]
         {
             let ctx = ctx.clone();
-            assets.add_loader(["png"], move |bytes, _| {
-                let (meta, data) = png_decoder::decode(bytes).unwrap();
-                Sprite::new(
+            let premultiply = self.premultiply;
+            assets.add_loader_fallible(["png"], move |bytes, _| {
+                let (meta, mut data) =
+                    png_decoder::decode(bytes).map_err(|e| format!("{:?}", e))?;
+                if premultiply {
+                    crate::graphics::premultiply_alpha(&mut data);
+                }
+                Ok(Sprite::new(
                     &ctx,
                     meta.width,
                     meta.height,
@@ -327,7 +944,49 @@ impl App {
                     fugu::ImageFilter::Nearest,
                     fugu::ImageWrap::Clamp,
                     data,
-                )
+                ))
+            });
+        }
+
+        #[cfg(feature = "jpeg")]
+        {
+            let ctx = ctx.clone();
+            assets.add_loader_fallible(["jpg", "jpeg"], move |bytes, _| {
+                let mut decoder = jpeg_decoder::Decoder::new(bytes);
+                let pixels = decoder.decode()?;
+                let info = decoder.info().ok_or("missing image info")?;
+                // JPEGs are always opaque, so there is no need to premultiply
+                let data: Vec<u8> = match info.pixel_format {
+                    jpeg_decoder::PixelFormat::L8 => {
+                        pixels.iter().flat_map(|&l| [l, l, l, 255]).collect()
+                    }
+                    jpeg_decoder::PixelFormat::RGB24 => pixels
+                        .chunks_exact(3)
+                        .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+                        .collect(),
+                    format => return Err(format!("unsupported pixel format {:?}", format).into()),
+                };
+                Ok(Sprite::new(
+                    &ctx,
+                    info.width as u32,
+                    info.height as u32,
+                    fugu::ImageFormat::Rgba8,
+                    fugu::ImageFilter::Nearest,
+                    fugu::ImageWrap::Clamp,
+                    data,
+                ))
+            });
+        }
+
+        #[cfg(feature = "aseprite")]
+        {
+            use crate::aseprite::AsepriteDoc;
+
+            let ctx = ctx.clone();
+            let resource_manager = resource_manager.clone();
+            let premultiply = self.premultiply;
+            assets.add_loader_fallible(["aseprite", "ase"], move |bytes, _| {
+                AsepriteDoc::load(&ctx, &resource_manager, bytes, premultiply)
             });
         }
 
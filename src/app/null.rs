@@ -0,0 +1,100 @@
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::assets::{Assets, ResourceManager};
+use crate::graphics::{Context, Gl, Graphics};
+use crate::input::{Gamepads, Input};
+use crate::time::Time;
+use crate::window::{Window, WindowEvents};
+use crate::{App, ExitReason, RunError};
+
+/// Runs the application without a window or graphics context. If `frames` is
+/// given, the application quits after that many frames.
+///
+/// No input is processed, and each frame advances time by exactly one frame at
+/// the target frame rate, like a headless run of the SDL backend.
+pub fn run(mut app: App, frames: Option<u32>) -> Result<ExitReason, RunError> {
+    let ctx = Rc::new(Context::new());
+    let resource_manager = ResourceManager::new();
+
+    app.init(&ctx, Gl::new(), &resource_manager);
+
+    if app.quit_requested() {
+        return Ok(ExitReason::Quit);
+    }
+
+    // SAFETY: We are guaranteed to have `Graphics`
+    let graphics = unsafe { app.state.get_mut::<Graphics>().unwrap_unchecked() };
+    graphics.set_viewport(app.size);
+
+    let frame_delta = Duration::from_secs_f64(
+        1. / if app.target_fps > 0 {
+            app.target_fps as f64
+        } else {
+            60.
+        },
+    );
+    let mut frame_time = Instant::now();
+    let mut frame = 0;
+
+    loop {
+        if frames == Some(frame) {
+            return Ok(ExitReason::Frames);
+        }
+        frame += 1;
+
+        {
+            // SAFETY: We are guaranteed to have `Input`
+            let input = unsafe { app.state.get_mut::<Input>().unwrap_unchecked() };
+            input.update();
+
+            // SAFETY: We are guaranteed to have `Gamepads`
+            let gamepads = unsafe { app.state.get_mut::<Gamepads>().unwrap_unchecked() };
+            gamepads.update();
+
+            // SAFETY: We are guaranteed to have `Assets`
+            let assets = unsafe { app.state.get_mut::<Assets>().unwrap_unchecked() };
+            assets.update();
+
+            // SAFETY: We are guaranteed to have `WindowEvents`
+            let window_events = unsafe { app.state.get_mut::<WindowEvents>().unwrap_unchecked() };
+            window_events.update();
+        }
+
+        {
+            // SAFETY: We are guaranteed to have `Time`
+            let time = unsafe { app.state.get_mut::<Time>().unwrap_unchecked() };
+            frame_time += frame_delta;
+            time.update(frame_time);
+            let fixed_steps = time.take_fixed_steps();
+
+            // SAFETY: We are guaranteed to have `Gamepads`
+            let gamepads = unsafe { app.state.get_mut::<Gamepads>().unwrap_unchecked() };
+            // SAFETY: We are guaranteed to have `Input`
+            let input = unsafe { app.state.get_mut::<Input>().unwrap_unchecked() };
+            gamepads.update_sticks(time.delta(), input);
+
+            // SAFETY: We are guaranteed to have `Graphics`
+            let graphics = unsafe { app.state.get_mut::<Graphics>().unwrap_unchecked() };
+            graphics.begin_frame();
+
+            for _ in 0..fixed_steps {
+                (app.fixed_callbacks.as_ref())(&mut app.state);
+            }
+        }
+
+        app.run_frame_callbacks();
+
+        if app.quit_requested() {
+            return Ok(ExitReason::Quit);
+        }
+
+        // SAFETY: We are guaranteed to have `Window`
+        let window_state = unsafe { app.state.get_mut::<Window>().unwrap_unchecked() };
+        if let Some(position) = window_state.mouse_warp.take() {
+            // SAFETY: We are guaranteed to have `Input`
+            let input = unsafe { app.state.get_mut::<Input>().unwrap_unchecked() };
+            input.mouse_position = position;
+        }
+    }
+}
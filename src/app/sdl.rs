@@ -1,104 +1,442 @@
 use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use fugu::Context;
+use sdl2::controller::{Axis as SDLAxis, Button as SDLButton};
 use sdl2::event::{Event, WindowEvent};
 use sdl2::keyboard::Keycode as SDLKeyCode;
+use sdl2::mouse::MouseButton as SDLMouseButton;
 use sdl2::video::GLProfile;
 
 use crate::assets::{Assets, ResourceManager};
-use crate::graphics::Graphics;
-use crate::input::{Input, KeyCode};
-use crate::App;
+use crate::graphics::{Context, Gl, Graphics};
+use crate::input::{GamepadAxis, GamepadButton, Gamepads, Input, KeyCode, MouseButton, Touch};
+use crate::time::{self, Time};
+use crate::window::{Window, WindowEvents};
+use crate::{App, ExitReason, GlProfile, RunError};
 
-pub fn run(mut app: App) {
-    let sdl_context = sdl2::init().unwrap();
-    let video_subsystem = sdl_context.video().unwrap();
+/// Runs the application. If `frames` is given, the window is hidden, no input
+/// is processed, and the application quits after that many frames.
+pub fn run(mut app: App, frames: Option<u32>) -> Result<ExitReason, RunError> {
+    let headless = frames.is_some();
+
+    let sdl_context = sdl2::init().map_err(RunError::new)?;
+    let video_subsystem = sdl_context.video().map_err(RunError::new)?;
+
+    video_subsystem
+        .gl_attr()
+        .set_framebuffer_srgb_compatible(app.srgb);
 
     let mut window_builder = video_subsystem.window(&app.title, app.size.0, app.size.1);
 
-    window_builder.opengl();
+    window_builder.opengl().allow_highdpi();
+
+    if headless {
+        window_builder.hidden();
+    }
 
-    if app.resizable {
+    if app.resizable && !headless {
         window_builder.resizable();
     }
 
-    let window = window_builder.build().unwrap();
+    let mut window = window_builder.build().map_err(RunError::new)?;
 
-    video_subsystem.gl_set_swap_interval(app.vsync as i32).ok();
+    video_subsystem
+        .gl_set_swap_interval((app.vsync && !headless) as i32)
+        .ok();
     let gl_attr = video_subsystem.gl_attr();
-    gl_attr.set_context_version(3, 3);
-    gl_attr.set_context_profile(GLProfile::Core);
+    let (major, minor, profile) = app.gl_version;
+    gl_attr.set_context_version(major, minor);
+    gl_attr.set_context_profile(match profile {
+        GlProfile::Core => GLProfile::Core,
+        GlProfile::Compatibility => GLProfile::Compatibility,
+        GlProfile::Es => GLProfile::GLES,
+    });
 
-    let _gl = window.gl_create_context().unwrap();
+    let _gl = window.gl_create_context().map_err(RunError::new)?;
     let ctx = Rc::new(Context::new(|s| {
         video_subsystem.gl_get_proc_address(s).cast()
     }));
+    let gl = Gl::load(|s| video_subsystem.gl_get_proc_address(s).cast());
 
-    let mut event_pump = sdl_context.event_pump().unwrap();
+    let timer_subsystem = sdl_context.timer().map_err(RunError::new)?;
+    let controller_subsystem = sdl_context.game_controller().map_err(RunError::new)?;
+    let mut controllers = Vec::new();
+    let mut event_pump = sdl_context.event_pump().map_err(RunError::new)?;
 
     let resource_manager = ResourceManager::new();
 
-    app.init(&ctx, &resource_manager);
+    app.init(&ctx, gl, &resource_manager);
 
+    if app.quit_requested() {
+        return Ok(ExitReason::Quit);
+    }
+
+    let mut drawable_size = window.drawable_size();
+    let mut scale_factor = drawable_size.0 as f32 / window.size().0.max(1) as f32;
     {
         // SAFETY: We are guaranteed to have `Graphics`
         let graphics = unsafe { app.state.get_mut::<Graphics>().unwrap_unchecked() };
-        graphics.set_viewport((app.size.0, app.size.1));
+        graphics.set_viewport(drawable_size);
+
+        // SAFETY: We are guaranteed to have `Window`
+        let window_state = unsafe { app.state.get_mut::<Window>().unwrap_unchecked() };
+        window_state.scale_factor = scale_factor;
     }
 
-    'running: loop {
+    let mut minimized = false;
+    // The cursor visibility, grab, and relative mode currently applied.
+    let mut cursor_state = (true, false, false);
+    // The position the mouse was last warped to, in window coordinates, until
+    // the motion event the warp generates has been received.
+    let mut warp_target = None;
+    let mut next_frame = Instant::now();
+    // Headless frames advance time by a fixed amount, so that runs are
+    // reproducible.
+    let frame_delta = Duration::from_secs_f64(
+        1. / if app.target_fps > 0 {
+            app.target_fps as f64
+        } else {
+            60.
+        },
+    );
+    let mut frame_time = Instant::now();
+    let mut frame = 0;
+
+    let reason = 'running: loop {
+        if frames == Some(frame) {
+            break 'running ExitReason::Frames;
+        }
+        frame += 1;
+
         {
             // SAFETY: We are guaranteed to have `Input`
             let input = unsafe { app.state.get_mut::<Input>().unwrap_unchecked() };
             input.update();
 
+            // SAFETY: We are guaranteed to have `Gamepads`
+            let gamepads = unsafe { app.state.get_mut::<Gamepads>().unwrap_unchecked() };
+            gamepads.update();
+
             // SAFETY: We are guaranteed to have `Assets`
             let assets = unsafe { app.state.get_mut::<Assets>().unwrap_unchecked() };
             assets.update();
 
+            // SAFETY: We are guaranteed to have `WindowEvents`
+            let window_events = unsafe { app.state.get_mut::<WindowEvents>().unwrap_unchecked() };
+            window_events.update();
+
+            // Check the drawable size before handling events, so that mouse
+            // positions are already scaled by the new scale factor. This also
+            // catches the window moving to a screen with a different scale,
+            // which doesn't resize it.
+            event_pump.pump_events();
+            let (w, h) = window.drawable_size();
+            if (w, h) != drawable_size && w > 0 && h > 0 {
+                drawable_size = (w, h);
+                scale_factor = w as f32 / window.size().0.max(1) as f32;
+
+                // SAFETY: We are guaranteed to have `Graphics`
+                let graphics = unsafe { app.state.get_mut::<Graphics>().unwrap_unchecked() };
+                graphics.set_viewport(drawable_size);
+                window_events.resize(w, h);
+
+                // SAFETY: We are guaranteed to have `Window`
+                let window_state = unsafe { app.state.get_mut::<Window>().unwrap_unchecked() };
+                window_state.scale_factor = scale_factor;
+            }
+
             for event in event_pump.poll_iter() {
+                if headless {
+                    continue;
+                }
                 match event {
-                    Event::Quit { .. } => break 'running,
+                    Event::Quit { .. } => window_events.request_close(),
                     Event::Window {
-                        win_event: WindowEvent::Resized(w, h),
+                        win_event: WindowEvent::Minimized,
                         ..
-                    } => {
-                        // SAFETY: We are guaranteed to have `Graphics`
-                        let graphics =
-                            unsafe { app.state.get_mut::<Graphics>().unwrap_unchecked() };
-                        graphics.set_viewport((w as u32, h as u32));
-                    }
+                    } => minimized = true,
+                    Event::Window {
+                        win_event: WindowEvent::Restored | WindowEvent::Maximized,
+                        ..
+                    } => minimized = false,
                     Event::KeyDown {
-                        keycode, repeat, ..
-                    } => {
-                        if let (Some(key), false) = (convert_keycode(keycode), repeat) {
-                            if !input.keys_down.contains(&key) {
-                                input.keys_down.push(key);
-                            }
-                            if !input.keys_pressed.contains(&key) {
-                                input.keys_pressed.push(key);
-                            }
+                        timestamp,
+                        keycode,
+                        repeat,
+                        ..
+                    } => match (convert_keycode(keycode), repeat) {
+                        (Some(key), false) => {
+                            input.press_key(key, Duration::from_millis(timestamp as u64));
                         }
-                    }
+                        (Some(key), true) => input.repeat_key(key),
+                        (None, _) => {}
+                    },
                     Event::KeyUp {
-                        keycode, repeat, ..
+                        timestamp,
+                        keycode,
+                        repeat,
+                        ..
                     } => {
                         if let (Some(key), false) = (convert_keycode(keycode), repeat) {
-                            input.keys_down.retain(|&k| k != key);
-                            input.keys_released.push(key);
+                            input.release_key(key, Duration::from_millis(timestamp as u64));
                         }
                     }
                     Event::TextInput { text, .. } => {
                         input.chars_pressed.extend(text.chars());
                     }
+                    Event::MouseMotion {
+                        x, y, xrel, yrel, ..
+                    } => {
+                        // Warping the mouse generates a motion event to the
+                        // warp target, which must not count as the mouse
+                        // moving.
+                        if warp_target == Some((x, y)) {
+                            warp_target = None;
+                        } else {
+                            input.mouse_delta.0 += xrel as f32 * scale_factor;
+                            input.mouse_delta.1 += yrel as f32 * scale_factor;
+                        }
+                        if !cursor_state.2 {
+                            input.mouse_position =
+                                (x as f32 * scale_factor, y as f32 * scale_factor);
+                        }
+                    }
+                    Event::MouseButtonDown {
+                        mouse_btn, x, y, ..
+                    } => {
+                        input.mouse_position = (x as f32 * scale_factor, y as f32 * scale_factor);
+                        if let Some(button) = convert_mouse_button(mouse_btn) {
+                            input.press_mouse(button);
+                        }
+                    }
+                    Event::MouseButtonUp {
+                        mouse_btn, x, y, ..
+                    } => {
+                        input.mouse_position = (x as f32 * scale_factor, y as f32 * scale_factor);
+                        if let Some(button) = convert_mouse_button(mouse_btn) {
+                            input.release_mouse(button);
+                        }
+                    }
+                    Event::ControllerDeviceAdded { which, .. } => {
+                        if let Ok(controller) = controller_subsystem.open(which) {
+                            gamepads.connect(controller.instance_id());
+                            controllers.push(controller);
+                        }
+                    }
+                    Event::ControllerDeviceRemoved { which, .. } => {
+                        gamepads.disconnect(which);
+                        controllers.retain(|e| e.instance_id() != which);
+                    }
+                    Event::ControllerButtonDown { which, button, .. } => {
+                        if let (Some(gamepad), Some(button)) =
+                            (gamepads.get_mut(which), convert_button(button))
+                        {
+                            gamepad.press_button(button);
+                        }
+                    }
+                    Event::ControllerButtonUp { which, button, .. } => {
+                        if let (Some(gamepad), Some(button)) =
+                            (gamepads.get_mut(which), convert_button(button))
+                        {
+                            gamepad.release_button(button);
+                        }
+                    }
+                    Event::ControllerAxisMotion {
+                        which, axis, value, ..
+                    } => {
+                        if let Some(gamepad) = gamepads.get_mut(which) {
+                            let (axis, value) = convert_axis(axis, value);
+                            gamepad.set_axis(axis, value);
+                        }
+                    }
+                    Event::FingerDown {
+                        finger_id,
+                        x,
+                        y,
+                        pressure,
+                        ..
+                    }
+                    | Event::FingerMotion {
+                        finger_id,
+                        x,
+                        y,
+                        pressure,
+                        ..
+                    } => {
+                        let (w, h) = drawable_size;
+                        let touch = Touch {
+                            id: finger_id,
+                            position: (x * w as f32, y * h as f32),
+                            normalized_position: (x, y),
+                            pressure,
+                        };
+                        if let Some(t) = input.touches.iter_mut().find(|t| t.id == finger_id) {
+                            *t = touch;
+                        } else {
+                            input.touches.push(touch);
+                        }
+                    }
+                    Event::FingerUp { finger_id, .. } => {
+                        input.touches.retain(|t| t.id != finger_id);
+                    }
                     _ => {}
                 }
             }
+            // Sampled after polling, so that all events received this frame
+            // occurred before it.
+            input.frame_timestamp = Duration::from_millis(timer_subsystem.ticks() as u64);
+        }
+
+        let (w, h) = window.drawable_size();
+        if !headless && (minimized || w == 0 || h == 0) {
+            if close_requested(&mut app) {
+                break 'running ExitReason::Closed;
+            }
+            if app.quit_requested() {
+                break 'running ExitReason::Quit;
+            }
+            // Avoid spinning while there is nothing to draw to
+            thread::sleep(Duration::from_millis(10));
+            continue;
+        }
+
+        {
+            // SAFETY: We are guaranteed to have `Time`
+            let time = unsafe { app.state.get_mut::<Time>().unwrap_unchecked() };
+            if headless {
+                frame_time += frame_delta;
+                time.update(frame_time);
+            } else {
+                time.update(Instant::now());
+            }
+            let fixed_steps = time.take_fixed_steps();
+
+            // SAFETY: We are guaranteed to have `Gamepads`
+            let gamepads = unsafe { app.state.get_mut::<Gamepads>().unwrap_unchecked() };
+            // SAFETY: We are guaranteed to have `Input`
+            let input = unsafe { app.state.get_mut::<Input>().unwrap_unchecked() };
+            gamepads.update_sticks(time.delta(), input);
+
+            // SAFETY: We are guaranteed to have `Graphics`
+            let graphics = unsafe { app.state.get_mut::<Graphics>().unwrap_unchecked() };
+            graphics.begin_frame();
+
+            for _ in 0..fixed_steps {
+                (app.fixed_callbacks.as_ref())(&mut app.state);
+            }
+        }
+
+        app.run_frame_callbacks();
+
+        if close_requested(&mut app) {
+            break 'running ExitReason::Closed;
+        }
+        if app.quit_requested() {
+            break 'running ExitReason::Quit;
+        }
+
+        {
+            // SAFETY: We are guaranteed to have `Window`
+            let window_state = unsafe { app.state.get_mut::<Window>().unwrap_unchecked() };
+            if let Some((x, y)) = window_state.mouse_warp.take() {
+                let target = ((x / scale_factor) as i32, (y / scale_factor) as i32);
+                sdl_context
+                    .mouse()
+                    .warp_mouse_in_window(&window, target.0, target.1);
+                warp_target = Some(target);
+                // SAFETY: We are guaranteed to have `Input`
+                let input = unsafe { app.state.get_mut::<Input>().unwrap_unchecked() };
+                input.mouse_position = (x, y);
+            }
+
+            let (visible, grabbed, relative) = (
+                window_state.cursor_visible,
+                window_state.cursor_grabbed,
+                window_state.relative_mouse,
+            );
+            if visible != cursor_state.0 {
+                sdl_context.mouse().show_cursor(visible);
+            }
+            if grabbed != cursor_state.1 {
+                window.set_grab(grabbed);
+            }
+            if relative != cursor_state.2 {
+                sdl_context.mouse().set_relative_mouse_mode(relative);
+            }
+            cursor_state = (visible, grabbed, relative);
         }
 
-        (app.frame_callbacks.as_ref())(&mut app.state);
+        if headless {
+            continue;
+        }
 
         window.gl_swap_window();
+
+        if app.target_fps > 0 {
+            next_frame += Duration::from_secs_f64(1. / app.target_fps as f64);
+            let now = Instant::now();
+            if next_frame > now {
+                time::sleep_until(next_frame);
+            } else {
+                // Don't try to catch up after falling behind.
+                next_frame = now;
+            }
+        }
+    };
+
+    Ok(reason)
+}
+
+/// Returns true if closing the window was requested and neither vetoed by the
+/// frame callbacks nor cancelled by the quit callbacks.
+fn close_requested(app: &mut App) -> bool {
+    // SAFETY: We are guaranteed to have `WindowEvents`
+    let window_events = unsafe { app.state.get_mut::<WindowEvents>().unwrap_unchecked() };
+    window_events.close_requested() && (app.quit_callbacks.as_ref())(&mut app.state)
+}
+
+fn convert_button(button: SDLButton) -> Option<GamepadButton> {
+    match button {
+        SDLButton::A => Some(GamepadButton::A),
+        SDLButton::B => Some(GamepadButton::B),
+        SDLButton::X => Some(GamepadButton::X),
+        SDLButton::Y => Some(GamepadButton::Y),
+        SDLButton::Back => Some(GamepadButton::Back),
+        SDLButton::Guide => Some(GamepadButton::Guide),
+        SDLButton::Start => Some(GamepadButton::Start),
+        SDLButton::LeftStick => Some(GamepadButton::LeftStick),
+        SDLButton::RightStick => Some(GamepadButton::RightStick),
+        SDLButton::LeftShoulder => Some(GamepadButton::LeftShoulder),
+        SDLButton::RightShoulder => Some(GamepadButton::RightShoulder),
+        SDLButton::DPadUp => Some(GamepadButton::DPadUp),
+        SDLButton::DPadDown => Some(GamepadButton::DPadDown),
+        SDLButton::DPadLeft => Some(GamepadButton::DPadLeft),
+        SDLButton::DPadRight => Some(GamepadButton::DPadRight),
+        _ => None,
+    }
+}
+
+fn convert_axis(axis: SDLAxis, value: i16) -> (GamepadAxis, f32) {
+    // Sticks range over the full `i16` range, while triggers only range over
+    // the positive half.
+    let value = (value as f32 / i16::MAX as f32).max(-1.);
+    match axis {
+        SDLAxis::LeftX => (GamepadAxis::LeftX, value),
+        SDLAxis::LeftY => (GamepadAxis::LeftY, value),
+        SDLAxis::RightX => (GamepadAxis::RightX, value),
+        SDLAxis::RightY => (GamepadAxis::RightY, value),
+        SDLAxis::TriggerLeft => (GamepadAxis::LeftTrigger, value),
+        SDLAxis::TriggerRight => (GamepadAxis::RightTrigger, value),
+    }
+}
+
+fn convert_mouse_button(button: SDLMouseButton) -> Option<MouseButton> {
+    match button {
+        SDLMouseButton::Left => Some(MouseButton::Left),
+        SDLMouseButton::Middle => Some(MouseButton::Middle),
+        SDLMouseButton::Right => Some(MouseButton::Right),
+        _ => None,
     }
 }
 
@@ -173,11 +511,52 @@ fn convert_keycode(keycode: Option<SDLKeyCode>) -> Option<KeyCode> {
         SDLKeyCode::Kp8 => Some(KeyCode::Num8),
         SDLKeyCode::Kp9 => Some(KeyCode::Num9),
 
+        SDLKeyCode::KpPlus => Some(KeyCode::NumAdd),
+        SDLKeyCode::KpMinus => Some(KeyCode::NumSubtract),
+        SDLKeyCode::KpMultiply => Some(KeyCode::NumMultiply),
+        SDLKeyCode::KpDivide => Some(KeyCode::NumDivide),
+        SDLKeyCode::KpPeriod => Some(KeyCode::NumDecimal),
+        SDLKeyCode::KpEnter => Some(KeyCode::NumEnter),
+
         SDLKeyCode::Left => Some(KeyCode::Left),
         SDLKeyCode::Right => Some(KeyCode::Right),
         SDLKeyCode::Up => Some(KeyCode::Up),
         SDLKeyCode::Down => Some(KeyCode::Down),
 
+        SDLKeyCode::F1 => Some(KeyCode::F1),
+        SDLKeyCode::F2 => Some(KeyCode::F2),
+        SDLKeyCode::F3 => Some(KeyCode::F3),
+        SDLKeyCode::F4 => Some(KeyCode::F4),
+        SDLKeyCode::F5 => Some(KeyCode::F5),
+        SDLKeyCode::F6 => Some(KeyCode::F6),
+        SDLKeyCode::F7 => Some(KeyCode::F7),
+        SDLKeyCode::F8 => Some(KeyCode::F8),
+        SDLKeyCode::F9 => Some(KeyCode::F9),
+        SDLKeyCode::F10 => Some(KeyCode::F10),
+        SDLKeyCode::F11 => Some(KeyCode::F11),
+        SDLKeyCode::F12 => Some(KeyCode::F12),
+
+        SDLKeyCode::Minus => Some(KeyCode::Minus),
+        SDLKeyCode::Equals => Some(KeyCode::Equals),
+        SDLKeyCode::LeftBracket => Some(KeyCode::LeftBracket),
+        SDLKeyCode::RightBracket => Some(KeyCode::RightBracket),
+        SDLKeyCode::Semicolon => Some(KeyCode::Semicolon),
+        SDLKeyCode::Quote => Some(KeyCode::Apostrophe),
+        SDLKeyCode::Comma => Some(KeyCode::Comma),
+        SDLKeyCode::Period => Some(KeyCode::Period),
+        SDLKeyCode::Slash => Some(KeyCode::Slash),
+        SDLKeyCode::Backslash => Some(KeyCode::Backslash),
+        SDLKeyCode::Backquote => Some(KeyCode::Grave),
+
+        SDLKeyCode::CapsLock => Some(KeyCode::CapsLock),
+        SDLKeyCode::NumLockClear => Some(KeyCode::NumLock),
+        SDLKeyCode::ScrollLock => Some(KeyCode::ScrollLock),
+        SDLKeyCode::PrintScreen => Some(KeyCode::PrintScreen),
+        SDLKeyCode::Pause => Some(KeyCode::Pause),
+        SDLKeyCode::LGui => Some(KeyCode::LeftSuper),
+        SDLKeyCode::RGui => Some(KeyCode::RightSuper),
+        SDLKeyCode::Application => Some(KeyCode::Menu),
+
         _ => None,
     })
 }
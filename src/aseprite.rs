@@ -0,0 +1,170 @@
+//! Types related to loading Aseprite files.
+
+use std::error::Error;
+
+use asefile::AsepriteFile;
+use fugu::{ImageFilter, ImageFormat, ImageWrap};
+
+use crate::assets::{ResourceHandle, ResourceManager};
+use crate::graphics::{Context, Rect, Sprite};
+
+/// An Aseprite document, with all of its frames packed into a single
+/// [`Sprite`].
+///
+/// Aseprite documents are loaded from `.aseprite` and `.ase` files. Draw a
+/// frame by passing its [`rect`](AsepriteFrame::rect) as the source rectangle
+/// to [`draw_sprite`](crate::graphics::Graphics::draw_sprite).
+pub struct AsepriteDoc {
+    sprite: ResourceHandle<Sprite>,
+    frames: Vec<AsepriteFrame>,
+    tags: Vec<AsepriteTag>,
+}
+
+/// A single frame of an [`AsepriteDoc`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AsepriteFrame {
+    /// The region of the document's sprite containing the frame, in pixels.
+    pub rect: Rect,
+    /// How long the frame is shown for, in seconds.
+    pub duration: f32,
+}
+
+/// A named range of frames of an [`AsepriteDoc`], usually representing an
+/// animation.
+#[derive(Clone, PartialEq, Debug)]
+pub struct AsepriteTag {
+    /// The name of the tag.
+    pub name: String,
+    /// The index of the first frame of the tag.
+    pub from: usize,
+    /// The index of the last frame of the tag, inclusive.
+    pub to: usize,
+    /// The direction in which the frames of the tag are played.
+    pub direction: AnimationDirection,
+}
+
+/// The direction in which the frames of an [`AsepriteTag`] are played.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AnimationDirection {
+    /// From the first frame to the last.
+    Forward,
+    /// From the last frame to the first.
+    Reverse,
+    /// From the first frame to the last and back again.
+    PingPong,
+}
+
+impl AsepriteDoc {
+    pub(crate) fn load(
+        ctx: &Context,
+        resource_manager: &ResourceManager,
+        bytes: &[u8],
+        premultiply: bool,
+    ) -> Result<Self, Box<dyn Error>> {
+        let file = AsepriteFile::read(bytes).map_err(|e| e.to_string())?;
+        let (width, height) = (file.width() as u32, file.height() as u32);
+        let num_frames = file.num_frames();
+
+        // Pack the frames into a roughly square grid.
+        let cols = (num_frames as f32).sqrt().ceil().max(1.) as u32;
+        let rows = ((num_frames + cols - 1) / cols).max(1);
+        let stride = (cols * width) as usize * 4;
+        let mut data = vec![0; stride * (rows * height) as usize];
+        let mut frames = Vec::with_capacity(num_frames as usize);
+        for i in 0..num_frames {
+            let frame = file.frame(i);
+            let (x, y) = (i % cols * width, i / cols * height);
+            let image = frame.image().into_raw();
+            let row_len = width as usize * 4;
+            for (row, pixels) in image.chunks_exact(row_len).enumerate() {
+                let start = (y as usize + row) * stride + x as usize * 4;
+                data[start..start + row_len].copy_from_slice(pixels);
+            }
+            frames.push(AsepriteFrame {
+                rect: Rect::new(x as f32, y as f32, width as f32, height as f32),
+                duration: frame.duration() as f32 / 1000.,
+            });
+        }
+
+        let tags = (0..file.num_tags())
+            .map(|i| {
+                let tag = file.tag(i);
+                AsepriteTag {
+                    name: tag.name().to_owned(),
+                    from: tag.from_frame() as usize,
+                    to: tag.to_frame() as usize,
+                    direction: match tag.animation_direction() {
+                        asefile::AnimationDirection::Forward => AnimationDirection::Forward,
+                        asefile::AnimationDirection::Reverse => AnimationDirection::Reverse,
+                        asefile::AnimationDirection::PingPong => AnimationDirection::PingPong,
+                    },
+                }
+            })
+            .collect();
+
+        if premultiply {
+            crate::graphics::premultiply_alpha(&mut data);
+        }
+
+        let sprite = resource_manager.allocate();
+        resource_manager.set(
+            sprite,
+            Sprite::new(
+                ctx,
+                cols * width,
+                rows * height,
+                ImageFormat::Rgba8,
+                ImageFilter::Nearest,
+                ImageWrap::Clamp,
+                data,
+            ),
+        );
+
+        Ok(Self {
+            sprite,
+            frames,
+            tags,
+        })
+    }
+
+    /// Returns the sprite containing all frames of the document.
+    pub fn sprite(&self) -> ResourceHandle<Sprite> {
+        self.sprite
+    }
+
+    /// Returns the frames of the document.
+    pub fn frames(&self) -> &[AsepriteFrame] {
+        &self.frames
+    }
+
+    /// Returns the frame with the given index or `None` if it does not exist.
+    pub fn frame(&self, index: usize) -> Option<&AsepriteFrame> {
+        self.frames.get(index)
+    }
+
+    /// Returns the tags of the document.
+    pub fn tags(&self) -> &[AsepriteTag] {
+        &self.tags
+    }
+
+    /// Returns the first tag with the given name or `None` if it does not
+    /// exist.
+    pub fn tag(&self, name: &str) -> Option<&AsepriteTag> {
+        self.tags.iter().find(|tag| tag.name == name)
+    }
+
+    /// Returns the frames of the given tag in the order they are played, not
+    /// repeating the first and last frames for ping-pong tags.
+    pub fn tag_frames(&self, tag: &AsepriteTag) -> Vec<AsepriteFrame> {
+        let frames = self.frames.get(tag.from..=tag.to).unwrap_or(&[]);
+        match tag.direction {
+            AnimationDirection::Forward => frames.to_vec(),
+            AnimationDirection::Reverse => frames.iter().rev().copied().collect(),
+            AnimationDirection::PingPong => {
+                let back = frames.iter().rev().skip(1);
+                let back = back.take(frames.len().saturating_sub(2));
+                frames.iter().chain(back).copied().collect()
+            }
+        }
+    }
+}
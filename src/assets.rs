@@ -28,7 +28,8 @@ use std::any::{Any, TypeId};
 use std::borrow::Cow;
 use std::cell::{RefCell, UnsafeCell};
 use std::collections::BTreeMap;
-use std::fmt::Debug;
+use std::error::Error;
+use std::fmt::{self, Debug};
 use std::hash::{BuildHasher, Hash, Hasher};
 use std::marker::PhantomData;
 use std::num::NonZeroU64;
@@ -40,6 +41,7 @@ use hashbrown::HashMap;
 
 use self::fs::ThreadedFileSystem;
 use crate::experimental::{FileSystem, FileTask};
+use crate::graphics::{grid_cell, Sprite};
 
 pub mod fs;
 
@@ -59,6 +61,10 @@ pub mod fs;
 #[derive(Clone)]
 pub struct ResourceManager {
     storage: Rc<RefCell<BTreeMap<(TypeId, u64), Resource>>>,
+    spares: Rc<RefCell<HashMap<TypeId, Vec<Resource>>>>,
+    /// The last index allocated for each type. Indices are never reused, so
+    /// that handles to freed resources never refer to newer ones.
+    last_idx: Rc<RefCell<HashMap<TypeId, u64>>>,
 }
 
 /// A handle to a resource of type `T`.
@@ -116,6 +122,18 @@ fn transmute_handle<T, U>(handle: ResourceHandle<T>) -> ResourceHandle<U> {
     }
 }
 
+/// The borrow state of a resource. Returned by
+/// [`ResourceManager::borrow_state`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BorrowState {
+    /// The resource is not borrowed.
+    Free,
+    /// The resource is immutably borrowed the given number of times.
+    Shared(usize),
+    /// The resource is mutably borrowed.
+    Mut,
+}
+
 #[derive(Clone)]
 struct Resource(Rc<UnsafeCell<dyn Any>>);
 
@@ -168,6 +186,17 @@ impl Resource {
         }
     }
 
+    fn borrow_state(&self) -> BorrowState {
+        // SAFETY: The flag is only read, and nothing else can access it at the
+        // same time since resources are not shared across threads.
+        let flag = unsafe { *(self.0.get() as *const isize) };
+        match flag {
+            0 => BorrowState::Free,
+            x if x < 0 => BorrowState::Mut,
+            x => BorrowState::Shared(x as usize),
+        }
+    }
+
     unsafe fn downcast_ref<T>(&self) -> &T {
         let inner = self.0.get() as *const dyn Any as *const ResourceInner<T>;
         &(*inner).data
@@ -277,13 +306,31 @@ impl<T> Drop for ResourceRefMut<T> {
     }
 }
 
+const FREED: &str = "resource has been freed";
+
 impl ResourceManager {
     pub(crate) fn new() -> Self {
         Self {
             storage: Rc::new(RefCell::new(BTreeMap::new())),
+            spares: Rc::new(RefCell::new(HashMap::new())),
+            last_idx: Rc::new(RefCell::new(HashMap::new())),
         }
     }
 
+    /// Reserves space for at least `additional` more resources of the given
+    /// type to be allocated without further memory allocations for the
+    /// resources themselves.
+    ///
+    /// This is useful when a large number of resources of the same type are
+    /// allocated at once, e.g. during startup. Note that the bookkeeping used
+    /// to look up resources may still allocate.
+    pub fn reserve<T: 'static>(&self, additional: usize) {
+        let mut spares = self.spares.borrow_mut();
+        let spares = spares.entry(TypeId::of::<T>()).or_default();
+        let missing = additional.saturating_sub(spares.len());
+        spares.extend((0..missing).map(|_| Resource::new::<Option<T>>(None)));
+    }
+
     /// Allocates and returns a new [`ResourceHandle`] for the given type.
     ///
     /// See the [module-level documentation] for more information.
@@ -294,14 +341,20 @@ impl ResourceManager {
         let type_id = TypeId::of::<T>();
         let mut storage = self.storage.borrow_mut();
 
-        // Find the greatest index and add 1 (or use 1 if there is no index).
-        let idx = storage
-            .keys()
-            .rfind(|k| k.0 == type_id)
-            .map(|e| e.1 + 1)
-            .unwrap_or(1);
+        let idx = {
+            let mut last_idx = self.last_idx.borrow_mut();
+            let idx = last_idx.entry(type_id).or_insert(0);
+            *idx += 1;
+            *idx
+        };
 
-        storage.insert((type_id, idx), Resource::new::<Option<T>>(None));
+        let resource = self
+            .spares
+            .borrow_mut()
+            .get_mut(&type_id)
+            .and_then(Vec::pop)
+            .unwrap_or_else(|| Resource::new::<Option<T>>(None));
+        storage.insert((type_id, idx), resource);
 
         // SAFETY: idx cannot be zero.
         ResourceHandle {
@@ -310,32 +363,167 @@ impl ResourceManager {
         }
     }
 
+    /// Allocates a new [`ResourceHandle`] for the given type and immediately
+    /// sets its underlying resource to the value returned by `init`.
+    ///
+    /// Unlike with [`allocate`](Self::allocate), getting the resource succeeds
+    /// right away.
+    #[must_use]
+    pub fn allocate_with<T>(&self, init: impl FnOnce() -> T) -> ResourceHandle<T> {
+        // Initialize first, so that `init` may use the manager itself.
+        let data = init();
+        let handle = self.allocate();
+        self.set(handle, data);
+        handle
+    }
+
     /// Sets the underlying resource corresponding to the given
     /// [`ResourceHandle`].
     ///
     /// See the [module-level documentation] for more information.
     ///
+    /// # Panics
+    ///
+    /// Panics if the given resource has been [freed](Self::free).
+    ///
     /// [module-level documentation]: self
     pub fn set<T>(&self, handle: ResourceHandle<T>, data: T) {
         let type_id = TypeId::of::<T>();
         let mut storage = self.storage.borrow_mut();
         unsafe {
-            // SAFETY: We know everything exists and the type is correct.
-            let resource = storage
-                .get_mut(&(type_id, handle.idx.get()))
-                .unwrap_unchecked();
+            // SAFETY: We know the type is correct.
+            let resource = storage.get_mut(&(type_id, handle.idx.get())).expect(FREED);
             resource.lock();
             *resource.downcast_mut::<Option<T>>() = Some(data);
             resource.unlock();
         }
     }
 
+    /// Frees the underlying resource corresponding to the given
+    /// [`ResourceHandle`], dropping its data.
+    ///
+    /// The handle and any copies of it must not be used afterwards, as doing
+    /// so panics. Indices of freed handles are never reused, so they never
+    /// refer to a newly allocated resource. To only drop the data while
+    /// keeping the handle valid, see [`Assets::unload`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the given resource is currently borrowed or has already been
+    /// freed.
+    pub fn free<T: 'static>(&self, handle: ResourceHandle<T>) {
+        let type_id = TypeId::of::<T>();
+        let resource = {
+            let mut storage = self.storage.borrow_mut();
+            let key = (type_id, handle.idx.get());
+            if storage.get(&key).expect(FREED).borrow_state() != BorrowState::Free {
+                panic!("cannot free resource; currently borrowed");
+            }
+            storage.remove(&key).unwrap()
+        };
+        // SAFETY: We know the type is correct and the resource is not borrowed.
+        // The data is dropped outside of the storage borrow in case its drop
+        // implementation uses the resource manager.
+        let data = unsafe { resource.downcast_mut::<Option<T>>().take() };
+        drop(data);
+        self.spares
+            .borrow_mut()
+            .entry(type_id)
+            .or_default()
+            .push(resource);
+    }
+
+    /// Clears the underlying resource corresponding to the given
+    /// [`ResourceHandle`], returning its data, so that getting it returns
+    /// `None` as if it was just allocated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the given resource is currently borrowed or has been freed.
+    pub(crate) fn clear<T: 'static>(&self, handle: ResourceHandle<T>) -> Option<T> {
+        let type_id = TypeId::of::<T>();
+        let storage = self.storage.borrow();
+        let resource = storage.get(&(type_id, handle.idx.get())).expect(FREED);
+        if resource.borrow_state() != BorrowState::Free {
+            panic!("cannot clear resource; currently borrowed");
+        }
+        // SAFETY: We know the type is correct and the resource is not borrowed.
+        unsafe { resource.downcast_mut::<Option<T>>().take() }
+    }
+
+    /// Returns the current [`BorrowState`] of the underlying resource
+    /// corresponding to the given [`ResourceHandle`], without borrowing it.
+    ///
+    /// This makes it possible to check whether [`get`] or [`get_mut`] would
+    /// panic beforehand. Note that the returned state is only a snapshot: it
+    /// stays accurate until the next time the resource is borrowed or a
+    /// borrow is released, which can only happen on the current thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the given resource has been [freed](Self::free).
+    ///
+    /// [`get`]: Self::get
+    /// [`get_mut`]: Self::get_mut
+    pub fn borrow_state<T: 'static>(&self, handle: ResourceHandle<T>) -> BorrowState {
+        let type_id = TypeId::of::<T>();
+        let storage = self.storage.borrow();
+        storage
+            .get(&(type_id, handle.idx.get()))
+            .expect(FREED)
+            .borrow_state()
+    }
+
+    /// Returns the [`ResourceHandle`]s of all allocated resources of the given
+    /// type, e.g. for a debug view of loaded assets.
+    ///
+    /// This includes resources that have not been loaded yet; see
+    /// [`is_loaded`](Self::is_loaded). The resources themselves are not
+    /// borrowed, and resources allocated or freed while iterating do not
+    /// affect the returned iterator.
+    pub fn iter<T: 'static>(&self) -> impl Iterator<Item = ResourceHandle<T>> {
+        let type_id = TypeId::of::<T>();
+        let storage = self.storage.borrow();
+        let handles: Vec<_> = storage
+            .range((type_id, 1)..=(type_id, u64::MAX))
+            // SAFETY: Resources are never stored at index `0`.
+            .map(|(&(_, idx), _)| ResourceHandle {
+                idx: unsafe { NonZeroU64::new_unchecked(idx) },
+                _marker: PhantomData,
+            })
+            .collect();
+        handles.into_iter()
+    }
+
+    /// Returns whether the underlying resource corresponding to the given
+    /// [`ResourceHandle`] has been loaded, without borrowing it.
+    ///
+    /// Unlike [`get`](Self::get), this never panics, neither for resources
+    /// that are currently borrowed nor for ones that have been
+    /// [freed](Self::free), which are not loaded.
+    pub fn is_loaded<T: 'static>(&self, handle: ResourceHandle<T>) -> bool {
+        let type_id = TypeId::of::<T>();
+        let storage = self.storage.borrow();
+        let inner = match storage.get(&(type_id, handle.idx.get())) {
+            Some(inner) => inner,
+            None => return false,
+        };
+        match inner.borrow_state() {
+            // Only loaded resources can be borrowed mutably.
+            BorrowState::Mut => true,
+            // SAFETY: We know the type is correct, and the resource is not
+            // mutably borrowed, so it can be read.
+            _ => unsafe { inner.downcast_ref::<Option<T>>().is_some() },
+        }
+    }
+
     /// Immutably borrows the underlying resource correspoding to the given
     /// [`ResourceHandle`].
     ///
     /// # Panics
     ///
-    /// Panics if the given resource is currently mutably borrowed.
+    /// Panics if the given resource is currently mutably borrowed or has been
+    /// [freed](Self::free).
     ///
     /// See the [module-level documentation] for more information.
     ///
@@ -344,9 +532,7 @@ impl ResourceManager {
         let type_id = TypeId::of::<T>();
         unsafe {
             let mut inner = self.storage.borrow_mut();
-            let inner = inner
-                .get_mut(&(type_id, handle.idx.get()))
-                .unwrap_unchecked();
+            let inner = inner.get_mut(&(type_id, handle.idx.get())).expect(FREED);
             inner.lock();
             if inner.downcast_ref::<Option<T>>().is_none() {
                 inner.unlock();
@@ -362,7 +548,8 @@ impl ResourceManager {
     ///
     /// # Panics
     ///
-    /// Panics if the given resource is currently borrowed.
+    /// Panics if the given resource is currently borrowed or has been
+    /// [freed](Self::free).
     ///
     /// See the [module-level documentation] for more information.
     ///
@@ -371,9 +558,7 @@ impl ResourceManager {
         let type_id = TypeId::of::<T>();
         unsafe {
             let mut inner = self.storage.borrow_mut();
-            let inner = inner
-                .get_mut(&(type_id, handle.idx.get()))
-                .unwrap_unchecked();
+            let inner = inner.get_mut(&(type_id, handle.idx.get())).expect(FREED);
             inner.lock_mut();
             if inner.downcast_ref::<Option<T>>().is_none() {
                 inner.unlock_mut();
@@ -399,12 +584,159 @@ pub struct Assets {
     resource_manager: ResourceManager,
     fs: Box<dyn FileSystem>,
     fs_init: bool,
-    loaders: HashMap<(TypeId, Cow<'static, str>), Option<Loader>>,
+    loaders: HashMap<(TypeId, Cow<'static, str>), Loader>,
     handles: HashMap<(TypeId, Cow<'static, str>), ResourceHandle<()>>,
-    tasks: Vec<Option<FileTaskResolve>>,
+    tasks: Vec<FileTaskResolve>,
+    /// The number of files queued since the queue of file tasks was last
+    /// empty, for reporting progress.
+    batch_size: usize,
+    ordered: bool,
+    pending: Vec<PendingAsset>,
+    errors: HashMap<(TypeId, NonZeroU64), LoadError>,
 }
 
-type Loader = Rc<dyn Fn(&[u8], &mut Assets, TypeId, NonZeroU64)>;
+/// An error that occurred while loading an asset. Returned by
+/// [`Assets::try_load`], [`Assets::load_error`], and [`Assets::wait_all`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[non_exhaustive]
+pub enum LoadError {
+    /// The file at the given path does not exist or could not be read.
+    NotFound {
+        /// The path of the asset.
+        path: String,
+    },
+    /// No loader is registered for the requested type and file extension.
+    NoLoader {
+        /// The name of the requested type.
+        type_name: &'static str,
+        /// The extension of the file.
+        extension: String,
+    },
+    /// The loader failed to load the asset.
+    LoaderFailed {
+        /// The path of the asset.
+        path: String,
+        /// A description of the failure.
+        message: String,
+    },
+    /// An asset that the asset depends on failed to load.
+    DependencyFailed {
+        /// The error of the dependency.
+        error: Box<LoadError>,
+    },
+    /// Assets that were waited for can never finish loading, because they
+    /// wait for dependencies that are not being loaded, e.g. because the
+    /// assets depend on each other.
+    Unresolved {
+        /// The number of assets that cannot finish loading.
+        count: usize,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::NotFound { path } => write!(f, "failed to read asset {}", path),
+            LoadError::NoLoader {
+                type_name,
+                extension,
+            } => write!(
+                f,
+                "no loader for type {} and extension \"{}\"",
+                type_name, extension
+            ),
+            LoadError::LoaderFailed { path, message } => {
+                write!(f, "failed to load asset {}: {}", path, message)
+            }
+            LoadError::DependencyFailed { error } => {
+                write!(f, "failed to load dependency: {}", error)
+            }
+            LoadError::Unresolved { count } => {
+                write!(f, "{} assets wait for dependencies that never load", count)
+            }
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+/// The loading state of an asset. Returned by [`Assets::load_state`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum LoadState {
+    /// The asset is still loading.
+    Pending,
+    /// The asset has finished loading.
+    Loaded,
+    /// The asset failed to load.
+    Failed(LoadError),
+}
+
+/// Calls the loader for an asset, returning an error message if it failed.
+type Loader = Rc<dyn Fn(&[u8], &mut Assets, NonZeroU64) -> Result<(), String>>;
+
+/// An asset that a [`PendingAsset`] is waiting for.
+struct Dependency {
+    /// The type and index of the asset.
+    key: (TypeId, NonZeroU64),
+    is_loaded: Box<dyn Fn(&ResourceManager) -> bool>,
+}
+
+impl Dependency {
+    fn new<T: 'static>(handle: ResourceHandle<T>) -> Self {
+        Self {
+            key: (TypeId::of::<T>(), handle.idx),
+            is_loaded: Box::new(move |resource_manager| resource_manager.is_loaded(handle)),
+        }
+    }
+}
+
+/// A loaded asset waiting for its dependencies to finish loading.
+struct PendingAsset {
+    /// The types and indices of the resources that are set once the asset
+    /// finishes. They are cancelled or fail to load together.
+    keys: Vec<(TypeId, NonZeroU64)>,
+    dependencies: Vec<Dependency>,
+    finish: Box<dyn FnOnce(&ResourceManager)>,
+}
+
+/// Context passed to asset loaders registered through
+/// [`add_loader`](Assets::add_loader).
+///
+/// Loaders can load other assets that the asset being loaded depends on
+/// through [`load_dependency`]. Like any other asset, dependencies may finish
+/// loading at any point after the loader returns, so the asset being loaded is
+/// only stored once all of its dependencies have finished loading. Until then,
+/// [`ResourceManager::get`] returns `None` for the asset, just like for any
+/// other asset that is still loading.
+///
+/// [`load_dependency`]: Self::load_dependency
+pub struct LoadContext<'a> {
+    assets: &'a mut Assets,
+    dependencies: Vec<Dependency>,
+}
+
+impl LoadContext<'_> {
+    /// Loads an asset that the asset being loaded depends on, returning its
+    /// [`ResourceHandle`].
+    ///
+    /// See [`Assets::load`] for more information.
+    pub fn load_dependency<T: 'static>(
+        &mut self,
+        path: impl Into<Cow<'static, str>>,
+    ) -> ResourceHandle<T> {
+        let handle = self.assets.load(path);
+        self.dependencies.push(Dependency::new(handle));
+        handle
+    }
+
+    /// Returns the [`Assets`] instance that is loading the asset.
+    ///
+    /// Assets loaded through the returned [`Assets`] are not treated as
+    /// dependencies.
+    pub fn assets(&mut self) -> &mut Assets {
+        self.assets
+    }
+}
 
 impl Assets {
     pub(crate) fn new(resource_manager: &ResourceManager) -> Self {
@@ -422,6 +754,10 @@ impl Assets {
             loaders: HashMap::new(),
             handles: HashMap::new(),
             tasks: Vec::new(),
+            batch_size: 0,
+            ordered: false,
+            pending: Vec::new(),
+            errors: HashMap::new(),
         }
     }
 
@@ -430,32 +766,80 @@ impl Assets {
     /// # Arguments
     ///
     /// * `extensions` - An array of file extensions to apply the loader to.
-    /// * `loader` - A closure that takes a byte slice and returns a value of
-    ///   type `T`.
+    /// * `loader` - A closure that takes a byte slice and a [`LoadContext`] and
+    ///   returns a value of type `T`.
     pub fn add_loader<T: 'static, const LEN: usize>(
         &mut self,
         extensions: [impl Into<Cow<'static, str>>; LEN],
-        loader: impl Fn(&[u8], &mut Assets) -> T + 'static,
+        loader: impl Fn(&[u8], &mut LoadContext) -> T + 'static,
     ) {
-        let loader: Loader = Rc::new(move |data, assets, type_id, idx| {
-            let val = loader(data, assets);
-            let mut storage = assets.resource_manager.storage.borrow_mut();
-            let resource = storage.get_mut(&(type_id, idx.get())).unwrap();
-            resource.lock();
-            // SAFETY: We know that the type is correct and we have the lock.
-            unsafe {
-                *resource.downcast_mut::<Option<T>>() = Some(val);
+        self.add_loader_fallible(extensions, move |data, ctx| Ok(loader(data, ctx)));
+    }
+
+    /// Registers a loader for the given type that may fail.
+    ///
+    /// If the loader returns an error, the asset fails to load with
+    /// [`LoadError::LoaderFailed`] instead of aborting the application. See
+    /// [`try_load`](Self::try_load) and [`load_state`](Self::load_state).
+    ///
+    /// # Arguments
+    ///
+    /// * `extensions` - An array of file extensions to apply the loader to.
+    /// * `loader` - A closure that takes a byte slice and a [`LoadContext`] and
+    ///   returns a value of type `T` or an error.
+    pub fn add_loader_fallible<T: 'static, const LEN: usize>(
+        &mut self,
+        extensions: [impl Into<Cow<'static, str>>; LEN],
+        loader: impl Fn(&[u8], &mut LoadContext) -> Result<T, Box<dyn Error>> + 'static,
+    ) {
+        let loader: Loader = Rc::new(move |data, assets, idx| {
+            let handle = ResourceHandle::<T> {
+                idx,
+                _marker: PhantomData,
+            };
+            let mut ctx = LoadContext {
+                assets,
+                dependencies: Vec::new(),
+            };
+            let val = loader(data, &mut ctx);
+            let LoadContext {
+                assets,
+                dependencies,
+            } = ctx;
+            let val = val.map_err(|e| e.to_string())?;
+            let resource_manager = &assets.resource_manager;
+            if dependencies.iter().all(|e| (e.is_loaded)(resource_manager)) {
+                resource_manager.set(handle, val);
+            } else {
+                assets.pending.push(PendingAsset {
+                    keys: vec![(TypeId::of::<T>(), idx)],
+                    dependencies,
+                    finish: Box::new(move |resource_manager| resource_manager.set(handle, val)),
+                });
             }
-            resource.unlock();
+            Ok(())
         });
         for extension in extensions {
-            self.loaders.insert(
-                (TypeId::of::<T>(), extension.into()),
-                Some(Rc::clone(&loader)),
-            );
+            self.loaders
+                .insert((TypeId::of::<T>(), extension.into()), Rc::clone(&loader));
         }
     }
 
+    /// Sets whether or not loaders should be called in the order the assets
+    /// were requested in.
+    ///
+    /// By default, the loader of each asset is called as soon as its file has
+    /// been read. Since files may be read concurrently, this order can differ
+    /// between runs. With this enabled, the loader of an asset is only called
+    /// once the loaders of all assets requested before it have been called,
+    /// which makes any side effects of loaders deterministic at the cost of
+    /// assets potentially finishing later.
+    ///
+    /// The default value is `false`.
+    pub fn set_ordered(&mut self, ordered: bool) {
+        self.ordered = ordered;
+    }
+
     /// Sets the file system to use for loading assets.
     pub fn set_fs(&mut self, fs: impl FileSystem + 'static) {
         self.fs = Box::new(fs);
@@ -471,27 +855,199 @@ impl Assets {
     /// returns, so you should gracefully handle cases where the asset is not
     /// loaded yet.
     ///
+    /// See [`try_load`](Self::try_load) for a version of this function that
+    /// does not panic.
+    ///
     /// # Panics
     ///
     /// Panics if no asset exists at the given path, the asset cannot be loaded
     /// successfully, or no loader matches the given file extension and type.
     pub fn load<T: 'static>(&mut self, path: impl Into<Cow<'static, str>>) -> ResourceHandle<T> {
+        match self.load_inner(path.into(), true, false) {
+            Ok(handle) => handle,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Returns a [`ResourceHandle`] of the given type representing the asset at
+    /// the given path, or an error if the asset cannot be loaded.
+    ///
+    /// This works like [`load`](Self::load), except that errors are returned
+    /// instead of causing a panic. Since assets are loaded asynchronously, an
+    /// error that occurs after this function returns is recorded instead and
+    /// can be retrieved through [`load_error`](Self::load_error). Loading an
+    /// asset that has failed to load before returns the recorded error.
+    pub fn try_load<T: 'static>(
+        &mut self,
+        path: impl Into<Cow<'static, str>>,
+    ) -> Result<ResourceHandle<T>, LoadError> {
+        self.load_inner(path.into(), false, false)
+    }
+
+    /// Returns a [`ResourceHandle`] of the given type representing the asset at
+    /// the given path, blocking until the asset has finished loading.
+    ///
+    /// This works like [`load`](Self::load), except that the asset and any
+    /// dependencies it loads are guaranteed to have finished loading by the
+    /// time this function returns, e.g. for assets needed on the first frame.
+    /// If the asset is already loading, this waits for it to finish. The
+    /// asset's loader is called right away even if loading is
+    /// [ordered](Self::set_ordered).
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`load`](Self::load), or if the
+    /// asset can never finish loading because it waits for dependencies that
+    /// are not being loaded, e.g. because they depend on the asset in turn.
+    pub fn load_blocking<T: 'static>(
+        &mut self,
+        path: impl Into<Cow<'static, str>>,
+    ) -> ResourceHandle<T> {
+        match self.load_inner(path.into(), true, true) {
+            Ok(handle) => handle,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Returns a [`ResourceHandle`] of the given type representing an asset
+    /// loaded from the given data, e.g. data embedded into the executable with
+    /// [`include_bytes!`].
+    ///
+    /// The file system is not used. Instead, the loader is selected by the
+    /// given file extension and called right away, so the asset has finished
+    /// loading by the time this function returns unless it is waiting for
+    /// dependencies. The given key takes the place of a path: like with
+    /// [`load`](Self::load), the loader is only called if no asset of the same
+    /// type has been loaded with the same key or path before.
+    ///
+    /// See [`try_load_bytes`](Self::try_load_bytes) for a version of this
+    /// function that does not panic.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the asset cannot be loaded successfully or no loader matches
+    /// the given file extension and type.
+    pub fn load_bytes<T: 'static>(
+        &mut self,
+        key: impl Into<Cow<'static, str>>,
+        extension: &str,
+        data: &'static [u8],
+    ) -> ResourceHandle<T> {
+        match self.try_load_bytes(key, extension, data) {
+            Ok(handle) => handle,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Returns a [`ResourceHandle`] of the given type representing an asset
+    /// loaded from the given data, or an error if the asset cannot be loaded.
+    ///
+    /// This works like [`load_bytes`](Self::load_bytes), except that errors
+    /// are returned instead of causing a panic. Like with
+    /// [`try_load`](Self::try_load), the error is also recorded, so it can be
+    /// retrieved through [`load_error`](Self::load_error), and loading the
+    /// same key again returns it.
+    pub fn try_load_bytes<T: 'static>(
+        &mut self,
+        key: impl Into<Cow<'static, str>>,
+        extension: &str,
+        data: &'static [u8],
+    ) -> Result<ResourceHandle<T>, LoadError> {
+        let type_id = TypeId::of::<T>();
+        let key = (type_id, key.into());
+        if let Some(&handle) = self.handles.get(&key) {
+            if let Some(error) = self.errors.get(&(type_id, handle.idx)) {
+                return Err(error.clone());
+            }
+            return Ok(transmute_handle(handle));
+        }
+
+        let loader = match self.loaders.get(&(type_id, Cow::Borrowed(extension))) {
+            Some(loader) => Rc::clone(loader),
+            None => {
+                return Err(LoadError::NoLoader {
+                    type_name: crate::util::type_name::<T>(),
+                    extension: extension.to_string(),
+                })
+            }
+        };
+        let handle = self.resource_manager.allocate::<T>();
+        self.handles.insert(key.clone(), transmute_handle(handle));
+        if let Err(message) = loader(data, self, handle.idx) {
+            let error = LoadError::LoaderFailed {
+                path: key.1.into_owned(),
+                message,
+            };
+            self.errors.insert((type_id, handle.idx), error.clone());
+            return Err(error);
+        }
+        Ok(handle)
+    }
+
+    /// Returns the error that occurred while loading the asset represented by
+    /// the given [`ResourceHandle`], if it failed to load.
+    ///
+    /// See [`try_load`](Self::try_load) for more information.
+    pub fn load_error<T: 'static>(&self, handle: ResourceHandle<T>) -> Option<&LoadError> {
+        self.errors.get(&(TypeId::of::<T>(), handle.idx))
+    }
+
+    /// Returns the loading state of the asset represented by the given
+    /// [`ResourceHandle`].
+    ///
+    /// This does not borrow the resource, so it can be called while the
+    /// resource is borrowed.
+    pub fn load_state<T: 'static>(&self, handle: ResourceHandle<T>) -> LoadState {
+        if let Some(error) = self.load_error(handle) {
+            LoadState::Failed(error.clone())
+        } else if self.resource_manager.is_loaded(handle) {
+            LoadState::Loaded
+        } else {
+            LoadState::Pending
+        }
+    }
+
+    fn load_inner<T: 'static>(
+        &mut self,
+        path: Cow<'static, str>,
+        strict: bool,
+        blocking: bool,
+    ) -> Result<ResourceHandle<T>, LoadError> {
         let type_id = TypeId::of::<T>();
-        let path: Cow<'static, str> = path.into();
 
         let mut hasher = self.handles.hasher().build_hasher();
         (type_id, &path).hash(&mut hasher);
         let hash = hasher.finish();
 
-        transmute_handle(
+        let handle = transmute_handle(
             if let Some((_, &handle)) = self
                 .handles
                 .raw_entry()
                 .from_hash(hash, |(a, b)| a == &type_id && b == &path)
             {
+                if let Some(error) = self.errors.get(&(type_id, handle.idx)) {
+                    return Err(error.clone());
+                }
+                if blocking {
+                    let key = (type_id, handle.idx);
+                    if let Some(i) = self.tasks.iter().position(|e| (e.type_id, e.idx) == key) {
+                        let task = self.tasks.remove(i);
+                        self.block_on(task);
+                    }
+                }
                 handle
             } else {
                 let p = Path::new(&*path);
+                let extension = p.extension().and_then(|e| e.to_str()).unwrap_or_default();
+                if !self
+                    .loaders
+                    .contains_key(&(type_id, Cow::Borrowed(extension)))
+                {
+                    return Err(LoadError::NoLoader {
+                        type_name: crate::util::type_name::<T>(),
+                        extension: extension.to_string(),
+                    });
+                }
                 let handle = self.resource_manager.allocate::<T>();
                 if !self.fs_init {
                     self.fs = Box::new(ThreadedFileSystem::new());
@@ -499,17 +1055,264 @@ impl Assets {
                 }
                 let mut task = FileTaskResolve {
                     task: self.fs.read(p),
+                    path: path.clone(),
                     type_id,
+                    type_name: crate::util::type_name::<T>(),
                     idx: handle.idx,
+                    complete: false,
+                    strict,
                 };
-                if !task.poll(self) {
-                    self.tasks.push(Some(task));
-                }
                 let handle = transmute_handle(handle);
                 self.handles.insert((type_id, path), handle);
+                if blocking {
+                    self.block_on(task);
+                } else if (!self.ordered || self.tasks.is_empty()) && task.poll() {
+                    task.resolve(self);
+                } else {
+                    if self.tasks.is_empty() {
+                        self.batch_size = 0;
+                    }
+                    self.batch_size += 1;
+                    self.tasks.push(task);
+                }
                 handle
             },
-        )
+        );
+
+        let key = (type_id, handle.idx);
+        if blocking {
+            // The asset may still be waiting for its dependencies.
+            while self.pending.iter().any(|e| e.keys.contains(&key)) {
+                self.update();
+                self.check_resolvable()?;
+                std::thread::yield_now();
+            }
+        }
+        match self.errors.get(&key) {
+            Some(error) => Err(error.clone()),
+            None => Ok(handle),
+        }
+    }
+
+    /// Waits for the given file task to complete and resolves it.
+    fn block_on(&mut self, mut task: FileTaskResolve) {
+        while !task.poll() {
+            std::thread::yield_now();
+        }
+        task.resolve(self);
+    }
+
+    /// Cancels loading the asset represented by the given [`ResourceHandle`],
+    /// returning true if it was still loading.
+    ///
+    /// The asset's loader is never called, and the handle never finishes
+    /// loading. Loading the same path again afterwards starts over with a new
+    /// handle. Any assets waiting for the asset to finish loading, such as
+    /// assets that loaded it through [`LoadContext::load_dependency`] or
+    /// sprites created by [`slice_grid`](Self::slice_grid), are cancelled as
+    /// well.
+    pub fn cancel<T: 'static>(&mut self, handle: ResourceHandle<T>) -> bool {
+        let key = (TypeId::of::<T>(), handle.idx);
+        let mut cancelled = Vec::new();
+        if let Some(i) = self.tasks.iter().position(|e| (e.type_id, e.idx) == key) {
+            self.tasks.remove(i).task.cancel();
+            cancelled.push(key);
+        }
+        if let Some(i) = self.pending.iter().position(|e| e.keys.contains(&key)) {
+            cancelled.extend(self.pending.remove(i).keys);
+        }
+
+        // Assets waiting for a cancelled asset can never finish, so cancel
+        // them as well.
+        let mut i = 0;
+        while i < cancelled.len() {
+            let key = cancelled[i];
+            let (dependents, pending): (Vec<_>, Vec<_>) = self
+                .pending
+                .drain(..)
+                .partition(|asset| asset.dependencies.iter().any(|e| e.key == key));
+            self.pending = pending;
+            for asset in dependents {
+                cancelled.extend(asset.keys);
+            }
+            i += 1;
+        }
+
+        self.handles
+            .retain(|(type_id, _), handle| !cancelled.contains(&(*type_id, handle.idx)));
+        !cancelled.is_empty()
+    }
+
+    /// Unloads the asset represented by the given [`ResourceHandle`], dropping
+    /// its data and cancelling it if it is still loading.
+    ///
+    /// The handle stays valid, but getting its resource returns `None`
+    /// afterwards. Loading the same path again starts over with a new handle.
+    /// To also free the handle itself, pass it to [`ResourceManager::free`]
+    /// afterwards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the asset is currently borrowed.
+    pub fn unload<T: 'static>(&mut self, handle: ResourceHandle<T>) {
+        let key = (TypeId::of::<T>(), handle.idx);
+        self.cancel(handle);
+        self.handles
+            .retain(|(type_id, _), handle| (*type_id, handle.idx) != key);
+        self.errors.remove(&key);
+        self.resource_manager.clear(handle);
+    }
+
+    /// Cancels loading all assets that are still loading.
+    ///
+    /// See [`cancel`](Self::cancel) for more information.
+    pub fn cancel_all_pending(&mut self) {
+        let mut keys: Vec<_> = self
+            .pending
+            .iter()
+            .flat_map(|asset| asset.keys.iter().copied())
+            .collect();
+        for mut task in self.tasks.drain(..) {
+            task.task.cancel();
+            keys.push((task.type_id, task.idx));
+        }
+        self.pending.clear();
+        self.handles
+            .retain(|(type_id, _), handle| !keys.contains(&(*type_id, handle.idx)));
+    }
+
+    /// Slices the given sprite into a grid of `cols` by `rows` sub-sprites,
+    /// returning their [`ResourceHandle`]s in row-major order.
+    ///
+    /// The sub-sprites share the underlying image of the given sprite, so they
+    /// can be drawn without a source rectangle. If the given sprite has not
+    /// finished loading yet, the sub-sprites are created once it has, or fail
+    /// to load if it fails to load. Any pixels left over when the size of the
+    /// sprite is not divisible by the number of columns or rows are not
+    /// included in any sub-sprite.
+    ///
+    /// If `cols` or `rows` is zero, no sub-sprites are created.
+    ///
+    /// See also [`Sprite::sub_sprite`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of sub-sprites overflows a `u32`.
+    pub fn slice_grid(
+        &mut self,
+        handle: ResourceHandle<Sprite>,
+        cols: u32,
+        rows: u32,
+    ) -> Vec<ResourceHandle<Sprite>> {
+        if cols == 0 || rows == 0 {
+            return Vec::new();
+        }
+        let count = cols.checked_mul(rows).expect("too many sub-sprites");
+        let handles: Vec<_> = (0..count)
+            .map(|_| self.resource_manager.allocate::<Sprite>())
+            .collect();
+
+        let sub_handles = handles.clone();
+        let finish = move |resource_manager: &ResourceManager| {
+            // The sprite may have been freed while the sub-sprites were
+            // pending, in which case they are left unloaded.
+            let sprite = match resource_manager.get(handle) {
+                Some(sprite) => sprite,
+                None => return,
+            };
+            let width = sprite.width() / cols;
+            let height = sprite.height() / rows;
+            for (i, &sub_handle) in (0..).zip(&sub_handles) {
+                let (x, y) = grid_cell(i, cols, (width, height), 0, 0);
+                resource_manager.set(sub_handle, sprite.sub_sprite(x, y, width, height));
+            }
+        };
+
+        if self.resource_manager.is_loaded(handle) {
+            finish(&self.resource_manager);
+        } else {
+            self.pending.push(PendingAsset {
+                keys: handles
+                    .iter()
+                    .map(|handle| (TypeId::of::<Sprite>(), handle.idx))
+                    .collect(),
+                dependencies: vec![Dependency::new(handle)],
+                finish: Box::new(finish),
+            });
+        }
+
+        handles
+    }
+
+    /// Returns the overall fraction of the files of loading assets that have
+    /// been read so far, from `0.0` to `1.0`, or `None` if no files are being
+    /// read.
+    ///
+    /// Progress is tracked over all files queued since no files were last
+    /// being read, so it only ever increases until they have all been read.
+    /// Files that have been resolved count as read, and files of unknown
+    /// [`progress`](FileTask::progress) count as unread until they are
+    /// complete.
+    pub fn progress(&self) -> Option<f32> {
+        if self.tasks.is_empty() {
+            return None;
+        }
+        let resolved = self.batch_size.saturating_sub(self.tasks.len());
+        let sum: f32 = self
+            .tasks
+            .iter()
+            .map(|task| {
+                if task.complete {
+                    1.
+                } else {
+                    task.task.progress().unwrap_or(0.)
+                }
+            })
+            .sum();
+        Some((resolved as f32 + sum) / self.batch_size.max(self.tasks.len()) as f32)
+    }
+
+    /// Returns the number of assets that are still loading, i.e. whose files
+    /// are still being read or that are waiting for their dependencies.
+    pub fn pending_count(&self) -> usize {
+        self.tasks.len() + self.pending.len()
+    }
+
+    /// Returns whether all assets have finished loading, e.g. to leave a
+    /// loading screen.
+    pub fn is_idle(&self) -> bool {
+        self.tasks.is_empty() && self.pending.is_empty()
+    }
+
+    /// Blocks until all assets have finished loading.
+    ///
+    /// Returns [`LoadError::Unresolved`] instead of blocking forever if assets
+    /// wait for dependencies that never finish loading, e.g. because they
+    /// depend on each other or on a resource that is never set. These assets
+    /// remain pending.
+    pub fn wait_all(&mut self) -> Result<(), LoadError> {
+        loop {
+            self.update();
+            if self.is_idle() {
+                return Ok(());
+            }
+            self.check_resolvable()?;
+            std::thread::yield_now();
+        }
+    }
+
+    /// Returns an error if assets are waiting for dependencies after all files
+    /// have been read. [`update`](Self::update) finishes every asset that it
+    /// can, so after it, such assets can only finish once a resource is set
+    /// from outside, which cannot happen while blocking.
+    fn check_resolvable(&self) -> Result<(), LoadError> {
+        if self.tasks.is_empty() && !self.pending.is_empty() {
+            Err(LoadError::Unresolved {
+                count: self.pending.len(),
+            })
+        } else {
+            Ok(())
+        }
     }
 
     /// Updates any pending file loads. This is called internally at the start
@@ -517,33 +1320,329 @@ impl Assets {
     pub fn update(&mut self) {
         let mut i = 0;
         while i < self.tasks.len() {
-            let mut task = self.tasks[i].take().unwrap();
-            if task.poll(self) {
-                self.tasks.remove(i);
+            if self.tasks[i].poll() {
+                self.tasks.remove(i).resolve(self);
+            } else if self.ordered {
+                break;
             } else {
-                self.tasks[i] = Some(task);
                 i += 1;
             }
         }
+
+        // Finishing an asset may complete the dependencies of another, and an
+        // asset fails to load if one of its dependencies does, so keep going
+        // until no more assets can be finished or failed.
+        loop {
+            let errors = &self.errors;
+            let (failed, pending): (Vec<_>, Vec<_>) = self
+                .pending
+                .drain(..)
+                .partition(|e| e.dependencies.iter().any(|e| errors.contains_key(&e.key)));
+            let resource_manager = &self.resource_manager;
+            let (ready, pending): (Vec<_>, Vec<_>) = pending.into_iter().partition(|e| {
+                e.dependencies
+                    .iter()
+                    .all(|e| (e.is_loaded)(resource_manager))
+            });
+            self.pending = pending;
+            if failed.is_empty() && ready.is_empty() {
+                break;
+            }
+            for asset in failed {
+                // SAFETY: The asset was only failed because one of its
+                // dependencies has an error.
+                let error = unsafe {
+                    asset
+                        .dependencies
+                        .iter()
+                        .find_map(|e| self.errors.get(&e.key))
+                        .unwrap_unchecked()
+                };
+                let error = LoadError::DependencyFailed {
+                    error: Box::new(error.clone()),
+                };
+                for key in asset.keys {
+                    self.errors.insert(key, error.clone());
+                }
+            }
+            for asset in ready {
+                (asset.finish)(&self.resource_manager);
+            }
+        }
     }
 }
 
 struct FileTaskResolve {
     task: Box<dyn FileTask>,
+    path: Cow<'static, str>,
     type_id: TypeId,
+    type_name: &'static str,
     idx: NonZeroU64,
+    complete: bool,
+    /// Whether to panic instead of recording an error if loading fails.
+    strict: bool,
 }
 
 impl FileTaskResolve {
-    fn poll(&mut self, assets: &mut Assets) -> bool {
-        let complete = self.task.poll();
-        if complete {
-            let key = (self.type_id, self.task.extension().to_owned().into());
-            let loader = assets.loaders.get_mut(&key).unwrap();
-            let loader = loader.take().unwrap();
-            loader(self.task.data(), assets, self.type_id, self.idx);
-            *assets.loaders.get_mut(&key).unwrap() = Some(loader);
-        }
-        complete
+    fn poll(&mut self) -> bool {
+        if !self.complete {
+            self.complete = self.task.poll();
+        }
+        self.complete
+    }
+
+    fn resolve(self, assets: &mut Assets) {
+        let extension = self.task.extension();
+        let key = (self.type_id, extension.to_owned().into());
+        let error = if self.task.error().is_some() {
+            LoadError::NotFound {
+                path: self.path.to_string(),
+            }
+        } else if let Some(loader) = assets.loaders.get(&key) {
+            let loader = Rc::clone(loader);
+            match loader(self.task.data(), assets, self.idx) {
+                Ok(()) => return,
+                Err(message) => LoadError::LoaderFailed {
+                    path: self.path.to_string(),
+                    message,
+                },
+            }
+        } else {
+            LoadError::NoLoader {
+                type_name: self.type_name,
+                extension: extension.to_string(),
+            }
+        };
+        if self.strict {
+            panic!("{}", error);
+        }
+        assets.errors.insert((self.type_id, self.idx), error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// Files that have been read, or `None` for files that failed to read.
+    /// Reading any other file does not complete until it is added.
+    type Files = Rc<RefCell<HashMap<PathBuf, Option<Vec<u8>>>>>;
+
+    struct TestFileSystem(Files);
+
+    impl FileSystem for TestFileSystem {
+        fn read(&mut self, path: &Path) -> Box<dyn FileTask> {
+            Box::new(TestFileTask {
+                files: Rc::clone(&self.0),
+                path: path.to_owned(),
+                result: None,
+            })
+        }
+    }
+
+    struct TestFileTask {
+        files: Files,
+        path: PathBuf,
+        result: Option<Result<Vec<u8>, io::Error>>,
+    }
+
+    impl FileTask for TestFileTask {
+        fn poll(&mut self) -> bool {
+            if self.result.is_none() {
+                self.result = self.files.borrow().get(&self.path).map(|e| {
+                    e.clone()
+                        .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+                });
+            }
+            self.result.is_some()
+        }
+
+        fn data(&self) -> &[u8] {
+            match &self.result {
+                Some(Ok(data)) => data,
+                _ => &[],
+            }
+        }
+
+        fn extension(&self) -> &str {
+            self.path.extension().unwrap().to_str().unwrap()
+        }
+
+        fn error(&self) -> Option<&io::Error> {
+            self.result.as_ref().and_then(|e| e.as_ref().err())
+        }
+    }
+
+    /// Returns assets that load text files, which load each line as a
+    /// dependency.
+    fn assets() -> (Assets, Files) {
+        let files = Files::default();
+        let mut assets = Assets::new(&ResourceManager::new());
+        assets.set_fs(TestFileSystem(Rc::clone(&files)));
+        assets.add_loader(["txt"], |bytes, ctx| {
+            let text = String::from_utf8(bytes.to_vec()).unwrap();
+            for line in text.lines() {
+                ctx.load_dependency::<String>(line.to_owned());
+            }
+            text
+        });
+        assets.add_loader_fallible(["png"], |_, _| -> Result<Sprite, _> {
+            Err("no sprites without a graphics context".into())
+        });
+        (assets, files)
+    }
+
+    #[test]
+    fn progress_counts_resolved_files() {
+        let (mut assets, files) = assets();
+        assert_eq!(assets.progress(), None);
+        let paths = ["a.txt", "b.txt", "c.txt", "d.txt"];
+        for path in paths {
+            assets.load::<String>(path);
+        }
+        for (i, path) in paths.iter().enumerate() {
+            let progress = i as f32 / paths.len() as f32;
+            assert_eq!(assets.progress(), Some(progress));
+            files.borrow_mut().insert(path.into(), Some(Vec::new()));
+            assets.update();
+        }
+        assert_eq!(assets.progress(), None);
+
+        // The next batch starts over.
+        assets.load::<String>("e.txt");
+        assert_eq!(assets.progress(), Some(0.));
+    }
+
+    #[test]
+    fn freed_indices_are_not_reused() {
+        let resource_manager = ResourceManager::new();
+        let a = resource_manager.allocate_with(|| 1);
+        let b = resource_manager.allocate_with(|| 2);
+        resource_manager.free(b);
+        let c = resource_manager.allocate_with(|| 3);
+        assert_ne!(b, c);
+        resource_manager.free(a);
+        resource_manager.free(c);
+        let d = resource_manager.allocate_with(|| 4);
+        assert!(![a, b, c].contains(&d));
+    }
+
+    #[test]
+    fn freed_resources_are_not_loaded() {
+        let resource_manager = ResourceManager::new();
+        let a = resource_manager.allocate_with(|| 1);
+        assert!(resource_manager.is_loaded(a));
+        resource_manager.free(a);
+        assert!(!resource_manager.is_loaded(a));
+    }
+
+    #[test]
+    fn failed_bytes_are_recorded() {
+        let (mut assets, _) = assets();
+        let error = assets
+            .try_load_bytes::<Sprite>("sprite", "png", &[])
+            .unwrap_err();
+        assert!(matches!(error, LoadError::LoaderFailed { .. }));
+        let key = (TypeId::of::<Sprite>(), Cow::Borrowed("sprite"));
+        let handle: ResourceHandle<Sprite> = transmute_handle(assets.handles[&key]);
+        assert_eq!(assets.load_state(handle), LoadState::Failed(error.clone()));
+        assert_eq!(
+            assets.try_load_bytes::<Sprite>("sprite", "png", &[]),
+            Err(error)
+        );
+        assert!(matches!(
+            assets.try_load_bytes::<Sprite>("other", "bmp", &[]),
+            Err(LoadError::NoLoader { .. })
+        ));
+    }
+
+    #[test]
+    fn load_state_of_borrowed_asset() {
+        let (mut assets, files) = assets();
+        files.borrow_mut().insert("a.txt".into(), Some(Vec::new()));
+        let a = assets.load::<String>("a.txt");
+        let resource_manager = assets.resource_manager.clone();
+        let _borrow = resource_manager.get_mut(a);
+        assert_eq!(assets.load_state(a), LoadState::Loaded);
+    }
+
+    #[test]
+    fn cancel_cascades_to_dependents() {
+        let (mut assets, files) = assets();
+        files
+            .borrow_mut()
+            .insert("a.txt".into(), Some(b"b.txt".to_vec()));
+        let a = assets.load::<String>("a.txt");
+        let b = assets.load::<String>("b.txt");
+        let sprite = assets.load::<Sprite>("sprite.png");
+        assets.slice_grid(sprite, 2, 1);
+        assert_eq!(assets.pending_count(), 4);
+
+        assert!(assets.cancel(b));
+        assert!(assets.cancel(sprite));
+        assert!(assets.is_idle());
+        assert_eq!(assets.wait_all(), Ok(()));
+        assert_eq!(assets.load_state(a), LoadState::Pending);
+        assert_ne!(assets.load::<String>("a.txt"), a);
+    }
+
+    #[test]
+    fn dependency_cycles_are_unresolved() {
+        let (mut assets, files) = assets();
+        files
+            .borrow_mut()
+            .insert("a.txt".into(), Some(b"b.txt".to_vec()));
+        files
+            .borrow_mut()
+            .insert("b.txt".into(), Some(b"a.txt".to_vec()));
+        let a = assets.load::<String>("a.txt");
+
+        assert_eq!(assets.wait_all(), Err(LoadError::Unresolved { count: 2 }));
+        assert_eq!(assets.load_state(a), LoadState::Pending);
+
+        // Blocking on an asset that depends on the cycle does not hang either.
+        files
+            .borrow_mut()
+            .insert("c.txt".into(), Some(b"a.txt".to_vec()));
+        assert_eq!(
+            assets.load_inner::<String>("c.txt".into(), false, true),
+            Err(LoadError::Unresolved { count: 3 })
+        );
+    }
+
+    #[test]
+    fn failed_dependencies_fail_dependents() {
+        let (mut assets, files) = assets();
+        files
+            .borrow_mut()
+            .insert("a.txt".into(), Some(b"b.txt".to_vec()));
+        let b = assets.try_load::<String>("b.txt").unwrap();
+        let a = assets.load::<String>("a.txt");
+        let sprite = assets.try_load::<Sprite>("sprite.png").unwrap();
+        let sub_sprites = assets.slice_grid(sprite, 2, 1);
+
+        files.borrow_mut().insert("b.txt".into(), None);
+        files.borrow_mut().insert("sprite.png".into(), None);
+        assert_eq!(assets.wait_all(), Ok(()));
+
+        let not_found = |path: &str| LoadError::NotFound { path: path.into() };
+        assert_eq!(assets.load_state(b), LoadState::Failed(not_found("b.txt")));
+        assert_eq!(
+            assets.load_state(a),
+            LoadState::Failed(LoadError::DependencyFailed {
+                error: Box::new(not_found("b.txt")),
+            })
+        );
+        for sub_sprite in sub_sprites {
+            assert_eq!(
+                assets.load_state(sub_sprite),
+                LoadState::Failed(LoadError::DependencyFailed {
+                    error: Box::new(not_found("sprite.png")),
+                })
+            );
+        }
     }
 }
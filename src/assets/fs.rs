@@ -1,16 +1,30 @@
 //! File system abstractions.
 
 use std::fs::File as StdFile;
-use std::io::Read;
+use std::io::{self, Read};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
 use std::thread::JoinHandle;
 
 use crate::experimental::{FileSystem, FileTask};
 
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The state shared between a [`BasicFileTask`] and its thread.
+#[derive(Default)]
+struct TaskState {
+    cancelled: AtomicBool,
+    bytes_read: AtomicU64,
+    total: AtomicU64,
+}
+
 struct BasicFileTask {
-    thread: Option<JoinHandle<Vec<u8>>>,
+    thread: Option<JoinHandle<io::Result<Vec<u8>>>>,
     buffer: Vec<u8>,
+    error: Option<io::Error>,
     ext: String,
+    state: Arc<TaskState>,
 }
 
 impl BasicFileTask {
@@ -19,17 +33,63 @@ impl BasicFileTask {
             .extension()
             .map(|e| e.to_str().unwrap().to_string())
             .unwrap_or_default();
+        Self::spawn(ext, move |state| {
+            let mut std = StdFile::open(path)?;
+            let len = std.metadata().map(|e| e.len()).unwrap_or(0);
+            state.total.store(len, Ordering::Relaxed);
+
+            // Read in chunks so that progress can be reported along the way.
+            let mut buffer = Vec::with_capacity(len as usize);
+            let mut chunk = vec![0; CHUNK_SIZE];
+            loop {
+                if state.cancelled.load(Ordering::Relaxed) {
+                    return Ok(Vec::new());
+                }
+                let n = std.read(&mut chunk)?;
+                if n == 0 {
+                    break;
+                }
+                buffer.extend_from_slice(&chunk[..n]);
+                state
+                    .bytes_read
+                    .store(buffer.len() as u64, Ordering::Relaxed);
+            }
+            Ok(buffer)
+        })
+    }
+
+    /// Creates a task that produces the data of a file with the given
+    /// extension by calling the given function in a different thread.
+    fn spawn(
+        ext: String,
+        read: impl FnOnce(&TaskState) -> io::Result<Vec<u8>> + Send + 'static,
+    ) -> Self {
+        let state = Arc::new(TaskState::default());
+        let thread_state = Arc::clone(&state);
         let thread = Some(std::thread::spawn(move || {
-            let mut std = StdFile::open(path).unwrap();
-            let mut buffer = Vec::new();
-            std.read_to_end(&mut buffer).unwrap();
-            buffer
+            if thread_state.cancelled.load(Ordering::Relaxed) {
+                return Ok(Vec::new());
+            }
+            read(&thread_state)
         }));
-        let buffer = Vec::new();
         BasicFileTask {
             thread,
-            buffer,
+            buffer: Vec::new(),
+            error: None,
             ext,
+            state,
+        }
+    }
+
+    /// Creates a task that has already failed with the given error.
+    #[cfg(feature = "zip")]
+    fn failed(ext: String, error: io::Error) -> Self {
+        BasicFileTask {
+            thread: None,
+            buffer: Vec::new(),
+            error: Some(error),
+            ext,
+            state: Arc::new(TaskState::default()),
         }
     }
 }
@@ -39,7 +99,10 @@ impl FileTask for BasicFileTask {
         if self.thread.is_none() {
             true
         } else if self.thread.as_ref().unwrap().is_finished() {
-            self.buffer = self.thread.take().unwrap().join().unwrap();
+            match self.thread.take().unwrap().join().unwrap() {
+                Ok(buffer) => self.buffer = buffer,
+                Err(error) => self.error = Some(error),
+            }
             true
         } else {
             false
@@ -53,6 +116,26 @@ impl FileTask for BasicFileTask {
     fn extension(&self) -> &str {
         &self.ext
     }
+
+    fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    fn progress(&self) -> Option<f32> {
+        if self.thread.is_none() {
+            return Some(1.);
+        }
+        let total = self.state.total.load(Ordering::Relaxed);
+        if total == 0 {
+            return None;
+        }
+        let bytes_read = self.state.bytes_read.load(Ordering::Relaxed);
+        Some((bytes_read as f64 / total as f64).min(1.) as f32)
+    }
+
+    fn cancel(&mut self) {
+        self.state.cancelled.store(true, Ordering::Relaxed);
+    }
 }
 
 /// A file system that loads files synchronously in a different thread.
@@ -113,3 +196,214 @@ impl FileSystem for ThreadedFileSystem {
         Box::new(file)
     }
 }
+
+#[cfg(feature = "zip")]
+mod zip {
+    use std::fs::File as StdFile;
+    use std::io::{self, Read, Seek, SeekFrom};
+    use std::path::{Component, Path};
+    use std::sync::atomic::Ordering;
+    use std::sync::{Arc, Mutex};
+
+    use hashbrown::HashMap;
+
+    use super::BasicFileTask;
+    use crate::experimental::{FileSystem, FileTask};
+
+    const END_OF_CENTRAL_DIRECTORY: u32 = 0x06054b50;
+    const CENTRAL_DIRECTORY_HEADER: u32 = 0x02014b50;
+    const LOCAL_FILE_HEADER: u32 = 0x04034b50;
+    const STORED: u16 = 0;
+    const DEFLATED: u16 = 8;
+    /// The value of a 32-bit size or offset that is stored in a ZIP64 extra
+    /// field instead.
+    const ZIP64_U32: u32 = 0xffffffff;
+    /// The value of a 16-bit entry count that is stored in a ZIP64 record
+    /// instead.
+    const ZIP64_U16: u16 = 0xffff;
+
+    /// An entry of a zip archive.
+    #[derive(Clone, Copy)]
+    struct Entry {
+        method: u16,
+        encrypted: bool,
+        compressed_size: u64,
+        size: u64,
+        header_offset: u64,
+    }
+
+    /// A file system that loads files from a zip archive, e.g. to distribute
+    /// all assets in a single file.
+    ///
+    /// The archive is opened and its directory read once when the file system
+    /// is created. Files are read and decompressed in a different thread, like
+    /// with [`ThreadedFileSystem`](super::ThreadedFileSystem). Entries must be
+    /// stored or compressed with deflate. ZIP64 archives and encrypted entries
+    /// are not supported.
+    pub struct ZipFileSystem {
+        file: Arc<Mutex<StdFile>>,
+        entries: HashMap<String, Entry>,
+    }
+
+    impl ZipFileSystem {
+        /// Opens the zip archive at the given path.
+        pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+            let mut file = StdFile::open(path)?;
+            let entries = read_entries(&mut file)?;
+            Ok(Self {
+                file: Arc::new(Mutex::new(file)),
+                entries,
+            })
+        }
+    }
+
+    impl FileSystem for ZipFileSystem {
+        fn read(&mut self, path: &Path) -> Box<dyn FileTask> {
+            // Entry names always use forward slashes.
+            let name = path
+                .components()
+                .filter_map(|e| match e {
+                    Component::Normal(e) => e.to_str(),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("/");
+            let ext = Path::new(&name)
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or_default()
+                .to_string();
+
+            let entry = match self.entries.get(&name) {
+                Some(&entry) => entry,
+                None => {
+                    let error = io::Error::new(io::ErrorKind::NotFound, "no such entry in archive");
+                    return Box::new(BasicFileTask::failed(ext, error));
+                }
+            };
+            let file = Arc::clone(&self.file);
+            Box::new(BasicFileTask::spawn(ext, move |state| {
+                state.total.store(entry.size, Ordering::Relaxed);
+                let data = read_entry(&file, &entry)?;
+                state.bytes_read.store(entry.size, Ordering::Relaxed);
+                Ok(data)
+            }))
+        }
+    }
+
+    fn invalid(message: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, message)
+    }
+
+    fn unsupported(message: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::Unsupported, message)
+    }
+
+    fn u16_at(data: &[u8], i: usize) -> u16 {
+        u16::from_le_bytes([data[i], data[i + 1]])
+    }
+
+    fn u32_at(data: &[u8], i: usize) -> u32 {
+        u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]])
+    }
+
+    /// Reads the central directory of the given archive.
+    fn read_entries(file: &mut StdFile) -> io::Result<HashMap<String, Entry>> {
+        // The end of central directory record is at the very end of the
+        // archive, followed only by a comment of at most `u16::MAX` bytes.
+        let len = file.seek(SeekFrom::End(0))?;
+        let tail_len = len.min(22 + u16::MAX as u64);
+        let mut tail = vec![0; tail_len as usize];
+        file.seek(SeekFrom::Start(len - tail_len))?;
+        file.read_exact(&mut tail)?;
+        let end = (0..tail.len().saturating_sub(21))
+            .rev()
+            .find(|&i| u32_at(&tail, i) == END_OF_CENTRAL_DIRECTORY)
+            .ok_or_else(|| invalid("not a zip archive"))?;
+        let count = u16_at(&tail, end + 10);
+        let directory_len = u32_at(&tail, end + 12);
+        let directory_offset = u32_at(&tail, end + 16);
+        if count == ZIP64_U16 || directory_len == ZIP64_U32 || directory_offset == ZIP64_U32 {
+            return Err(unsupported("ZIP64 archives are not supported"));
+        }
+
+        let mut directory = vec![0; directory_len as usize];
+        file.seek(SeekFrom::Start(directory_offset as u64))?;
+        file.read_exact(&mut directory)?;
+
+        let mut entries = HashMap::with_capacity(count as usize);
+        let mut i = 0;
+        for _ in 0..count {
+            if directory.len() < i + 46 || u32_at(&directory, i) != CENTRAL_DIRECTORY_HEADER {
+                return Err(invalid("corrupt zip central directory"));
+            }
+            let name_len = u16_at(&directory, i + 28) as usize;
+            let extra_len = u16_at(&directory, i + 30) as usize;
+            let comment_len = u16_at(&directory, i + 32) as usize;
+            let name = directory
+                .get(i + 46..i + 46 + name_len)
+                .ok_or_else(|| invalid("corrupt zip central directory"))?;
+            let name = String::from_utf8_lossy(name).into_owned();
+            let compressed_size = u32_at(&directory, i + 20);
+            let size = u32_at(&directory, i + 24);
+            let header_offset = u32_at(&directory, i + 42);
+            if [compressed_size, size, header_offset].contains(&ZIP64_U32) {
+                return Err(unsupported("ZIP64 archives are not supported"));
+            }
+            let entry = Entry {
+                method: u16_at(&directory, i + 10),
+                encrypted: u16_at(&directory, i + 8) & 1 != 0,
+                compressed_size: compressed_size as u64,
+                size: size as u64,
+                header_offset: header_offset as u64,
+            };
+            if !name.ends_with('/') {
+                entries.insert(name, entry);
+            }
+            i += 46 + name_len + extra_len + comment_len;
+        }
+        Ok(entries)
+    }
+
+    /// Reads and decompresses the data of the given entry.
+    fn read_entry(file: &Mutex<StdFile>, entry: &Entry) -> io::Result<Vec<u8>> {
+        if entry.encrypted {
+            return Err(unsupported("encrypted zip entries are not supported"));
+        }
+
+        let compressed = {
+            let mut file = file.lock().unwrap();
+            let mut header = [0; 30];
+            file.seek(SeekFrom::Start(entry.header_offset))?;
+            file.read_exact(&mut header)?;
+            if u32_at(&header, 0) != LOCAL_FILE_HEADER {
+                return Err(invalid("corrupt zip file header"));
+            }
+            // The local header may have a different extra field than the
+            // central directory, so skip past it using its own lengths.
+            let skip = u16_at(&header, 26) as i64 + u16_at(&header, 28) as i64;
+            file.seek(SeekFrom::Current(skip))?;
+            let mut compressed = vec![0; entry.compressed_size as usize];
+            file.read_exact(&mut compressed)?;
+            compressed
+        };
+
+        // The size in the central directory bounds the decompressed data, so
+        // a corrupt or malicious entry cannot exhaust memory.
+        let data = match entry.method {
+            STORED => compressed,
+            DEFLATED => {
+                miniz_oxide::inflate::decompress_to_vec_with_limit(&compressed, entry.size as usize)
+                    .map_err(|_| invalid("corrupt deflate stream"))?
+            }
+            _ => return Err(unsupported("unsupported zip compression method")),
+        };
+        if data.len() as u64 != entry.size {
+            return Err(invalid("zip entry size mismatch"));
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(feature = "zip")]
+pub use self::zip::ZipFileSystem;
@@ -1,3 +1,4 @@
+use std::io;
 use std::path::Path;
 
 /// Common interface for file systems. See also the [`fs`] module in [`assets`].
@@ -20,4 +21,26 @@ pub trait FileTask {
 
     /// Returns the extension of the file being loaded.
     fn extension(&self) -> &str;
+
+    /// Returns the error that occurred while reading the file, if any.
+    ///
+    /// This is only checked once [`poll`](Self::poll) has returned true. The
+    /// default implementation always returns `None`.
+    fn error(&self) -> Option<&io::Error> {
+        None
+    }
+
+    /// Returns the fraction of the file that has been read so far, from `0.0`
+    /// to `1.0`, or `None` if it is not known.
+    ///
+    /// The default implementation always returns `None`.
+    fn progress(&self) -> Option<f32> {
+        None
+    }
+
+    /// Cancels the file task. The task is dropped right after this is called.
+    ///
+    /// Implementations may use this to stop any work that is still in progress.
+    /// The default implementation does nothing.
+    fn cancel(&mut self) {}
 }
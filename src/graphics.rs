@@ -1,67 +1,142 @@
 //! Types relating to graphics and drawing.
 
+use std::fmt;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use fugu::{
-    BlendFactor, BlendOp, BlendState, Buffer, BufferKind, BufferLayout, BufferUsage, Context,
-    Image, ImageFilter, ImageFormat, ImageUniform, ImageWrap, PassAction, Pipeline, Uniform,
-    UniformFormat, VertexAttribute, VertexFormat,
+    BlendFactor, BlendOp, BlendState, BufferKind, BufferLayout, BufferUsage, ImageFilter,
+    ImageFormat, ImageUniform, ImageWrap, PassAction, Uniform, UniformFormat, VertexAttribute,
+    VertexFormat,
 };
+#[cfg(not(feature = "null-backend"))]
+pub(crate) use fugu::{Buffer, Context, Image, Pipeline};
 
 use crate::assets::{ResourceHandle, ResourceManager};
 
 mod color;
 pub use color::Color;
+mod rect;
+pub use rect::Rect;
+mod camera;
+pub use camera::Camera;
+mod render_target;
+pub use render_target::RenderTarget;
+mod sprite_sheet;
+pub(crate) use sprite_sheet::grid_cell;
+pub use sprite_sheet::SpriteSheet;
 pub mod commands;
 use commands::*;
+pub(crate) mod gl;
+#[cfg(not(feature = "null-backend"))]
+pub(crate) use gl::Gl;
+#[cfg(feature = "null-backend")]
+pub mod null;
+#[cfg(feature = "null-backend")]
+pub(crate) use null::{Buffer, Context, Gl, Image, Pipeline};
 
 mod shader {
     pub const VERT: &str = r"
-        #version 330
-        
         uniform vec2 viewport_size;
         
         in vec2 pos;
         in vec4 color;
         in vec2 uv;
+        in float slot;
         
         out vec4 vert_color;
         out vec2 vert_uv;
+        flat out float vert_slot;
         
         void main() {
             vec2 npos = pos * vec2(2, -2) / viewport_size + vec2(-1, 1);
             gl_Position = vec4(npos, 0, 1);
             vert_color = color;
             vert_uv = uv;
+            vert_slot = slot;
         }
     ";
 
     pub const FRAG: &str = r"
-        #version 330
-        
-        uniform sampler2D tex;
+        uniform sampler2D tex0;
+        uniform sampler2D tex1;
+        uniform sampler2D tex2;
+        uniform sampler2D tex3;
+        uniform sampler2D tex4;
+        uniform sampler2D tex5;
+        uniform sampler2D tex6;
+        uniform sampler2D tex7;
+        uniform vec4 flash;
 
         in vec4 vert_color;
         in vec2 vert_uv;
+        flat in float vert_slot;
         
         out vec4 out_color;
+
+        // Samplers cannot be indexed dynamically, so select the texture of
+        // the slot with a branch instead.
+        vec4 sample_slot(vec2 uv) {
+            int slot = int(vert_slot + 0.5);
+            if (slot == 0) return texture(tex0, uv);
+            if (slot == 1) return texture(tex1, uv);
+            if (slot == 2) return texture(tex2, uv);
+            if (slot == 3) return texture(tex3, uv);
+            if (slot == 4) return texture(tex4, uv);
+            if (slot == 5) return texture(tex5, uv);
+            if (slot == 6) return texture(tex6, uv);
+            return texture(tex7, uv);
+        }
         
         void main() {
-            out_color = vert_color * texture(tex, vert_uv);
+            vec4 color = vert_color * sample_slot(vert_uv);
+            out_color = vec4(mix(color.rgb, flash.rgb, flash.a), color.a);
         }
     ";
+
+    /// The overridable uniforms of the default fragment shader and their sizes
+    /// in floats, which all default to zero.
+    pub const UNIFORMS: [(&str, usize); 1] = [("flash", 4)];
+
+    /// The image uniforms of the default fragment shader, one per texture
+    /// slot.
+    pub const TEXTURES: [&str; super::TEXTURE_SLOTS] = [
+        "tex0", "tex1", "tex2", "tex3", "tex4", "tex5", "tex6", "tex7",
+    ];
+
+    /// Prepends the appropriate `#version` directive for the current OpenGL
+    /// version to the given shader source.
+    pub fn with_version(gl: &super::Gl, source: &str) -> String {
+        let (major, minor) = gl.version();
+        let version = if gl.is_es() {
+            "300 es\nprecision mediump float;"
+        } else {
+            match (major, minor) {
+                (3, 0) => "130",
+                (3, 1) => "140",
+                (3, 2) => "150",
+                _ => "330",
+            }
+        };
+        format!("#version {}\n{}", version, source)
+    }
 }
 
 /// A sprite.
 pub struct Sprite {
-    image: Image,
+    image: Rc<Image>,
     width: u32,
     height: u32,
+    region: (f32, f32, f32, f32),
+    anchor: (f32, f32),
 }
 
 impl Sprite {
     /// Creates a new sprite from the given parameters.
     ///
+    /// The width and height of the sprite must not exceed the maximum texture
+    /// size returned by [`Graphics::max_texture_size`].
+    ///
     /// # Arguments
     ///
     /// * `ctx` - A reference to the [`Context`] to use to create the image.
@@ -82,12 +157,79 @@ impl Sprite {
     ) -> Self {
         let image = ctx.create_image_with_data(width, height, format, filter, wrap, data.as_ref());
         Self {
-            image,
+            image: Rc::new(image),
             width,
             height,
+            region: (0., 0., 1., 1.),
+            anchor: (0., 0.),
         }
     }
 
+    /// Creates a new sprite from the given parameters like [`Sprite::new`],
+    /// premultiplying the color channels of the data by its alpha channel
+    /// before uploading it. See [`premultiply_alpha`].
+    ///
+    /// The data must be in the [`ImageFormat::Rgba8`] format.
+    pub fn new_premultiplied(
+        ctx: &Context,
+        width: u32,
+        height: u32,
+        filter: ImageFilter,
+        wrap: ImageWrap,
+        data: impl Into<Vec<u8>>,
+    ) -> Self {
+        let mut data = data.into();
+        premultiply_alpha(&mut data);
+        Self::new(ctx, width, height, ImageFormat::Rgba8, filter, wrap, data)
+    }
+
+    /// Creates a new sprite from a region of this sprite, in pixels.
+    ///
+    /// The new sprite shares the underlying [`Image`] of this sprite, so no
+    /// image data is copied. Since only the given region is sampled, the wrap
+    /// mode of the image has no effect when drawing the new sprite.
+    pub fn sub_sprite(&self, x: u32, y: u32, width: u32, height: u32) -> Self {
+        let (rx, ry, rw, rh) = self.region;
+        let (w, h) = (self.width as f32, self.height as f32);
+        Self {
+            image: Rc::clone(&self.image),
+            width,
+            height,
+            region: (
+                rx + x as f32 / w * rw,
+                ry + y as f32 / h * rh,
+                width as f32 / w * rw,
+                height as f32 / h * rh,
+            ),
+            anchor: self.anchor,
+        }
+    }
+
+    /// Sets the anchor of the sprite and returns it.
+    ///
+    /// See [`set_anchor`](Self::set_anchor) for more information.
+    pub fn with_anchor(mut self, x: f32, y: f32) -> Self {
+        self.set_anchor(x, y);
+        self
+    }
+
+    /// Sets the anchor of the sprite, normalized to the range `[0, 1]`.
+    ///
+    /// The anchor is the point of the sprite that is placed at the position
+    /// the sprite is drawn at, e.g. `(0.5, 0.5)` to center the sprite on that
+    /// position. It can be overridden for individual draws with
+    /// [`DrawSprite::anchor`].
+    ///
+    /// The default value is `(0, 0)`, the top-left corner of the sprite.
+    pub fn set_anchor(&mut self, x: f32, y: f32) {
+        self.anchor = (x, y);
+    }
+
+    /// Returns the anchor of the sprite.
+    pub fn anchor(&self) -> (f32, f32) {
+        self.anchor
+    }
+
     /// Returns the sprite's underlying [`Image`].
     pub fn inner(&self) -> &Image {
         &self.image
@@ -104,13 +246,578 @@ impl Sprite {
     }
 }
 
+/// Implementation limits of the graphics hardware. Returned by the [`limits`]
+/// method on [`Graphics`].
+///
+/// [`limits`]: Graphics::limits
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    /// The maximum width and height of a texture, in pixels.
+    pub max_texture_size: u32,
+    /// The maximum number of textures that can be sampled at once.
+    pub max_texture_units: u32,
+    /// The maximum width and height of the viewport, in pixels.
+    pub max_viewport_size: (u32, u32),
+}
+
+impl Limits {
+    fn query(gl: &Gl) -> Limits {
+        let [max_texture_size, ..] = gl.get_integers(gl::MAX_TEXTURE_SIZE);
+        let [max_texture_units, ..] = gl.get_integers(gl::MAX_TEXTURE_IMAGE_UNITS);
+        let [max_viewport_width, max_viewport_height, ..] = gl.get_integers(gl::MAX_VIEWPORT_DIMS);
+        Limits {
+            max_texture_size: max_texture_size as u32,
+            max_texture_units: max_texture_units as u32,
+            max_viewport_size: (max_viewport_width as u32, max_viewport_height as u32),
+        }
+    }
+}
+
+/// Premultiplies the color channels of the given RGBA8 pixel data by its
+/// alpha channel, in place.
+///
+/// Premultiplied data should be drawn with [`BlendMode::Premultiplied`], which
+/// avoids dark fringes around soft edges when the sprite is scaled or
+/// filtered. Note that premultiplying is lossy and must only be done once:
+/// premultiplying data that is already premultiplied darkens any partially
+/// transparent pixels.
+pub fn premultiply_alpha(data: &mut [u8]) {
+    for pixel in data.chunks_exact_mut(4) {
+        let a = pixel[3] as u16;
+        for c in &mut pixel[..3] {
+            *c = ((*c as u16 * a + 127) / 255) as u8;
+        }
+    }
+}
+
+/// The maximum number of vertices that can be addressed by `u16` indices.
+const MAX_VERTICES: usize = u16::MAX as usize + 1;
+/// Returns true if the given number of vertices can be added to a batch that
+/// already holds the given number of vertices without exceeding what `u16`
+/// indices can address.
+fn fits_indices(batch_verts: usize, verts: usize) -> bool {
+    batch_verts + verts <= MAX_VERTICES
+}
+
+/// Appends the indices of a draw command to a batch, offsetting them by the
+/// number of vertices the batch already holds.
+fn append_indices(batch_indices: &mut Vec<u16>, indices: &[u16], batch_verts: usize) {
+    batch_indices.extend(indices.iter().map(|&e| e + batch_verts as u16));
+}
+
+/// The number of vertices that initially fit into the vertex buffer.
+const VERTEX_CAPACITY: usize = 8000;
+/// The number of indices that initially fit into the index buffer.
+const INDEX_CAPACITY: usize = 12000;
+
+/// Returns the given buffer capacity doubled until it is at least the given
+/// required size.
+fn grown_capacity(capacity: usize, required: usize) -> usize {
+    let mut capacity = capacity.max(1);
+    while capacity < required {
+        capacity *= 2;
+    }
+    capacity
+}
+
+/// The number of textures the default shader can sample from in a single
+/// draw call. The first slot is reserved for the blank image used by shapes.
+const TEXTURE_SLOTS: usize = 8;
+
+/// The maximum number of floats passed to a shader as uniforms.
+const MAX_UNIFORM_FLOATS: usize = 32;
+
+/// A value for a shader uniform.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[allow(missing_docs)]
+pub enum UniformValue {
+    Float(f32),
+    Float2(f32, f32),
+    Float3(f32, f32, f32),
+    Float4(f32, f32, f32, f32),
+}
+
+impl UniformValue {
+    fn write_to(&self, out: &mut [f32]) {
+        let values = match *self {
+            UniformValue::Float(x) => [x, 0., 0., 0.],
+            UniformValue::Float2(x, y) => [x, y, 0., 0.],
+            UniformValue::Float3(x, y, z) => [x, y, z, 0.],
+            UniformValue::Float4(x, y, z, w) => [x, y, z, w],
+        };
+        let len = out.len().min(4);
+        out[..len].copy_from_slice(&values[..len]);
+    }
+}
+
+impl From<f32> for UniformValue {
+    fn from(x: f32) -> Self {
+        UniformValue::Float(x)
+    }
+}
+
+impl From<(f32, f32)> for UniformValue {
+    fn from((x, y): (f32, f32)) -> Self {
+        UniformValue::Float2(x, y)
+    }
+}
+
+impl From<(f32, f32, f32)> for UniformValue {
+    fn from((x, y, z): (f32, f32, f32)) -> Self {
+        UniformValue::Float3(x, y, z)
+    }
+}
+
+impl From<(f32, f32, f32, f32)> for UniformValue {
+    fn from((x, y, z, w): (f32, f32, f32, f32)) -> Self {
+        UniformValue::Float4(x, y, z, w)
+    }
+}
+
+impl From<Color> for UniformValue {
+    fn from(color: Color) -> Self {
+        UniformValue::Float4(color.r, color.g, color.b, color.a)
+    }
+}
+
+impl UniformValue {
+    fn format(&self) -> UniformFormat {
+        match self {
+            UniformValue::Float(..) => UniformFormat::Float1,
+            UniformValue::Float2(..) => UniformFormat::Float2,
+            UniformValue::Float3(..) => UniformFormat::Float3,
+            UniformValue::Float4(..) => UniformFormat::Float4,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            UniformValue::Float(..) => 1,
+            UniformValue::Float2(..) => 2,
+            UniformValue::Float3(..) => 3,
+            UniformValue::Float4(..) => 4,
+        }
+    }
+}
+
+/// A custom fragment shader to draw with, e.g. for palette swaps or grayscale
+/// effects. Created with [`Graphics::create_shader`].
+///
+/// Cloning a [`Shader`] is cheap, and clones share the same underlying
+/// pipeline.
+#[derive(Clone)]
+pub struct Shader {
+    inner: Rc<ShaderInner>,
+    values: Vec<(&'static str, UniformValue)>,
+}
+
+struct ShaderInner {
+    pipeline: Pipeline,
+    uniforms: Vec<(&'static str, usize)>,
+}
+
+impl Shader {
+    /// Sets the value of a uniform declared by the shader.
+    ///
+    /// The value is used for draws after the next call to
+    /// [`Graphics::set_shader`] with this shader. It can be overridden with
+    /// [`Graphics::set_uniform`] and for individual sprites with
+    /// [`DrawSprite::uniform`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the shader does not declare a uniform with the given name, or
+    /// if the value has a different type than the declared uniform.
+    pub fn set_uniform(&mut self, name: &'static str, value: impl Into<UniformValue>) {
+        let value = value.into();
+        let slot = match self.values.iter_mut().find(|(e, _)| *e == name) {
+            Some((_, slot)) => slot,
+            None => panic!("shader has no uniform named {:?}", name),
+        };
+        assert!(
+            slot.len() == value.len(),
+            "mismatched type for uniform {:?}: expected {} floats, got {}",
+            name,
+            slot.len(),
+            value.len(),
+        );
+        *slot = value;
+    }
+
+    /// Returns the current value of the uniform with the given name, if the
+    /// shader declares it.
+    pub fn uniform(&self, name: &str) -> Option<UniformValue> {
+        self.values
+            .iter()
+            .find(|(e, _)| *e == name)
+            .map(|&(_, value)| value)
+    }
+}
+
+impl PartialEq for ShaderInner {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Debug for ShaderInner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ShaderInner")
+            .field("uniforms", &self.uniforms)
+            .finish()
+    }
+}
+
+impl PartialEq for Shader {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl fmt::Debug for Shader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Shader")
+            .field("uniforms", &self.values)
+            .finish()
+    }
+}
+
+/// A single sprite drawn by [`Graphics::draw_sprites`].
+///
+/// Fields left as `None` behave the same as the corresponding options of
+/// [`DrawSprite`] when they are not set.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SpriteInstance {
+    /// The position of the sprite's origin.
+    pub pos: (f32, f32),
+    /// The size of the sprite. Defaults to the size of the source rectangle.
+    pub size: Option<(f32, f32)>,
+    /// The rotation of the sprite around its origin, in radians, with positive
+    /// angles going clockwise.
+    pub rotation: f32,
+    /// The point that the sprite is positioned and rotated around, relative to
+    /// its size, like an anchor. Defaults to the sprite's own anchor.
+    pub origin: Option<(f32, f32)>,
+    /// The rectangle of the sprite to draw, in pixels. Defaults to the whole
+    /// sprite.
+    pub source_rect: Option<Rect>,
+    /// The color of the sprite. Defaults to the current color.
+    pub color: Option<Color>,
+}
+
+impl SpriteInstance {
+    /// Creates a new [`SpriteInstance`] at the given position with every other
+    /// field left at its default.
+    pub fn new(x: f32, y: f32) -> Self {
+        SpriteInstance {
+            pos: (x, y),
+            size: None,
+            rotation: 0.,
+            origin: None,
+            source_rect: None,
+            color: None,
+        }
+    }
+}
+
+/// The border widths of a nine-patch sprite, in pixels of the sprite. See
+/// [`Graphics::draw_nine_patch`].
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Insets {
+    /// The width of the left border.
+    pub left: f32,
+    /// The width of the right border.
+    pub right: f32,
+    /// The height of the top border.
+    pub top: f32,
+    /// The height of the bottom border.
+    pub bottom: f32,
+}
+
+impl Insets {
+    /// Creates new [`Insets`] with the given border widths.
+    pub fn new(left: f32, right: f32, top: f32, bottom: f32) -> Self {
+        Insets {
+            left,
+            right,
+            top,
+            bottom,
+        }
+    }
+
+    /// Creates new [`Insets`] with the same width for every border.
+    pub fn uniform(width: f32) -> Self {
+        Insets::new(width, width, width, width)
+    }
+}
+
+/// Statistics about what has been drawn during the current frame. Returned by
+/// [`Graphics::stats`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct RenderStats {
+    /// The number of draw commands that were drawn, e.g. one per sprite.
+    pub draw_commands: usize,
+    /// The number of draw calls that were issued to the GPU.
+    pub draw_calls: usize,
+    /// The number of vertices that were uploaded to the GPU.
+    pub vertices: usize,
+    /// The number of indices that were uploaded to the GPU.
+    pub indices: usize,
+}
+
+/// A debug visualization used when drawing. See [`Graphics::set_debug_mode`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DebugMode {
+    /// Everything is drawn normally.
+    Off,
+    /// Triangles are drawn as outlines. Not supported on OpenGL ES, where
+    /// everything is drawn normally instead.
+    Wireframe,
+    /// Everything is drawn in a dim flat color that is added to whatever is
+    /// behind it, so areas that are drawn to many times appear brighter.
+    Overdraw,
+    /// Everything is drawn in a flat color that depends on the batch it is
+    /// drawn in, so each draw call appears in a different color.
+    Batches,
+}
+
+/// How colors being drawn are combined with the colors behind them. See
+/// [`Graphics::set_blend_mode`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlendMode {
+    /// Colors are blended based on their alpha. This is the default.
+    Alpha,
+    /// Colors are multiplied by their alpha and added to the colors behind
+    /// them, e.g. for lights and particle effects.
+    Additive,
+    /// The colors behind are multiplied by the colors being drawn, e.g. for
+    /// shadows and darkening effects.
+    Multiply,
+    /// Colors replace the colors behind them, including their alpha.
+    None,
+    /// Like [`Alpha`](Self::Alpha), but for colors whose alpha has already
+    /// been premultiplied, e.g. sprites loaded with
+    /// [`App::with_premultiplied_sprites`](crate::App::with_premultiplied_sprites).
+    /// This avoids dark halos around the soft edges of filtered sprites.
+    ///
+    /// Draw colors are multiplied with the colors being drawn as usual, so
+    /// they should be premultiplied as well: to fade a sprite to half its
+    /// opacity, draw it with `Color::WHITE.with_alpha(0.5) * 0.5` rather than
+    /// `Color::WHITE.with_alpha(0.5)`, which would brighten it instead.
+    Premultiplied,
+}
+
+impl BlendMode {
+    /// Returns the blend state implementing the blend mode.
+    fn blend_state(self) -> BlendState {
+        let (source, dest) = match self {
+            BlendMode::Alpha => return default_blend(),
+            BlendMode::Additive => (BlendFactor::SourceAlpha, BlendFactor::One),
+            BlendMode::Multiply => (
+                BlendFactor::DestinationColor,
+                BlendFactor::OneMinusSourceAlpha,
+            ),
+            BlendMode::None => (BlendFactor::One, BlendFactor::Zero),
+            BlendMode::Premultiplied => (BlendFactor::One, BlendFactor::OneMinusSourceAlpha),
+        };
+        BlendState {
+            op: BlendOp::Add,
+            source,
+            dest,
+        }
+    }
+}
+
+const OVERDRAW_COLOR: Color = Color::from_rgb(0.1, 0.1, 0.1);
+
+const BATCH_COLORS: [Color; 8] = [
+    Color::from_rgb(0.9, 0.2, 0.2),
+    Color::from_rgb(0.2, 0.8, 0.2),
+    Color::from_rgb(0.2, 0.4, 0.9),
+    Color::from_rgb(0.9, 0.8, 0.2),
+    Color::from_rgb(0.8, 0.3, 0.9),
+    Color::from_rgb(0.2, 0.8, 0.9),
+    Color::from_rgb(0.9, 0.5, 0.1),
+    Color::from_rgb(0.6, 0.6, 0.6),
+];
+
 #[derive(Debug)]
 struct DrawBatch {
-    sprite: Option<ResourceHandle<Sprite>>,
+    state: DrawState,
+    /// The sprites bound to the texture slots after the first, when drawing
+    /// with the default shader.
+    sprites: Vec<ResourceHandle<Sprite>>,
     start: usize,
     count: usize,
 }
 
+/// A vertex as uploaded to the vertex buffer, with the texture slot of the
+/// sprite it belongs to.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct BatchVertex {
+    vertex: Vertex,
+    slot: f32,
+}
+
+/// Returns whether a viewport of the given size has any area to draw to.
+///
+/// The vertex shader divides by the size of the viewport, so drawing to one
+/// without area would produce NaN positions.
+fn has_area((width, height): (f32, f32)) -> bool {
+    width > 0. && height > 0.
+}
+
+/// Batches that are uploaded and drawn together, along with their vertices and
+/// indices. The indices of a group always fit into `u16`s.
+#[derive(Default)]
+struct BatchGroup {
+    batches: Vec<DrawBatch>,
+    verts: Vec<BatchVertex>,
+    indices: Vec<u16>,
+}
+
+/// Batches the given draw commands in order, returning the number of commands
+/// that were batched.
+///
+/// Commands whose sprite is not loaded according to `is_loaded` are skipped
+/// before their vertices are accumulated, so that they neither take up space in
+/// the buffers nor break batches. Whenever the next command's indices would
+/// overflow `u16`, a new group is started. Existing groups are reused, so that
+/// their allocations carry over from previous calls.
+fn batch_commands(
+    draw_commands: &[DrawCommand],
+    debug_mode: DebugMode,
+    mut is_loaded: impl FnMut(ResourceHandle<Sprite>) -> bool,
+    groups: &mut Vec<BatchGroup>,
+) -> usize {
+    for group in groups.iter_mut() {
+        group.batches.clear();
+        group.verts.clear();
+        group.indices.clear();
+    }
+    if groups.is_empty() {
+        groups.push(BatchGroup::default());
+    }
+    let mut curr_state = match draw_commands.first() {
+        Some(draw_command) => draw_command.state.clone(),
+        None => {
+            groups.truncate(1);
+            return 0;
+        }
+    };
+    let mut group = 0;
+    let mut slots = Vec::new();
+    let mut begin = 0;
+    let mut batched = 0;
+    // The number of batches in earlier groups, for coloring batches.
+    let mut prev_batches = 0;
+    // The last sprite checked for whether it has finished loading.
+    let mut last_sprite = None;
+
+    for draw_command in draw_commands {
+        if draw_command.verts.len() > MAX_VERTICES {
+            warn_oversized_draw(draw_command.verts.len());
+            continue;
+        }
+        let sprite = draw_command.state.sprite;
+        if let Some(sprite) = sprite {
+            let loaded = match last_sprite {
+                Some((last, loaded)) if last == sprite => loaded,
+                _ => {
+                    let loaded = is_loaded(sprite);
+                    last_sprite = Some((sprite, loaded));
+                    loaded
+                }
+            };
+            if !loaded {
+                continue;
+            }
+        }
+        batched += 1;
+        // Indices are `u16`s, so start a new group before they would overflow.
+        // The buffers grow to fit.
+        if !fits_indices(groups[group].verts.len(), draw_command.verts.len()) {
+            let BatchGroup {
+                batches, indices, ..
+            } = &mut groups[group];
+            batches.push(DrawBatch {
+                state: curr_state.clone(),
+                sprites: slots.clone(),
+                start: begin,
+                count: indices.len() - begin,
+            });
+            prev_batches += batches.len();
+            group += 1;
+            if group == groups.len() {
+                groups.push(BatchGroup::default());
+            }
+            begin = 0;
+        }
+        let BatchGroup {
+            batches,
+            verts,
+            indices,
+        } = &mut groups[group];
+        // Sprites drawn with the default shader share a batch as long as
+        // there are texture slots left for them.
+        let slots_full = match sprite {
+            Some(sprite) => !slots.contains(&sprite) && slots.len() + 1 == TEXTURE_SLOTS,
+            None => false,
+        };
+        if !curr_state.batches_with(&draw_command.state) || slots_full {
+            batches.push(DrawBatch {
+                state: std::mem::replace(&mut curr_state, draw_command.state.clone()),
+                sprites: std::mem::take(&mut slots),
+                start: begin,
+                count: indices.len() - begin,
+            });
+            begin = indices.len();
+        }
+        // Custom shaders only sample from a single texture.
+        let slot = match sprite {
+            Some(sprite) if curr_state.shader.is_none() => {
+                match slots.iter().position(|&e| e == sprite) {
+                    Some(i) => i + 1,
+                    None => {
+                        slots.push(sprite);
+                        slots.len()
+                    }
+                }
+            }
+            _ => 0,
+        } as f32;
+        append_indices(indices, &draw_command.indices, verts.len());
+        let debug_color = match debug_mode {
+            DebugMode::Off | DebugMode::Wireframe => None,
+            DebugMode::Overdraw => Some(OVERDRAW_COLOR),
+            DebugMode::Batches => {
+                let batch = prev_batches + batches.len();
+                Some(BATCH_COLORS[batch % BATCH_COLORS.len()])
+            }
+        };
+        verts.extend(draw_command.verts.iter().map(|&vertex| BatchVertex {
+            vertex: match debug_color {
+                Some(color) => vertex.with_color(color),
+                None => vertex,
+            },
+            slot,
+        }));
+    }
+
+    let BatchGroup {
+        batches, indices, ..
+    } = &mut groups[group];
+    batches.push(DrawBatch {
+        state: curr_state,
+        sprites: slots,
+        start: begin,
+        count: indices.len() - begin,
+    });
+    groups.truncate(group + 1);
+    batched
+}
+
 /// An interface for hardware-accelerated 2D drawing. Accessible from
 /// [`App`](crate::App) by default.
 pub struct Graphics {
@@ -118,57 +825,57 @@ pub struct Graphics {
     pub ctx: Rc<Context>,
     /// The [`ResourceManager`] instance used by the [`Graphics`].
     pub resource_manager: ResourceManager,
+    gl: Gl,
+    limits: Limits,
     pipeline: Pipeline,
+    shader: Option<Shader>,
+    uniforms: Vec<(&'static str, UniformValue)>,
+    /// Names of undeclared uniforms that have already been warned about.
+    warned_uniforms: Vec<&'static str>,
     vertex_buffer: Buffer,
     index_buffer: Buffer,
+    /// The sizes of the vertex and index buffers, in bytes.
+    buffer_capacity: (usize, usize),
     blank_image: Image,
     default_font: Option<ResourceHandle<Font>>,
+    snap_text: bool,
     draw_commands: Vec<DrawCommand>,
+    batch_groups: Vec<BatchGroup>,
     viewport: (f32, f32),
+    viewport_origin: (f32, f32),
+    surface: (f32, f32),
+    window_size: (f32, f32),
     color: Color,
+    layer: i16,
     depth: f32,
+    depth_range: (f32, f32),
+    depth_sorting: bool,
+    blend_mode: BlendMode,
+    scissors: Vec<Rect>,
+    seq: u32,
+    framebuffer: Option<u32>,
+    stats: RenderStats,
+    frame: u64,
+    debug_mode: DebugMode,
+    camera: Camera,
+    scale: f32,
 }
 
 impl Graphics {
-    pub(crate) fn new(ctx: &Rc<Context>, resource_manager: &ResourceManager) -> Graphics {
-        ctx.set_blend(BlendState {
-            op: BlendOp::Add,
-            source: BlendFactor::SourceAlpha,
-            dest: BlendFactor::OneMinusSourceAlpha,
-        });
+    pub(crate) fn new(ctx: &Rc<Context>, gl: Gl, resource_manager: &ResourceManager) -> Graphics {
+        ctx.set_blend(default_blend());
 
-        let default_shader = ctx.create_shader(
-            shader::VERT,
-            shader::FRAG,
-            &[Uniform {
-                name: "viewport_size",
-                format: UniformFormat::Float2,
-            }],
-            &[ImageUniform { name: "tex" }],
-        );
-        let pipeline = ctx.create_pipeline(
-            default_shader,
-            &[BufferLayout::default()],
-            &[
-                VertexAttribute {
-                    name: "pos",
-                    format: VertexFormat::Float2,
-                    buffer_index: 0,
-                },
-                VertexAttribute {
-                    name: "color",
-                    format: VertexFormat::Float4,
-                    buffer_index: 0,
-                },
-                VertexAttribute {
-                    name: "uv",
-                    format: VertexFormat::Float2,
-                    buffer_index: 0,
-                },
-            ],
-        );
-        let vertex_buffer = ctx.create_buffer(BufferKind::Vertex, BufferUsage::Stream, 8000 * 32);
-        let index_buffer = ctx.create_buffer(BufferKind::Index, BufferUsage::Stream, 12000 * 2);
+        let declared = shader::UNIFORMS.iter().map(|&(name, _)| Uniform {
+            name,
+            format: UniformFormat::Float4,
+        });
+        let pipeline = create_pipeline(ctx, &gl, shader::FRAG, declared, &shader::TEXTURES);
+        let vertex_capacity = VERTEX_CAPACITY * std::mem::size_of::<BatchVertex>();
+        let index_capacity = INDEX_CAPACITY * std::mem::size_of::<u16>();
+        let vertex_buffer =
+            ctx.create_buffer(BufferKind::Vertex, BufferUsage::Stream, vertex_capacity);
+        let index_buffer =
+            ctx.create_buffer(BufferKind::Index, BufferUsage::Stream, index_capacity);
         let blank_image = ctx.create_image_with_data(
             1,
             1,
@@ -177,43 +884,448 @@ impl Graphics {
             ImageWrap::Clamp,
             &[255_u8; 3],
         );
+        let shader = None;
+        let uniforms = Vec::new();
+        let warned_uniforms = Vec::new();
+        let limits = Limits::query(&gl);
         let default_font = None;
+        let snap_text = false;
         let draw_commands = Vec::new();
+        let batch_groups = Vec::new();
         let viewport = (0., 0.);
+        let viewport_origin = (0., 0.);
+        let surface = (0., 0.);
+        let window_size = (0., 0.);
         let color = Color::WHITE;
+        let layer = 0;
         let depth = 0.;
+        let depth_range = (f32::NEG_INFINITY, f32::INFINITY);
+        let depth_sorting = true;
+        let blend_mode = BlendMode::Alpha;
+        let scissors = Vec::new();
+        let seq = 0;
+        let framebuffer = None;
+        let stats = RenderStats::default();
+        let frame = 0;
+        let debug_mode = DebugMode::Off;
+        let camera = Camera::default();
+        let scale = 1.;
 
         Graphics {
             ctx: ctx.clone(),
             resource_manager: resource_manager.clone(),
+            gl,
+            limits,
             pipeline,
+            shader,
+            uniforms,
+            warned_uniforms,
             vertex_buffer,
             index_buffer,
+            buffer_capacity: (vertex_capacity, index_capacity),
             blank_image,
             default_font,
+            snap_text,
             draw_commands,
+            batch_groups,
             viewport,
+            viewport_origin,
+            surface,
+            window_size,
             color,
+            layer,
             depth,
+            depth_range,
+            depth_sorting,
+            blend_mode,
+            scissors,
+            seq,
+            framebuffer,
+            stats,
+            frame,
+            debug_mode,
+            camera,
+            scale,
         }
     }
 
     pub(crate) fn set_viewport(&mut self, (width, height): (u32, u32)) {
-        self.ctx.set_viewport(0, 0, width, height);
-        self.viewport = (width as f32, height as f32);
+        self.window_size = (width as f32, height as f32);
+        // While a render target is bound, the new size is applied once it is
+        // unbound again.
+        if self.framebuffer.is_none() {
+            self.ctx.set_viewport(0, 0, width, height);
+            self.set_surface(self.window_size);
+        }
+    }
+
+    /// Sets the size of the surface being drawn to and resets the viewport to
+    /// cover all of it.
+    fn set_surface(&mut self, size: (f32, f32)) {
+        self.viewport = size;
+        self.viewport_origin = (0., 0.);
+        self.surface = size;
+    }
+
+    pub(crate) fn set_srgb(&self, srgb: bool) {
+        self.gl.set_enabled(gl::FRAMEBUFFER_SRGB, srgb);
+    }
+
+    /// Returns the implementation limits of the graphics hardware.
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Returns the maximum width and height of a texture, in pixels.
+    ///
+    /// Creating a [`Sprite`] larger than this will fail.
+    pub fn max_texture_size(&self) -> u32 {
+        self.limits.max_texture_size
+    }
+
+    /// Returns the rectangle of the world that is currently visible on the
+    /// screen.
+    ///
+    /// This is useful for skipping draw calls for anything that is off-screen.
+    /// If the camera is rotated, this is the smallest rectangle containing
+    /// everything that is visible.
+    pub fn visible_bounds(&self) -> Rect {
+        let (w, h) = self.viewport;
+        let corners = [(0., 0.), (w, 0.), (w, h), (0., h)]
+            .map(|(x, y)| self.camera.screen_to_world(x / self.scale, y / self.scale));
+        let (mut min, mut max) = (corners[0], corners[0]);
+        for (x, y) in corners {
+            min = (min.0.min(x), min.1.min(y));
+            max = (max.0.max(x), max.1.max(y));
+        }
+        Rect::new(min.0, min.1, max.0 - min.0, max.1 - min.1)
+    }
+
+    /// Converts the given position in window coordinates, i.e. in pixels
+    /// relative to the top-left corner of the window, to world coordinates,
+    /// i.e. the coordinates that are passed to the drawing methods.
+    ///
+    /// Inside of a [`pass`](Self::pass), world coordinates are relative to the
+    /// pass's rectangle. The current [camera](Self::set_camera) and
+    /// [scale](Self::set_scale) are taken into account as well.
+    pub fn screen_to_world(&self, x: f32, y: f32) -> (f32, f32) {
+        let (ox, oy) = self.viewport_origin;
+        self.camera
+            .screen_to_world((x - ox) / self.scale, (y - oy) / self.scale)
+    }
+
+    /// Sets the camera used to transform the positions of subsequent draws
+    /// from world coordinates to screen coordinates.
+    ///
+    /// Draws made before calling this function keep using the camera that was
+    /// set at the time. To draw in screen coordinates again, e.g. for a user
+    /// interface, set the default camera.
+    pub fn set_camera(&mut self, camera: &Camera) {
+        self.camera = *camera;
+    }
+
+    /// Returns the current camera.
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    /// Sets the factor by which the positions of subsequent draws are
+    /// multiplied after applying the camera, e.g. to draw in logical units on
+    /// a high-DPI screen by passing [`Window::scale_factor`].
+    ///
+    /// Viewports and scissor rectangles are still given in pixels. The default
+    /// value is `1.0`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the scale is not finite and positive.
+    ///
+    /// [`Window::scale_factor`]: crate::window::Window::scale_factor
+    pub fn set_scale(&mut self, scale: f32) {
+        assert!(scale.is_finite() && scale > 0., "invalid scale {}", scale);
+        self.scale = scale;
+    }
+
+    /// Returns the current scale.
+    ///
+    /// See [`set_scale`](Self::set_scale) for more information.
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Returns statistics about everything that has been drawn since the start
+    /// of the current frame.
+    ///
+    /// Draws are only counted once they are ended, so this is best called
+    /// after [`end`](Self::end). This makes it possible to e.g. check how well
+    /// draws are being batched.
+    pub fn stats(&self) -> RenderStats {
+        self.stats
+    }
+
+    /// Resets the stats and advances the frame counter at the start of a
+    /// frame.
+    pub(crate) fn begin_frame(&mut self) {
+        self.stats = RenderStats::default();
+        self.frame += 1;
+    }
+
+    /// Returns the number of frames begun so far.
+    pub(crate) fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Sets the debug visualization to use when drawing.
+    ///
+    /// While a mode other than [`DebugMode::Off`] is active, the normal colors
+    /// of whatever is drawn are overridden, and [`DebugMode::Overdraw`] and
+    /// [`DebugMode::Batches`] ignore sprite images as well. The default mode is
+    /// [`DebugMode::Off`].
+    pub fn set_debug_mode(&mut self, mode: DebugMode) {
+        self.debug_mode = mode;
+    }
+
+    /// Returns the current debug visualization.
+    pub fn debug_mode(&self) -> DebugMode {
+        self.debug_mode
     }
 
     /// Immediately clears the screen to the given color.
     pub fn clear(&self, color: Color) {
-        self.ctx.begin_default_pass(PassAction::Clear {
-            color: Some((color.r, color.g, color.b, color.a)),
-            depth: None,
-            stencil: None,
-        });
+        let color = (color.r, color.g, color.b, color.a);
+        let partial = self.viewport_origin != (0., 0.) || self.viewport != self.surface;
+        if partial {
+            // Clearing ignores the viewport, so restrict it to the current
+            // pass with the scissor test instead.
+            self.begin_pass(PassAction::Nothing);
+            let (x, y, width, height) = self.gl_viewport();
+            self.gl.set_scissor(x, y, width, height);
+            self.gl.set_enabled(gl::SCISSOR_TEST, true);
+            self.gl.clear_color_buffer(color);
+            self.gl.set_enabled(gl::SCISSOR_TEST, false);
+        } else if self.framebuffer.is_some() {
+            self.begin_pass(PassAction::Nothing);
+            self.gl.clear_color_buffer(color);
+        } else {
+            self.begin_pass(PassAction::Clear {
+                color: Some(color),
+                depth: None,
+                stencil: None,
+            });
+        }
         self.ctx.end_render_pass();
     }
 
+    /// Renders the draws made by the given closure to an offscreen image of the
+    /// given size and returns its pixels as RGBA8 data, with the top row first.
+    ///
+    /// The image starts out transparent. The viewport is set to the size of
+    /// the image while the closure runs, and anything drawn but not yet ended
+    /// before calling this function is left untouched. The camera, scale,
+    /// scissor rectangles, shader, and uniforms are reset to their defaults
+    /// for the closure and restored afterwards.
+    ///
+    /// This reads the pixels back from the GPU, which stalls the pipeline, so
+    /// it is best suited for occasional use such as generating thumbnails.
+    pub fn render_to_image(
+        &mut self,
+        width: u32,
+        height: u32,
+        draw: impl FnOnce(&mut Graphics),
+    ) -> Vec<u8> {
+        let (framebuffer, texture) = self.gl.create_framebuffer(width, height);
+
+        let prev_framebuffer = self.framebuffer.replace(framebuffer);
+        let prev_viewport = (self.viewport, self.viewport_origin, self.surface);
+        let prev_draw_commands = std::mem::take(&mut self.draw_commands);
+        let prev_seq = std::mem::replace(&mut self.seq, 0);
+        let prev_state = (
+            std::mem::take(&mut self.camera),
+            std::mem::replace(&mut self.scale, 1.),
+            std::mem::take(&mut self.scissors),
+            self.shader.take(),
+            std::mem::take(&mut self.uniforms),
+        );
+        self.set_surface((width as f32, height as f32));
+
+        draw(self);
+        self.end();
+
+        self.gl.bind_framebuffer(framebuffer);
+        let pixels = self.gl.read_pixels(width, height);
+
+        self.framebuffer = prev_framebuffer;
+        self.draw_commands = prev_draw_commands;
+        self.seq = prev_seq;
+        (
+            self.camera,
+            self.scale,
+            self.scissors,
+            self.shader,
+            self.uniforms,
+        ) = prev_state;
+        (self.viewport, self.viewport_origin, self.surface) = prev_viewport;
+        self.gl.bind_framebuffer(prev_framebuffer.unwrap_or(0));
+        self.apply_viewport();
+        self.gl.delete_framebuffer(framebuffer, texture);
+
+        pixels
+    }
+
+    /// Creates a new [`RenderTarget`] of the given size, which starts out
+    /// transparent.
+    ///
+    /// The sprite of the render target is sampled with
+    /// [`ImageFilter::Nearest`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the render target cannot be rendered to on this hardware.
+    pub fn create_render_target(&mut self, width: u32, height: u32) -> RenderTarget {
+        let data = vec![0_u8; width as usize * height as usize * 4];
+        let image = self.ctx.create_image_with_data(
+            width,
+            height,
+            ImageFormat::Rgba8,
+            ImageFilter::Nearest,
+            ImageWrap::Clamp,
+            &data,
+        );
+
+        // fugu doesn't expose the texture behind an image, so bind the image
+        // to the first texture unit through fugu and query that unit's binding
+        // instead. Querying the unit explicitly doesn't depend on which unit
+        // fugu leaves active.
+        self.ctx.set_pipeline(&self.pipeline);
+        self.ctx.set_images(&[&image]);
+        let texture = self.gl.texture_binding(0);
+        let framebuffer = self.gl.create_framebuffer_for(texture);
+
+        let sprite = self.resource_manager.allocate_with(|| Sprite {
+            image: Rc::new(image),
+            width,
+            height,
+            // OpenGL stores the bottom row of the framebuffer first, so flip
+            // the sprite vertically.
+            region: (0., 1., 1., -1.),
+            anchor: (0., 0.),
+        });
+
+        RenderTarget {
+            framebuffer,
+            sprite,
+            width,
+            height,
+        }
+    }
+
+    /// Deletes the given [`RenderTarget`] and frees its sprite.
+    ///
+    /// Anything drawn before calling this function is ended first. If the
+    /// render target is the current render target, subsequent draws are
+    /// rendered to the screen again.
+    pub fn delete_render_target(&mut self, target: RenderTarget) {
+        self.end();
+        if self.framebuffer == Some(target.framebuffer) {
+            self.set_render_target(None);
+        }
+        self.gl.delete_framebuffer_only(target.framebuffer);
+        self.resource_manager.free(target.sprite);
+    }
+
+    /// Sets the render target that subsequent draws are rendered to, or the
+    /// screen if `None`.
+    ///
+    /// Anything drawn before calling this function is ended first, so it is
+    /// rendered to the previous target. The viewport is reset to cover the
+    /// whole target, and [`clear`](Self::clear) clears the target instead of
+    /// the screen.
+    ///
+    /// Drawing the sprite of the current render target has undefined results.
+    pub fn set_render_target(&mut self, target: Option<&RenderTarget>) {
+        self.end();
+        self.framebuffer = target.map(|e| e.framebuffer);
+        self.set_surface(target.map_or(self.window_size, |e| (e.width as f32, e.height as f32)));
+        self.gl.bind_framebuffer(self.framebuffer.unwrap_or(0));
+        self.apply_viewport();
+    }
+
+    /// Draws everything drawn by the given closure into the given rectangle of
+    /// the screen, e.g. for split-screen views or a minimap.
+    ///
+    /// Anything drawn before calling this function is ended first. While the
+    /// closure runs, the viewport is restricted to the given rectangle, so
+    /// positions are relative to its top-left corner and anything outside of it
+    /// is cut off. [`clear`](Self::clear) only clears the rectangle as well.
+    ///
+    /// Everything drawn by the closure is ended once it returns, and the
+    /// previous viewport, color, layer, depth, blend mode, camera, shader, and
+    /// uniforms are restored. Passes can be nested, in which case the
+    /// rectangle is relative to the enclosing pass.
+    pub fn pass(&mut self, viewport: Rect, draw: impl FnOnce(&mut Graphics)) {
+        self.end();
+
+        let prev_viewport = (self.viewport, self.viewport_origin);
+        let prev_state = (
+            self.color,
+            self.layer,
+            self.depth,
+            self.blend_mode,
+            self.camera,
+            self.shader.clone(),
+            self.uniforms.clone(),
+        );
+        self.viewport = (viewport.w.max(0.), viewport.h.max(0.));
+        self.viewport_origin.0 += viewport.x;
+        self.viewport_origin.1 += viewport.y;
+
+        draw(self);
+        self.end();
+
+        (self.viewport, self.viewport_origin) = prev_viewport;
+        (
+            self.color,
+            self.layer,
+            self.depth,
+            self.blend_mode,
+            self.camera,
+            self.shader,
+            self.uniforms,
+        ) = prev_state;
+        self.apply_viewport();
+    }
+
+    /// Begins a render pass on the current framebuffer.
+    fn begin_pass(&self, action: PassAction) {
+        self.ctx.begin_default_pass(action);
+        if let Some(framebuffer) = self.framebuffer {
+            self.gl.bind_framebuffer(framebuffer);
+        }
+        self.apply_viewport();
+    }
+
+    /// Applies the current viewport.
+    fn apply_viewport(&self) {
+        let (x, y, width, height) = self.gl_viewport();
+        self.gl.set_viewport(x, y, width, height);
+    }
+
+    /// Returns the current viewport, converted to OpenGL's bottom-left origin.
+    fn gl_viewport(&self) -> (i32, i32, i32, i32) {
+        let (x, y) = self.viewport_origin;
+        let (width, height) = self.viewport;
+        (
+            x as i32,
+            (self.surface.1 - y - height) as i32,
+            width as i32,
+            height as i32,
+        )
+    }
+
     /// Begins drawing.
+    ///
+    /// This is called automatically before the frame callbacks of each frame,
+    /// so it is only necessary when drawing was ended manually.
     pub fn begin(&mut self) {}
 
     /// Sets the default color to use when drawing.
@@ -221,98 +1333,753 @@ impl Graphics {
         self.color = color;
     }
 
+    /// Sets the layer to use when drawing.
+    ///
+    /// Layers are drawn in ascending order, so anything drawn on a higher layer
+    /// always appears on top of anything drawn on a lower layer, regardless of
+    /// depth. The default layer is `0`.
+    pub fn set_layer(&mut self, layer: i16) {
+        self.layer = layer;
+    }
+
     /// Sets the default depth to use when drawing.
+    ///
+    /// Within a layer, draws with greater depths are drawn first and thus
+    /// appear behind draws with lesser depths. Draws with equal depths are
+    /// drawn in the order they were submitted. The default depth is `0`.
+    ///
+    /// Depths are clamped to the range set by [`set_depth_range`] when drawing.
+    ///
+    /// [`set_depth_range`]: Self::set_depth_range
     pub fn set_depth(&mut self, depth: f32) {
         self.depth = depth;
     }
 
+    /// Sets the range of valid depths, from the nearest depth to the farthest.
+    ///
+    /// Any depth outside of the range, whether set through [`set_depth`] or on
+    /// an individual draw, is clamped to the range. By default, the range is
+    /// unbounded, so any depth other than NaN is valid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `near` is greater than `far` or either is NaN.
+    ///
+    /// [`set_depth`]: Self::set_depth
+    pub fn set_depth_range(&mut self, near: f32, far: f32) {
+        assert!(near <= far, "invalid depth range {}..{}", near, far);
+        self.depth_range = (near, far);
+    }
+
+    /// Creates a [`Shader`] from the given GLSL fragment shader source, e.g. for
+    /// palette swaps, grayscale, or CRT effects.
+    ///
+    /// The source must not contain a `#version` directive, which is added
+    /// automatically. It receives the interpolated vertex color and texture
+    /// coordinates as `in vec4 vert_color` and `in vec2 vert_uv`, the sprite
+    /// being drawn as `uniform sampler2D tex`, and must write the final color
+    /// to `out vec4 out_color`. The default fragment shader is equivalent to
+    /// `out_color = vert_color * texture(tex, vert_uv);`.
+    ///
+    /// Any further uniforms must be declared both in the source and in
+    /// `uniforms`, which gives their names and initial values. Their types are
+    /// inferred from the initial values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the uniforms take up more than 30 floats in total.
+    pub fn create_shader(&self, frag: &str, uniforms: &[(&'static str, UniformValue)]) -> Shader {
+        let len: usize = uniforms.iter().map(|(_, value)| value.len()).sum();
+        assert!(
+            len + 2 <= MAX_UNIFORM_FLOATS,
+            "too many uniform floats ({}, maximum is {})",
+            len,
+            MAX_UNIFORM_FLOATS - 2,
+        );
+        let declared = uniforms.iter().map(|&(name, value)| Uniform {
+            name,
+            format: value.format(),
+        });
+        Shader {
+            inner: Rc::new(ShaderInner {
+                pipeline: create_pipeline(&self.ctx, &self.gl, frag, declared, &["tex"]),
+                uniforms: uniforms
+                    .iter()
+                    .map(|&(name, value)| (name, value.len()))
+                    .collect(),
+            }),
+            values: uniforms.to_vec(),
+        }
+    }
+
+    /// Sets the shader to use when drawing, or `None` for the default shader.
+    ///
+    /// The current uniform values of the shader are used for subsequent draws.
+    /// Draws with different shaders or uniform values cannot be batched
+    /// together.
+    pub fn set_shader(&mut self, shader: Option<&Shader>) {
+        self.shader = shader.cloned();
+    }
+
+    /// Returns the current shader, or `None` for the default shader.
+    pub fn shader(&self) -> Option<&Shader> {
+        self.shader.as_ref()
+    }
+
+    /// Sets the value of a uniform for subsequent draws, overriding the value
+    /// set on the current [`Shader`], e.g. for a time or flash amount that
+    /// changes from draw to draw.
+    ///
+    /// The value stays in effect, including across shader changes, until it
+    /// is set again or [`clear_uniforms`](Self::clear_uniforms) is called.
+    /// Uniforms that are not declared by the shader being drawn with are
+    /// ignored, with a warning printed the first time each name is ignored.
+    /// Draws with different uniform values cannot be batched together.
+    ///
+    /// The default shader declares a single uniform, `flash`, a [`Color`] that
+    /// is mixed into the drawn color by its alpha, e.g. to flash a sprite white
+    /// when it is hit. It defaults to transparent, which leaves the drawn color
+    /// as it is.
+    pub fn set_uniform(&mut self, name: &'static str, value: impl Into<UniformValue>) {
+        let value = value.into();
+        match self.uniforms.iter_mut().find(|(e, _)| *e == name) {
+            Some((_, slot)) => *slot = value,
+            None => self.uniforms.push((name, value)),
+        }
+    }
+
+    /// Returns the value of a uniform set with
+    /// [`set_uniform`](Self::set_uniform), if any.
+    pub fn uniform(&self, name: &str) -> Option<UniformValue> {
+        self.uniforms
+            .iter()
+            .find(|(e, _)| *e == name)
+            .map(|&(_, value)| value)
+    }
+
+    /// Clears all uniform values set with [`set_uniform`](Self::set_uniform),
+    /// so that subsequent draws use the values set on the shader.
+    pub fn clear_uniforms(&mut self) {
+        self.uniforms.clear();
+    }
+
+    /// Sets whether draws are sorted by layer and depth before they are
+    /// committed.
+    ///
+    /// With sorting disabled, everything is drawn purely in the order it was
+    /// submitted, regardless of [`set_layer`](Self::set_layer) and
+    /// [`set_depth`](Self::set_depth). This saves the cost of sorting when
+    /// draws are already submitted back to front.
+    ///
+    /// The default value is `true`.
+    pub fn set_depth_sorting(&mut self, sorting: bool) {
+        self.depth_sorting = sorting;
+    }
+
+    /// Returns whether draws are sorted by layer and depth.
+    pub fn depth_sorting(&self) -> bool {
+        self.depth_sorting
+    }
+
+    /// Sets the blend mode to use when drawing.
+    ///
+    /// Draws with different blend modes cannot be batched together, so
+    /// switching back and forth between blend modes increases the number of
+    /// draw calls. The default blend mode is [`BlendMode::Alpha`].
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend_mode = mode;
+    }
+
+    /// Returns the current blend mode.
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// Restricts subsequent draws to the given rectangle until the matching
+    /// call to [`pop_scissor`](Self::pop_scissor), e.g. for scrollable panels.
+    ///
+    /// The rectangle is in pixels relative to the current viewport and is not
+    /// affected by the camera. If a scissor rectangle is already active, draws
+    /// are restricted to the intersection of both rectangles.
+    ///
+    /// Draws with different scissor rectangles cannot be batched together.
+    pub fn push_scissor(&mut self, x: f32, y: f32, w: f32, h: f32) {
+        let (ox, oy) = self.viewport_origin;
+        let rect = Rect::new(x + ox, y + oy, w.max(0.), h.max(0.));
+        let rect = match self.scissors.last() {
+            Some(top) => top
+                .intersection(&rect)
+                .unwrap_or(Rect::new(x + ox, y + oy, 0., 0.)),
+            None => rect,
+        };
+        self.scissors.push(rect);
+    }
+
+    /// Removes the scissor rectangle added by the last call to
+    /// [`push_scissor`](Self::push_scissor).
+    ///
+    /// # Panics
+    ///
+    /// Panics if no scissor rectangle is active.
+    pub fn pop_scissor(&mut self) {
+        self.scissors
+            .pop()
+            .expect("pop_scissor called without a matching push_scissor");
+    }
+
     /// Draws a rectangle at the given position with the given dimensions.
     pub fn draw_rect(&mut self, x: f32, y: f32, w: f32, h: f32) -> DrawRect {
         DrawRect::new(self, x, y, w, h)
     }
 
+    /// Draws a line from the first position to the second.
+    pub fn draw_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) -> DrawLine {
+        DrawLine::new(self, x1, y1, x2, y2)
+    }
+
+    /// Draws a circle centered at the given position with the given radius.
+    pub fn draw_circle(&mut self, x: f32, y: f32, radius: f32) -> DrawCircle {
+        DrawCircle::new(self, x, y, radius)
+    }
+
+    /// Draws a triangle with the given corners.
+    pub fn draw_triangle(
+        &mut self,
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        x3: f32,
+        y3: f32,
+    ) -> DrawTriangle {
+        DrawTriangle::new(self, [(x1, y1), (x2, y2), (x3, y3)])
+    }
+
+    /// Draws a path connecting the given points in order.
+    pub fn draw_path<'a>(&'a mut self, points: &'a [(f32, f32)]) -> DrawPath {
+        DrawPath::new(self, points)
+    }
+
+    /// Draws an arc centered at the given position with the given radius.
+    ///
+    /// Angles are given in radians, with `0` pointing to the right and
+    /// positive angles going clockwise. Spans larger than a full turn are
+    /// clamped to a full circle.
+    pub fn draw_arc(
+        &mut self,
+        cx: f32,
+        cy: f32,
+        radius: f32,
+        start_angle: f32,
+        end_angle: f32,
+    ) -> DrawArc {
+        DrawArc::new(self, cx, cy, radius, start_angle, end_angle)
+    }
+
     /// Draws a sprite at the given position.
     pub fn draw_sprite(&mut self, x: f32, y: f32, sprite: ResourceHandle<Sprite>) -> DrawSprite {
         DrawSprite::new(self, x, y, sprite)
     }
 
+    /// Draws a sprite as a nine-patch stretched to fill the given rectangle,
+    /// e.g. for scalable UI panels.
+    ///
+    /// The sprite is divided into nine parts by the given insets. The corners
+    /// are drawn unscaled, the edges are stretched along one axis, and the
+    /// center is stretched along both. If the rectangle is smaller than the
+    /// borders, the borders are scaled down to fit.
+    pub fn draw_nine_patch(
+        &mut self,
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        sprite: ResourceHandle<Sprite>,
+        insets: Insets,
+    ) -> DrawNinePatch {
+        DrawNinePatch::new(self, Rect::new(x, y, w, h), sprite, insets)
+    }
+
+    /// Draws the frame with the given index of a [`SpriteSheet`] at the given
+    /// position.
+    ///
+    /// See [`SpriteSheet::frame_rect`] for how frames are indexed.
+    pub fn draw_sprite_frame(
+        &mut self,
+        x: f32,
+        y: f32,
+        sheet: &SpriteSheet,
+        index: u32,
+    ) -> DrawSprite {
+        let rect = self
+            .resource_manager
+            .get(sheet.sprite())
+            .map(|sprite| sheet.frame_rect(&sprite, index));
+        let draw = DrawSprite::new(self, x, y, sheet.sprite());
+        match rect {
+            Some(rect) => draw.source_rect(rect.x, rect.y, rect.w, rect.h),
+            None => draw,
+        }
+    }
+
+    /// Draws the named region of a [`SpriteSheet`] at the given position.
+    ///
+    /// Nothing is drawn and `None` is returned if the sprite sheet has no
+    /// region with the given name.
+    pub fn draw_sprite_region(
+        &mut self,
+        x: f32,
+        y: f32,
+        sheet: &SpriteSheet,
+        name: &str,
+    ) -> Option<DrawSprite> {
+        let rect = sheet.region(name)?;
+        Some(
+            DrawSprite::new(self, x, y, sheet.sprite()).source_rect(rect.x, rect.y, rect.w, rect.h),
+        )
+    }
+
+    /// Draws many instances of the same sprite at once, each with its own
+    /// transform, source rectangle, and color, at the current depth.
+    ///
+    /// This is much faster than calling [`draw_sprite`](Self::draw_sprite) for
+    /// each instance, since the instances are transformed and batched together
+    /// directly. Nothing is drawn if the sprite is not loaded yet.
+    pub fn draw_sprites(&mut self, sprite: ResourceHandle<Sprite>, instances: &[SpriteInstance]) {
+        let (w, h, anchor, (rx, ry, rw, rh)) = match self.resource_manager.get(sprite) {
+            Some(sprite) => (
+                sprite.width as f32,
+                sprite.height as f32,
+                sprite.anchor,
+                sprite.region,
+            ),
+            None => return,
+        };
+
+        // Split the instances into commands whose vertices can each be
+        // addressed by `u16` indices.
+        for chunk in instances.chunks(MAX_VERTICES / 4) {
+            let mut verts = Vec::with_capacity(chunk.len() * 4);
+            let mut indices = Vec::with_capacity(chunk.len() * 6);
+            for instance in chunk {
+                let source = instance.source_rect.unwrap_or(Rect::new(0., 0., w, h));
+                let (dw, dh) = instance.size.unwrap_or((source.w, source.h));
+                let (ox, oy) = instance.origin.unwrap_or(anchor);
+                let (sin, cos) = instance.rotation.sin_cos();
+                let (sx, sy) = (rx + source.x / w * rw, ry + source.y / h * rh);
+                let (sw, sh) = (source.w / w * rw, source.h / h * rh);
+                let color = instance.color.unwrap_or(self.color);
+
+                let corners = [
+                    ((0., 0.), (sx, sy)),
+                    ((dw, 0.), (sx + sw, sy)),
+                    ((dw, dh), (sx + sw, sy + sh)),
+                    ((0., dh), (sx, sy + sh)),
+                ];
+                let base = verts.len() as u16;
+                for ((x, y), uv) in corners {
+                    let (x, y) = (x - ox * dw, y - oy * dh);
+                    let pos = (
+                        instance.pos.0 + x * cos - y * sin,
+                        instance.pos.1 + x * sin + y * cos,
+                    );
+                    verts.push(Vertex::new(pos, color, uv));
+                }
+                indices.extend([0, 3, 1, 1, 3, 2].iter().map(|e| base + e));
+            }
+            self.push_command(Some(sprite), verts, indices, self.depth);
+        }
+    }
+
+    /// Draws arbitrary triangles, e.g. for trails, beams, or deformed sprites,
+    /// at the current depth.
+    ///
+    /// Each vertex is given as `(x, y, color, u, v)`. Positions are in the
+    /// same coordinates as all other drawing, in pixels with the y axis
+    /// pointing down, and are affected by the camera. Texture coordinates
+    /// range from `0.0` to `1.0` across the whole sprite and are ignored if no
+    /// sprite is given, in which case the triangles are filled with their
+    /// vertex colors. Every three indices form a triangle, and triangles are
+    /// drawn regardless of their winding order.
+    ///
+    /// Nothing is drawn if the sprite is not loaded yet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of indices is not a multiple of three, if an index
+    /// is out of bounds of the vertices, or if there are more than 65536
+    /// vertices.
+    pub fn draw_mesh(
+        &mut self,
+        sprite: Option<ResourceHandle<Sprite>>,
+        vertices: &[(f32, f32, Color, f32, f32)],
+        indices: &[u16],
+    ) {
+        assert!(
+            indices.len() % 3 == 0,
+            "number of indices ({}) is not a multiple of three",
+            indices.len()
+        );
+        assert!(
+            vertices.len() <= MAX_VERTICES,
+            "too many vertices ({}, maximum is {})",
+            vertices.len(),
+            MAX_VERTICES
+        );
+        if let Some(&index) = indices.iter().find(|&&e| e as usize >= vertices.len()) {
+            panic!(
+                "index {} out of bounds for {} vertices",
+                index,
+                vertices.len()
+            );
+        }
+
+        let (rx, ry, rw, rh) = match sprite {
+            Some(sprite) => match self.resource_manager.get(sprite) {
+                Some(sprite) => sprite.region,
+                None => return,
+            },
+            None => (0., 0., 1., 1.),
+        };
+        let verts = vertices
+            .iter()
+            .map(|&(x, y, color, u, v)| Vertex::new((x, y), color, (rx + u * rw, ry + v * rh)))
+            .collect();
+        self.push_command(sprite, verts, indices.to_vec(), self.depth);
+    }
+
     /// Ends drawing and commits everything to the screen.
+    ///
+    /// This is called automatically after the frame callbacks of each frame,
+    /// but can be called manually to commit everything drawn so far, e.g.
+    /// before reading back the screen.
+    ///
+    /// Nothing is drawn if the viewport has a width or height of zero, e.g.
+    /// when the window is minimized.
     pub fn end(&mut self) {
+        if !has_area(self.viewport) {
+            self.draw_commands.clear();
+            self.seq = 0;
+            return;
+        }
+
         if self.draw_commands.is_empty() {
             return;
         }
 
-        self.ctx.begin_default_pass(PassAction::Nothing);
+        self.begin_pass(PassAction::Nothing);
 
         self.ctx.set_pipeline(&self.pipeline);
         self.ctx.set_vertex_buffer(&self.vertex_buffer);
         self.ctx.set_index_buffer(&self.index_buffer);
-        self.ctx.set_uniforms(self.viewport);
         self.ctx.set_images(&[&self.blank_image]);
+        match self.debug_mode {
+            DebugMode::Off | DebugMode::Batches => {}
+            DebugMode::Wireframe => self.gl.set_wireframe(true),
+            DebugMode::Overdraw => self.ctx.set_blend(BlendState {
+                op: BlendOp::Add,
+                source: BlendFactor::One,
+                dest: BlendFactor::One,
+            }),
+        }
 
-        self.draw_commands.sort_by(|a, b| {
-            f32::partial_cmp(&b.depth, &a.depth).unwrap_or(std::cmp::Ordering::Equal)
-        });
+        if self.depth_sorting {
+            self.draw_commands
+                .sort_unstable_by_key(|draw_command| draw_command.sort_key());
+        }
+        self.seq = 0;
+
+        // Reuse the allocations of the previous frame.
+        let mut groups = std::mem::take(&mut self.batch_groups);
+        let resource_manager = &self.resource_manager;
+        self.stats.draw_commands += batch_commands(
+            &self.draw_commands,
+            self.debug_mode,
+            |sprite| resource_manager.is_loaded(sprite),
+            &mut groups,
+        );
+        self.draw_commands.clear();
+        for group in &groups {
+            self.stats.draw_calls += self.flush(&group.batches, &group.verts, &group.indices);
+        }
+        self.batch_groups = groups;
+
+        match self.debug_mode {
+            DebugMode::Off | DebugMode::Batches => {}
+            DebugMode::Wireframe => self.gl.set_wireframe(false),
+            DebugMode::Overdraw => self.ctx.set_blend(default_blend()),
+        }
+        self.gl.set_enabled(gl::SCISSOR_TEST, false);
+
+        self.ctx.end_render_pass();
+    }
 
-        let mut batches = Vec::new();
-        let mut curr_sprite = self.draw_commands[0].sprite;
-        let mut begin = 0;
-
-        let mut verts = Vec::new();
-        let mut indices = Vec::new();
-
-        for draw_command in self.draw_commands.drain(..) {
-            if curr_sprite != draw_command.sprite {
-                batches.push(DrawBatch {
-                    sprite: curr_sprite,
-                    start: begin,
-                    count: indices.len() - begin,
-                });
-                curr_sprite = draw_command.sprite;
-                begin = indices.len();
+    fn push_command(
+        &mut self,
+        sprite: Option<ResourceHandle<Sprite>>,
+        mut verts: Vec<Vertex>,
+        indices: Vec<u16>,
+        depth: f32,
+    ) -> &mut DrawCommand {
+        if !self.camera.is_identity() {
+            let camera = self.camera;
+            for vert in &mut verts {
+                *vert = vert.map_pos(|x, y| camera.world_to_screen(x, y));
             }
-            indices.extend(
-                draw_command
-                    .indices
+        }
+        if self.scale != 1. {
+            let scale = self.scale;
+            for vert in &mut verts {
+                *vert = vert.map_pos(|x, y| (x * scale, y * scale));
+            }
+        }
+        self.draw_commands.push(DrawCommand {
+            state: DrawState {
+                sprite,
+                blend_mode: self.blend_mode,
+                scissor: self.scissors.last().copied(),
+                shader: self.shader.as_ref().map(|shader| shader.inner.clone()),
+                uniforms: self
+                    .shader
                     .iter()
+                    .flat_map(|shader| &shader.values)
+                    .chain(&self.uniforms)
                     .copied()
-                    .map(|e| e + verts.len() as u16),
-            );
-            verts.extend_from_slice(&draw_command.verts);
+                    .collect(),
+            },
+            verts,
+            indices,
+            layer: self.layer,
+            depth: depth.max(self.depth_range.0).min(self.depth_range.1),
+            seq: self.seq,
+        });
+        self.seq = self.seq.wrapping_add(1);
+        self.draw_commands.last_mut().unwrap()
+    }
+
+    fn apply_uniforms(
+        &mut self,
+        declared: &[(&'static str, usize)],
+        overrides: &[(&'static str, UniformValue)],
+    ) {
+        for &(name, _) in overrides {
+            if !declared.iter().any(|&(e, _)| e == name) && !self.warned_uniforms.contains(&name) {
+                eprintln!(
+                    "warning: ignoring uniform {:?}, which the shader does not declare",
+                    name
+                );
+                self.warned_uniforms.push(name);
+            }
         }
 
-        batches.push(DrawBatch {
-            sprite: curr_sprite,
-            start: begin,
-            count: indices.len() - begin,
-        });
+        let mut data = [0.; MAX_UNIFORM_FLOATS];
+        data[..2].copy_from_slice(&[self.viewport.0, self.viewport.1]);
+        let mut len = 2;
+        for &(name, size) in declared {
+            if let Some((_, value)) = overrides.iter().rev().find(|(e, _)| *e == name) {
+                value.write_to(&mut data[len..len + size]);
+            }
+            len += size;
+        }
+
+        // Uniforms are passed to the shader as a value of exactly the right
+        // size, so dispatch on the number of floats.
+        macro_rules! set_uniforms {
+            ($($n:literal)*) => {
+                match len {
+                    $($n => {
+                        let mut uniforms = [0_f32; $n];
+                        uniforms.copy_from_slice(&data[..$n]);
+                        self.ctx.set_uniforms(uniforms);
+                    })*
+                    _ => unreachable!(),
+                }
+            };
+        }
+        set_uniforms!(2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32);
+    }
 
-        self.vertex_buffer.update(&verts);
-        self.index_buffer.update(&indices);
+    /// Replaces the vertex and index buffers with larger ones if they are
+    /// smaller than the given sizes in bytes, doubling their size until they
+    /// fit.
+    ///
+    /// The buffers must be bound again afterwards.
+    fn reserve_buffers(&mut self, vertex_bytes: usize, index_bytes: usize) {
+        let (vertex_capacity, index_capacity) = &mut self.buffer_capacity;
+        if vertex_bytes > *vertex_capacity {
+            *vertex_capacity = grown_capacity(*vertex_capacity, vertex_bytes);
+            self.vertex_buffer =
+                self.ctx
+                    .create_buffer(BufferKind::Vertex, BufferUsage::Stream, *vertex_capacity);
+        }
+        if index_bytes > *index_capacity {
+            *index_capacity = grown_capacity(*index_capacity, index_bytes);
+            self.index_buffer =
+                self.ctx
+                    .create_buffer(BufferKind::Index, BufferUsage::Stream, *index_capacity);
+        }
+    }
 
+    /// Uploads the given vertices and indices and draws the given batches,
+    /// returning the number of draw calls issued.
+    fn flush(&mut self, batches: &[DrawBatch], verts: &[BatchVertex], indices: &[u16]) -> usize {
+        self.reserve_buffers(std::mem::size_of_val(verts), std::mem::size_of_val(indices));
+        self.vertex_buffer.update(verts);
+        self.index_buffer.update(indices);
+        self.stats.vertices += verts.len();
+        self.stats.indices += indices.len();
+
+        let mut draw_calls = 0;
+        let mut curr_shader = None;
         for batch in batches {
-            if let Some(sprite) = batch.sprite {
-                if let Some(sprite) = self.resource_manager.get::<Sprite>(sprite) {
-                    self.ctx.set_images(&[&sprite.image]);
+            if batch.count == 0 {
+                continue;
+            }
+            let shader = batch.state.shader.as_ref();
+            if curr_shader.map(Rc::as_ptr) != shader.map(Rc::as_ptr) || draw_calls == 0 {
+                let pipeline = shader.map_or(&self.pipeline, |shader| &shader.pipeline);
+                self.ctx.set_pipeline(pipeline);
+                self.ctx.set_vertex_buffer(&self.vertex_buffer);
+                self.ctx.set_index_buffer(&self.index_buffer);
+                curr_shader = shader;
+            }
+            let flat = matches!(self.debug_mode, DebugMode::Overdraw | DebugMode::Batches);
+            // Custom shaders sample from the batch's sprite, while the default
+            // shader samples from the blank image and the sprites in the slots.
+            let sprites = match &batch.state.sprite {
+                Some(sprite) if shader.is_some() => std::slice::from_ref(sprite),
+                _ if shader.is_some() => &[],
+                _ => &batch.sprites[..],
+            };
+            let sprites: Option<Vec<_>> = sprites
+                .iter()
+                .map(|&sprite| self.resource_manager.get::<Sprite>(sprite))
+                .collect();
+            let sprites = match sprites {
+                Some(sprites) => sprites,
+                None => continue,
+            };
+            let mut images = Vec::with_capacity(TEXTURE_SLOTS);
+            if shader.is_none() || sprites.is_empty() {
+                images.push(&self.blank_image);
+            }
+            images.extend(sprites.iter().map(|sprite| {
+                if flat {
+                    &self.blank_image
                 } else {
-                    continue;
+                    &*sprite.image
                 }
-            } else {
-                self.ctx.set_images(&[&self.blank_image]);
+            }));
+            if shader.is_none() {
+                images.resize(TEXTURE_SLOTS, &self.blank_image);
             }
+            self.ctx.set_images(&images);
+            // The overdraw visualization replaces blending entirely.
+            if self.debug_mode != DebugMode::Overdraw {
+                self.ctx.set_blend(batch.state.blend_mode.blend_state());
+            }
+            if let Some(scissor) = batch.state.scissor {
+                self.gl.set_scissor(
+                    scissor.x as i32,
+                    (self.surface.1 - scissor.y - scissor.h) as i32,
+                    scissor.w as i32,
+                    scissor.h as i32,
+                );
+            }
+            self.gl
+                .set_enabled(gl::SCISSOR_TEST, batch.state.scissor.is_some());
+            let declared = shader.map_or(&shader::UNIFORMS[..], |shader| &shader.uniforms);
+            self.apply_uniforms(declared, &batch.state.uniforms);
             self.ctx.draw(batch.start, batch.count, 1);
+            draw_calls += 1;
         }
+        draw_calls
+    }
+}
 
-        self.ctx.end_render_pass();
+/// Creates a pipeline drawing with the default vertex shader and the given
+/// fragment shader, which declares the given uniforms after `viewport_size`.
+fn create_pipeline(
+    ctx: &Context,
+    gl: &Gl,
+    frag: &str,
+    uniforms: impl IntoIterator<Item = Uniform>,
+    images: &[&'static str],
+) -> Pipeline {
+    let uniforms: Vec<_> = std::iter::once(Uniform {
+        name: "viewport_size",
+        format: UniformFormat::Float2,
+    })
+    .chain(uniforms)
+    .collect();
+    let shader = ctx.create_shader(
+        &shader::with_version(gl, shader::VERT),
+        &shader::with_version(gl, frag),
+        &uniforms,
+        &images
+            .iter()
+            .map(|&name| ImageUniform { name })
+            .collect::<Vec<_>>(),
+    );
+    ctx.create_pipeline(
+        shader,
+        &[BufferLayout::default()],
+        &[
+            VertexAttribute {
+                name: "pos",
+                format: VertexFormat::Float2,
+                buffer_index: 0,
+            },
+            VertexAttribute {
+                name: "color",
+                format: VertexFormat::Float4,
+                buffer_index: 0,
+            },
+            VertexAttribute {
+                name: "uv",
+                format: VertexFormat::Float2,
+                buffer_index: 0,
+            },
+            VertexAttribute {
+                name: "slot",
+                format: VertexFormat::Float1,
+                buffer_index: 0,
+            },
+        ],
+    )
+}
+
+/// Returns the blend state used for normal drawing.
+fn default_blend() -> BlendState {
+    BlendState {
+        op: BlendOp::Add,
+        source: BlendFactor::SourceAlpha,
+        dest: BlendFactor::OneMinusSourceAlpha,
+    }
+}
+
+/// Warns about a draw command with more vertices than `u16` indices can
+/// address, the first time one is skipped.
+fn warn_oversized_draw(verts: usize) {
+    static WARNED: AtomicBool = AtomicBool::new(false);
+    if !WARNED.swap(true, Ordering::Relaxed) {
+        eprintln!(
+            "warning: skipping a draw with {} vertices, more than the {} that can be indexed",
+            verts, MAX_VERTICES,
+        );
     }
 }
 
 #[cfg(feature = "text")]
-use crate::text::Font;
+use crate::text::{Font, FontOptions};
 
 #[cfg(feature = "text")]
 impl Graphics {
     /// Draws the given text at the given position.
+    ///
+    /// The glyphs of the text are drawn as a unit: at equal depth, the text
+    /// appears on top of anything submitted before it and below anything
+    /// submitted after it.
     pub fn draw_text<'a>(&'a mut self, x: f32, y: f32, text: &'a str) -> DrawText {
         DrawText::new(self, x, y, text)
     }
@@ -323,12 +2090,18 @@ impl Graphics {
     }
 
     /// Gets the default font, loading it if it hasn't been loaded already.
+    ///
+    /// The default font is a pixel font, so it is sampled with
+    /// [`ImageFilter::Nearest`].
     pub fn default_font(&mut self) -> ResourceHandle<Font> {
         *self.default_font.get_or_insert_with(|| {
-            let font = self.resource_manager.allocate();
-            self.resource_manager
-                .set(font, Font::new(include_bytes!("graphics/monogram.otf")));
-            font
+            self.resource_manager.allocate_with(|| {
+                let options = FontOptions {
+                    filter: ImageFilter::Nearest,
+                    ..Default::default()
+                };
+                Font::new_with_options(include_bytes!("graphics/monogram.otf"), options)
+            })
         })
     }
 
@@ -338,4 +2111,367 @@ impl Graphics {
     pub fn set_default_font(&mut self, font: ResourceHandle<Font>) {
         self.default_font = Some(font);
     }
+
+    /// Sets whether the glyphs of subsequently drawn text are rounded to whole
+    /// pixels, e.g. for user interfaces.
+    ///
+    /// See [`DrawText::snap_to_pixel`] for the tradeoff. The default value is
+    /// `false`.
+    pub fn set_snap_text(&mut self, snap: bool) {
+        self.snap_text = snap;
+    }
+
+    /// Returns whether the glyphs of drawn text are rounded to whole pixels by
+    /// default.
+    pub fn snap_text(&self) -> bool {
+        self.snap_text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUAD_INDICES: [u16; 6] = [0, 1, 2, 0, 2, 3];
+
+    /// Returns the draw command of a unit quad drawn with the given sprite.
+    fn quad(sprite: Option<ResourceHandle<Sprite>>, seq: u32) -> DrawCommand {
+        let corners = [(0., 0.), (1., 0.), (1., 1.), (0., 1.)];
+        DrawCommand {
+            state: DrawState {
+                sprite,
+                blend_mode: BlendMode::Alpha,
+                scissor: None,
+                shader: None,
+                uniforms: Vec::new(),
+            },
+            verts: corners
+                .iter()
+                .map(|&pos| Vertex::new(pos, Color::WHITE, pos))
+                .collect(),
+            indices: QUAD_INDICES.to_vec(),
+            layer: 0,
+            depth: 0.,
+            seq,
+        }
+    }
+
+    #[test]
+    fn batches_split_before_indices_overflow() {
+        const QUADS: usize = 20_000;
+
+        let draw_commands: Vec<_> = (0..QUADS as u32).map(|seq| quad(None, seq)).collect();
+        let mut groups = Vec::new();
+        let batched = batch_commands(&draw_commands, DebugMode::Off, |_| true, &mut groups);
+
+        assert_eq!(batched, QUADS);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(
+            groups.iter().map(|group| group.verts.len()).sum::<usize>(),
+            QUADS * 4
+        );
+        for group in &groups {
+            assert!(group.verts.len() <= MAX_VERTICES);
+            assert_eq!(group.indices.len(), group.verts.len() / 4 * 6);
+            // Every quad refers to its own four vertices.
+            for (i, quad) in group.indices.chunks(6).enumerate() {
+                let base = (i * 4) as u16;
+                assert_eq!(quad, QUAD_INDICES.map(|e| base + e));
+            }
+            // The batches of a group cover its indices without gaps.
+            let mut start = 0;
+            for batch in &group.batches {
+                assert_eq!(batch.start, start);
+                start += batch.count;
+            }
+            assert_eq!(start, group.indices.len());
+        }
+
+        // The allocations are reused, and surplus groups are dropped.
+        let batched = batch_commands(&draw_commands[..10], DebugMode::Off, |_| true, &mut groups);
+        assert_eq!(batched, 10);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].verts.len(), 40);
+    }
+
+    #[test]
+    fn unloaded_sprites_are_skipped() {
+        let resource_manager = ResourceManager::new();
+        let sprite = resource_manager.allocate::<Sprite>();
+        let draw_commands = [quad(None, 0), quad(Some(sprite), 1), quad(None, 2)];
+        let mut checked = Vec::new();
+        let mut groups = Vec::new();
+        let batched = batch_commands(
+            &draw_commands,
+            DebugMode::Off,
+            |sprite| {
+                checked.push(sprite);
+                false
+            },
+            &mut groups,
+        );
+
+        assert_eq!(checked, [sprite]);
+        assert_eq!(batched, 2);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].verts.len(), 8);
+        assert_eq!(groups[0].indices.len(), 12);
+        assert!(groups[0]
+            .batches
+            .iter()
+            .all(|batch| batch.state.sprite.is_none() && batch.sprites.is_empty()));
+    }
+
+    #[test]
+    fn text_stacks_as_a_unit_between_rects() {
+        let resource_manager = ResourceManager::new();
+        let atlases = [
+            resource_manager.allocate::<Sprite>(),
+            resource_manager.allocate::<Sprite>(),
+        ];
+        let draw = |sprite, color, seq| {
+            let mut draw_command = quad(sprite, seq);
+            for vert in &mut draw_command.verts {
+                *vert = vert.with_color(color);
+            }
+            draw_command.depth = 0.5;
+            draw_command
+        };
+        // A rect, then text whose glyphs span two atlases, then another rect,
+        // all at the same depth.
+        let mut draw_commands = vec![
+            draw(None, Color::RED, 0),
+            draw(Some(atlases[0]), Color::WHITE, 1),
+            draw(Some(atlases[1]), Color::WHITE, 2),
+            draw(Some(atlases[0]), Color::WHITE, 3),
+            draw(None, Color::BLUE, 4),
+        ];
+        draw_commands.reverse();
+        draw_commands.sort_unstable_by_key(|draw_command| draw_command.sort_key());
+
+        let mut groups = Vec::new();
+        batch_commands(&draw_commands, DebugMode::Off, |_| true, &mut groups);
+        let group = &groups[0];
+        // The first vertex of each quad, in the order they are drawn.
+        let drawn: Vec<_> = group
+            .indices
+            .chunks(6)
+            .map(|quad| group.verts[quad[0] as usize].vertex)
+            .collect();
+        let expected: Vec<_> = [
+            Color::RED,
+            Color::WHITE,
+            Color::WHITE,
+            Color::WHITE,
+            Color::BLUE,
+        ]
+        .iter()
+        .map(|&color| Vertex::new((0., 0.), color, (0., 0.)))
+        .collect();
+        assert_eq!(drawn, expected);
+    }
+
+    #[cfg(feature = "null-backend")]
+    #[test]
+    fn null_backend_records_stats() {
+        let resource_manager = ResourceManager::new();
+        let mut g = Graphics::new(&Rc::new(Context::new()), Gl::new(), &resource_manager);
+        g.set_viewport((800, 600));
+        let sprite = resource_manager.allocate_with(|| {
+            Sprite::new(
+                &g.ctx,
+                2,
+                2,
+                ImageFormat::Rgba8,
+                ImageFilter::Nearest,
+                ImageWrap::Clamp,
+                [255; 16],
+            )
+        });
+
+        g.begin_frame();
+        for i in 0..12 {
+            g.draw_sprite(i as f32 * 2., 0., sprite);
+        }
+        g.set_blend_mode(BlendMode::Additive);
+        g.draw_rect(0., 0., 10., 10.);
+        g.set_blend_mode(BlendMode::Alpha);
+        g.draw_rect(10., 0., 10., 10.);
+        g.end();
+
+        assert_eq!(
+            g.stats(),
+            RenderStats {
+                draw_commands: 14,
+                draw_calls: 3,
+                vertices: 14 * 4,
+                indices: 14 * 6,
+            }
+        );
+    }
+
+    #[cfg(feature = "null-backend")]
+    #[test]
+    fn resize_while_rendering_to_target() {
+        let resource_manager = ResourceManager::new();
+        let mut g = Graphics::new(&Rc::new(Context::new()), Gl::new(), &resource_manager);
+        g.set_viewport((800, 600));
+        let target = g.create_render_target(64, 32);
+
+        g.set_render_target(Some(&target));
+        g.set_viewport((1024, 768));
+        assert_eq!(g.surface, (64., 32.));
+        assert_eq!(g.viewport, (64., 32.));
+
+        g.set_render_target(None);
+        assert_eq!(g.surface, (1024., 768.));
+        assert_eq!(g.viewport, (1024., 768.));
+        g.delete_render_target(target);
+    }
+
+    #[test]
+    fn zero_size_viewport_is_not_drawn() {
+        // A minimized window reports a drawable size of zero.
+        assert!(!has_area((0., 0.)));
+        assert!(!has_area((800., 0.)));
+        assert!(!has_area((0., 600.)));
+        assert!(!has_area((f32::NAN, 600.)));
+        assert!(has_area((800., 600.)));
+        assert!(has_area((1., 1.)));
+    }
+
+    #[test]
+    fn premultiply_alpha_known_pixels() {
+        let mut data = [
+            255, 255, 255, 255, // opaque
+            255, 128, 0, 0, // transparent
+            255, 128, 64, 128, // half transparent
+            10, 20, 30, 1, // nearly transparent
+        ];
+        premultiply_alpha(&mut data);
+        assert_eq!(
+            data,
+            [255, 255, 255, 255, 0, 0, 0, 0, 128, 64, 32, 128, 0, 0, 0, 1]
+        );
+    }
+
+    /// Blends the given color over the given opaque color like the GPU would
+    /// with the given blend mode.
+    fn blend(mode: BlendMode, src: [f32; 4], dst: [f32; 3]) -> [f32; 3] {
+        let state = mode.blend_state();
+        assert!(matches!(state.op, BlendOp::Add));
+        let factor = |factor, dst: f32| match factor {
+            BlendFactor::Zero => 0.,
+            BlendFactor::One => 1.,
+            BlendFactor::SourceAlpha => src[3],
+            BlendFactor::OneMinusSourceAlpha => 1. - src[3],
+            BlendFactor::DestinationColor => dst,
+            _ => unimplemented!(),
+        };
+        [0, 1, 2]
+            .map(|i| src[i] * factor(state.source, dst[i]) + dst[i] * factor(state.dest, dst[i]))
+    }
+
+    #[test]
+    fn premultiplied_edges_have_no_halo() {
+        // An opaque white texel next to a transparent one, sampled halfway
+        // between them by linear filtering and drawn over white.
+        let straight = [255, 255, 255, 255, 0, 0, 0, 0];
+        let mut premultiplied = straight;
+        premultiply_alpha(&mut premultiplied);
+        let filter = |texels: [u8; 8]| {
+            [0, 1, 2, 3].map(|i| (texels[i] as f32 + texels[i + 4] as f32) / 2. / 255.)
+        };
+        let white = [1., 1., 1.];
+
+        // The transparent texel's black bleeds into the edge.
+        assert_eq!(blend(BlendMode::Alpha, filter(straight), white), [0.75; 3]);
+        assert_eq!(
+            blend(BlendMode::Premultiplied, filter(premultiplied), white),
+            white
+        );
+    }
+
+    #[test]
+    fn buffers_grow_by_doubling() {
+        let capacity = VERTEX_CAPACITY * std::mem::size_of::<BatchVertex>();
+        assert_eq!(grown_capacity(capacity, capacity), capacity);
+        assert_eq!(grown_capacity(capacity, capacity + 1), capacity * 2);
+        assert_eq!(grown_capacity(capacity, capacity * 2), capacity * 2);
+        assert_eq!(grown_capacity(capacity, capacity * 3), capacity * 4);
+
+        // A full batch of vertices fits after growing.
+        let full_batch = MAX_VERTICES * std::mem::size_of::<BatchVertex>();
+        let grown = grown_capacity(capacity, full_batch);
+        assert!(grown >= full_batch && grown / 2 < full_batch);
+        assert_eq!(grown % capacity, 0);
+    }
+    #[test]
+    fn many_quads_split_into_full_groups() {
+        const QUADS: usize = 50_000;
+        const QUADS_PER_GROUP: usize = MAX_VERTICES / 4;
+
+        let draw_commands: Vec<_> = (0..QUADS as u32).map(|seq| quad(None, seq)).collect();
+        let mut groups = Vec::new();
+        let batched = batch_commands(&draw_commands, DebugMode::Off, |_| true, &mut groups);
+        assert_eq!(batched, QUADS);
+        let sizes: Vec<_> = groups.iter().map(|group| group.verts.len() / 4).collect();
+        assert_eq!(
+            sizes,
+            [
+                QUADS_PER_GROUP,
+                QUADS_PER_GROUP,
+                QUADS_PER_GROUP,
+                QUADS - 3 * QUADS_PER_GROUP
+            ]
+        );
+
+        // Like `reserve_buffers`, the buffers only grow for the first group,
+        // and every group fits once they have.
+        let vertex_size = std::mem::size_of::<BatchVertex>();
+        let mut capacity = (VERTEX_CAPACITY * vertex_size, INDEX_CAPACITY * 2);
+        for group in &groups {
+            let required = (
+                std::mem::size_of_val(&group.verts[..]),
+                std::mem::size_of_val(&group.indices[..]),
+            );
+            capacity.0 = grown_capacity(capacity.0, required.0);
+            capacity.1 = grown_capacity(capacity.1, required.1);
+            assert!(required.0 <= capacity.0 && required.1 <= capacity.1);
+        }
+        assert_eq!(
+            capacity,
+            (VERTEX_CAPACITY * 16 * vertex_size, INDEX_CAPACITY * 16 * 2)
+        );
+    }
+
+    #[cfg(feature = "null-backend")]
+    #[test]
+    fn many_rects_are_flushed_in_groups() {
+        const RECTS: usize = 50_000;
+
+        let resource_manager = ResourceManager::new();
+        let mut g = Graphics::new(&Rc::new(Context::new()), Gl::new(), &resource_manager);
+        g.set_viewport((800, 600));
+        g.begin_frame();
+        for i in 0..RECTS {
+            g.draw_rect(i as f32 % 800., 0., 1., 1.);
+        }
+        g.end();
+
+        assert_eq!(
+            g.stats(),
+            RenderStats {
+                draw_commands: RECTS,
+                draw_calls: 4,
+                vertices: RECTS * 4,
+                indices: RECTS * 6,
+            }
+        );
+        let vertex_size = std::mem::size_of::<BatchVertex>();
+        assert_eq!(
+            g.buffer_capacity,
+            (VERTEX_CAPACITY * 16 * vertex_size, INDEX_CAPACITY * 16 * 2)
+        );
+    }
 }
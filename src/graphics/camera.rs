@@ -0,0 +1,74 @@
+/// A 2D camera that transforms world coordinates into screen coordinates.
+///
+/// The camera shows the point [`target`](Self::target) of the world at the
+/// point [`offset`](Self::offset) of the screen, zoomed and rotated around it.
+/// The default camera maps world coordinates directly to screen coordinates.
+///
+/// Set the camera used for drawing with [`Graphics::set_camera`].
+///
+/// [`Graphics::set_camera`]: super::Graphics::set_camera
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Camera {
+    /// The point of the world the camera is focused on.
+    pub target: (f32, f32),
+    /// The point of the screen the target is shown at, in pixels relative to
+    /// the top-left corner of the viewport, e.g. the center of the viewport.
+    pub offset: (f32, f32),
+    /// The zoom factor, where values greater than `1` zoom in.
+    pub zoom: f32,
+    /// The rotation of the world around the target, in radians, with positive
+    /// angles going clockwise.
+    pub rotation: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            target: (0., 0.),
+            offset: (0., 0.),
+            zoom: 1.,
+            rotation: 0.,
+        }
+    }
+}
+
+impl Camera {
+    /// Creates a new camera that shows the given point of the world at the
+    /// given point of the screen, without zoom or rotation.
+    pub fn new(target: (f32, f32), offset: (f32, f32)) -> Camera {
+        Camera {
+            target,
+            offset,
+            ..Default::default()
+        }
+    }
+
+    /// Converts the given position in world coordinates to screen coordinates.
+    pub fn world_to_screen(&self, x: f32, y: f32) -> (f32, f32) {
+        let (sin, cos) = self.rotation.sin_cos();
+        let (x, y) = (
+            (x - self.target.0) * self.zoom,
+            (y - self.target.1) * self.zoom,
+        );
+        (
+            self.offset.0 + x * cos - y * sin,
+            self.offset.1 + x * sin + y * cos,
+        )
+    }
+
+    /// Converts the given position in screen coordinates to world coordinates.
+    ///
+    /// This is the inverse of [`world_to_screen`](Self::world_to_screen).
+    pub fn screen_to_world(&self, x: f32, y: f32) -> (f32, f32) {
+        let (sin, cos) = self.rotation.sin_cos();
+        let (x, y) = (x - self.offset.0, y - self.offset.1);
+        (
+            self.target.0 + (x * cos + y * sin) / self.zoom,
+            self.target.1 + (-x * sin + y * cos) / self.zoom,
+        )
+    }
+
+    pub(super) fn is_identity(&self) -> bool {
+        self.target == self.offset && self.zoom == 1. && self.rotation == 0.
+    }
+}
@@ -1,3 +1,5 @@
+use std::ops::Mul;
+
 /// A linear RGBA color represented by 4 [f32]s.
 #[repr(C)]
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -22,6 +24,111 @@ impl Color {
     pub const fn from_rgb(r: f32, g: f32, b: f32) -> Color {
         Color { r, g, b, a: 1. }
     }
+
+    /// Creates a new color from the given sRGB-encoded components, converting
+    /// them to linear, with an alpha of 1.
+    pub fn from_srgb(r: f32, g: f32, b: f32) -> Color {
+        Color::from_srgba(r, g, b, 1.)
+    }
+
+    /// Creates a new color from the given sRGB-encoded components, converting
+    /// them to linear. The alpha component is not converted.
+    pub fn from_srgba(r: f32, g: f32, b: f32, a: f32) -> Color {
+        Color {
+            r: srgb_to_linear(r),
+            g: srgb_to_linear(g),
+            b: srgb_to_linear(b),
+            a,
+        }
+    }
+
+    /// Returns the components of the color encoded as sRGB. The alpha
+    /// component is not converted.
+    pub fn to_srgb(self) -> (f32, f32, f32, f32) {
+        (
+            linear_to_srgb(self.r),
+            linear_to_srgb(self.g),
+            linear_to_srgb(self.b),
+            self.a,
+        )
+    }
+
+    /// Linearly interpolates between the color and the given color, where a
+    /// `t` of 0 returns this color and a `t` of 1 returns the other color.
+    ///
+    /// `t` is clamped to the range `[0, 1]`.
+    pub fn lerp(self, other: Color, t: f32) -> Color {
+        let t = t.clamp(0., 1.);
+        Color {
+            r: self.r + (other.r - self.r) * t,
+            g: self.g + (other.g - self.g) * t,
+            b: self.b + (other.b - self.b) * t,
+            a: self.a + (other.a - self.a) * t,
+        }
+    }
+
+    /// Returns the color with its alpha component replaced by the given alpha.
+    pub fn with_alpha(self, a: f32) -> Color {
+        Color { a, ..self }
+    }
+
+    /// Returns the color with each component clamped to the range `[0, 1]`.
+    ///
+    /// NaN components are left as they are.
+    pub fn clamp(self) -> Color {
+        Color {
+            r: self.r.clamp(0., 1.),
+            g: self.g.clamp(0., 1.),
+            b: self.b.clamp(0., 1.),
+            a: self.a.clamp(0., 1.),
+        }
+    }
+
+    /// Returns the color with each component clamped to the range `[0, 1]`.
+    ///
+    /// This is an alias for [`clamp`](Self::clamp).
+    pub fn saturate(self) -> Color {
+        self.clamp()
+    }
+
+    /// Returns true if none of the color's components are infinite or NaN.
+    pub fn is_finite(&self) -> bool {
+        self.r.is_finite() && self.g.is_finite() && self.b.is_finite() && self.a.is_finite()
+    }
+}
+
+/// Multiplies the red, green, and blue components of the color by the given
+/// factor, e.g. to brighten or darken it. The alpha component is left as it is.
+impl Mul<f32> for Color {
+    type Output = Color;
+
+    fn mul(self, factor: f32) -> Color {
+        Color {
+            r: self.r * factor,
+            g: self.g * factor,
+            b: self.b * factor,
+            a: self.a,
+        }
+    }
+}
+
+/// Converts an sRGB-encoded component to linear using the piecewise sRGB
+/// transfer function.
+fn srgb_to_linear(x: f32) -> f32 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a linear component to sRGB, the inverse of [`srgb_to_linear`].
+fn linear_to_srgb(x: f32) -> f32 {
+    if x <= 0.0031308 {
+        x * 12.92
+    } else {
+        1.055 * x.powf(1. / 2.4) - 0.055
+    }
 }
 
 #[allow(missing_docs)]
@@ -36,3 +143,56 @@ impl Color {
     pub const MAGENTA: Color = Color::from_rgb(1., 0., 1.);
     pub const TRANSPARENT: Color = Color::from_rgba(0., 0., 0., 0.);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn srgb_endpoints() {
+        assert_eq!(srgb_to_linear(0.), 0.);
+        assert_eq!(linear_to_srgb(0.), 0.);
+        assert_close(srgb_to_linear(1.), 1.);
+        assert_close(linear_to_srgb(1.), 1.);
+    }
+
+    #[test]
+    fn srgb_round_trip() {
+        let linear = srgb_to_linear(0.5);
+        assert_close(linear, 0.214_041);
+        assert_close(linear_to_srgb(linear), 0.5);
+
+        let (r, g, b, a) = Color::from_srgba(0.5, 0.5, 0.5, 0.5).to_srgb();
+        assert_close(r, 0.5);
+        assert_close(g, 0.5);
+        assert_close(b, 0.5);
+        assert_eq!(a, 0.5);
+    }
+
+    #[test]
+    fn srgb_linear_segment_threshold() {
+        // Below the threshold, the transfer function is linear.
+        assert_close(srgb_to_linear(0.02), 0.02 / 12.92);
+        assert_close(linear_to_srgb(0.002), 0.002 * 12.92);
+
+        // Both segments meet at the threshold.
+        assert_close(srgb_to_linear(0.04045), 0.003_130_8);
+        assert_close(srgb_to_linear(0.040_451), srgb_to_linear(0.04045));
+        assert_close(linear_to_srgb(0.003_130_8), 0.04045);
+        assert_close(linear_to_srgb(0.003_130_9), linear_to_srgb(0.003_130_8));
+    }
+
+    #[test]
+    fn lerp_black_to_white() {
+        assert_eq!(
+            Color::BLACK.lerp(Color::WHITE, 0.5),
+            Color::from_rgb(0.5, 0.5, 0.5)
+        );
+        assert_eq!(Color::BLACK.lerp(Color::WHITE, 0.), Color::BLACK);
+        assert_eq!(Color::BLACK.lerp(Color::WHITE, 2.), Color::WHITE);
+    }
+}
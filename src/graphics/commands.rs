@@ -1,8 +1,10 @@
 //! Drawing commands.
 
-use super::{Color, Graphics, Sprite};
+use std::rc::Rc;
+
+use super::{BlendMode, Color, Graphics, Insets, Rect, ShaderInner, Sprite, UniformValue};
 use crate::assets::ResourceHandle;
-use crate::text::Font;
+use crate::text::{Font, TextAlign, TextOptions};
 
 #[repr(C)]
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -12,11 +14,71 @@ pub(super) struct Vertex {
     uv: (f32, f32),
 }
 
-pub(super) struct DrawCommand {
+impl Vertex {
+    pub(super) fn new(pos: (f32, f32), color: Color, uv: (f32, f32)) -> Vertex {
+        Vertex { pos, color, uv }
+    }
+
+    /// Returns the vertex with its position transformed by the given function.
+    pub(super) fn map_pos(self, f: impl FnOnce(f32, f32) -> (f32, f32)) -> Vertex {
+        Vertex {
+            pos: f(self.pos.0, self.pos.1),
+            ..self
+        }
+    }
+
+    /// Returns the vertex with its color replaced by the given color.
+    pub(super) fn with_color(self, color: Color) -> Vertex {
+        Vertex { color, ..self }
+    }
+}
+
+/// The state a draw command is drawn with. See [`DrawState::batches_with`].
+#[derive(Clone, PartialEq, Debug)]
+pub(super) struct DrawState {
     pub sprite: Option<ResourceHandle<Sprite>>,
+    pub blend_mode: BlendMode,
+    pub scissor: Option<Rect>,
+    pub shader: Option<Rc<ShaderInner>>,
+    pub uniforms: Vec<(&'static str, UniformValue)>,
+}
+
+impl DrawState {
+    /// Returns whether draw commands with the given state can be batched
+    /// together with draw commands with this state.
+    ///
+    /// Draws with the default shader can be batched regardless of their
+    /// sprites, which are bound to separate texture slots.
+    pub fn batches_with(&self, other: &DrawState) -> bool {
+        self.blend_mode == other.blend_mode
+            && self.scissor == other.scissor
+            && self.shader == other.shader
+            && self.uniforms == other.uniforms
+            && (self.shader.is_none() || self.sprite == other.sprite)
+    }
+}
+
+pub(super) struct DrawCommand {
+    pub state: DrawState,
     pub verts: Vec<Vertex>,
     pub indices: Vec<u16>,
+    pub layer: i16,
     pub depth: f32,
+    pub seq: u32,
+}
+
+impl DrawCommand {
+    /// Returns the key draw commands are sorted by, in ascending order.
+    ///
+    /// Commands are sorted by layer first, then by depth (with greater depths
+    /// drawn first), and finally by submission order.
+    pub fn sort_key(&self) -> (i16, std::cmp::Reverse<i32>, u32) {
+        // Maps the bits of the depth to an integer with the same total order as
+        // the float, so that NaNs sort consistently.
+        let bits = self.depth.to_bits() as i32;
+        let depth = bits ^ (((bits >> 31) as u32) >> 1) as i32;
+        (self.layer, std::cmp::Reverse(depth), self.seq)
+    }
 }
 
 /// A rectangle to be drawn.
@@ -65,9 +127,9 @@ impl<'a> DrawRect<'a> {
         let color = self.color.unwrap_or(self.g.color);
         let depth = self.depth.unwrap_or(self.g.depth);
 
-        self.g.draw_commands.push(DrawCommand {
-            sprite: None,
-            verts: vec![
+        self.g.push_command(
+            None,
+            vec![
                 Vertex {
                     pos: (x, y),
                     color,
@@ -89,9 +151,9 @@ impl<'a> DrawRect<'a> {
                     uv: (0., 1.),
                 },
             ],
-            indices: vec![0, 3, 1, 1, 3, 2],
+            vec![0, 3, 1, 1, 3, 2],
             depth,
-        });
+        );
     }
 }
 
@@ -101,6 +163,571 @@ impl Drop for DrawRect<'_> {
     }
 }
 
+/// A line to be drawn.
+///
+/// This is a builder struct that allows you to specify extra parameters for the
+/// line via method chaining. The line is commited to the [`Graphics`] struct
+/// when [`DrawLine`] is dropped.
+///
+/// This struct is created using the [`draw_line`] method on [`Graphics`].
+///
+/// [`draw_line`]: Graphics::draw_line
+pub struct DrawLine<'a> {
+    g: &'a mut Graphics,
+    start: (f32, f32),
+    end: (f32, f32),
+    thickness: f32,
+    color: Option<Color>,
+    depth: Option<f32>,
+}
+
+impl<'a> DrawLine<'a> {
+    pub(super) fn new(g: &'a mut Graphics, x1: f32, y1: f32, x2: f32, y2: f32) -> Self {
+        DrawLine {
+            g,
+            start: (x1, y1),
+            end: (x2, y2),
+            thickness: 1.,
+            color: None,
+            depth: None,
+        }
+    }
+
+    /// Sets the thickness of the line. The default thickness is `1`.
+    pub fn thickness(mut self, thickness: f32) -> Self {
+        self.thickness = thickness;
+        self
+    }
+
+    /// Sets the color of the line.
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Sets the depth of the line.
+    pub fn depth(mut self, depth: f32) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    fn commit(&mut self) {
+        let (x1, y1) = self.start;
+        let (x2, y2) = self.end;
+        let (dx, dy) = (x2 - x1, y2 - y1);
+        let len = (dx * dx + dy * dy).sqrt();
+        if len == 0. || !len.is_finite() {
+            return;
+        }
+        // Offset both ends by half the thickness along the normal.
+        let (nx, ny) = (
+            -dy / len * self.thickness / 2.,
+            dx / len * self.thickness / 2.,
+        );
+        let color = self.color.unwrap_or(self.g.color);
+        let depth = self.depth.unwrap_or(self.g.depth);
+
+        self.g.push_command(
+            None,
+            vec![
+                Vertex {
+                    pos: (x1 + nx, y1 + ny),
+                    color,
+                    uv: (0., 0.),
+                },
+                Vertex {
+                    pos: (x2 + nx, y2 + ny),
+                    color,
+                    uv: (1., 0.),
+                },
+                Vertex {
+                    pos: (x2 - nx, y2 - ny),
+                    color,
+                    uv: (1., 1.),
+                },
+                Vertex {
+                    pos: (x1 - nx, y1 - ny),
+                    color,
+                    uv: (0., 1.),
+                },
+            ],
+            vec![0, 3, 1, 1, 3, 2],
+            depth,
+        );
+    }
+}
+
+impl Drop for DrawLine<'_> {
+    fn drop(&mut self) {
+        self.commit();
+    }
+}
+
+/// A circle to be drawn.
+///
+/// This is a builder struct that allows you to specify extra parameters for the
+/// circle via method chaining. The circle is commited to the [`Graphics`]
+/// struct when [`DrawCircle`] is dropped.
+///
+/// By default, the circle is filled in. Use [`outline`] to draw a ring instead.
+///
+/// This struct is created using the [`draw_circle`] method on [`Graphics`].
+///
+/// [`outline`]: DrawCircle::outline
+/// [`draw_circle`]: Graphics::draw_circle
+pub struct DrawCircle<'a> {
+    g: &'a mut Graphics,
+    center: (f32, f32),
+    radius: f32,
+    outline: Option<f32>,
+    segments: Option<u32>,
+    color: Option<Color>,
+    depth: Option<f32>,
+}
+
+impl<'a> DrawCircle<'a> {
+    pub(super) fn new(g: &'a mut Graphics, x: f32, y: f32, radius: f32) -> Self {
+        DrawCircle {
+            g,
+            center: (x, y),
+            radius,
+            outline: None,
+            segments: None,
+            color: None,
+            depth: None,
+        }
+    }
+
+    /// Draws only the outline of the circle with the given width, measured
+    /// inwards from the radius.
+    pub fn outline(mut self, width: f32) -> Self {
+        self.outline = Some(width);
+        self
+    }
+
+    /// Sets the number of segments used to approximate the circle.
+    ///
+    /// By default, the number of segments is chosen based on the radius of the
+    /// circle.
+    pub fn segments(mut self, segments: u32) -> Self {
+        self.segments = Some(segments);
+        self
+    }
+
+    /// Sets the color of the circle.
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Sets the depth of the circle.
+    pub fn depth(mut self, depth: f32) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    fn commit(&mut self) {
+        let (cx, cy) = self.center;
+        let mut arc = DrawArc::new(self.g, cx, cy, self.radius, 0., std::f32::consts::TAU);
+        match self.outline {
+            Some(width) => arc.width = width,
+            None => arc.fill = true,
+        }
+        arc.segments = self.segments;
+        arc.color = self.color;
+        arc.depth = self.depth;
+    }
+}
+
+impl Drop for DrawCircle<'_> {
+    fn drop(&mut self) {
+        self.commit();
+    }
+}
+
+/// A triangle to be drawn.
+///
+/// This is a builder struct that allows you to specify extra parameters for the
+/// triangle via method chaining. The triangle is commited to the [`Graphics`]
+/// struct when [`DrawTriangle`] is dropped.
+///
+/// This struct is created using the [`draw_triangle`] method on [`Graphics`].
+///
+/// [`draw_triangle`]: Graphics::draw_triangle
+pub struct DrawTriangle<'a> {
+    g: &'a mut Graphics,
+    points: [(f32, f32); 3],
+    color: Option<Color>,
+    depth: Option<f32>,
+}
+
+impl<'a> DrawTriangle<'a> {
+    pub(super) fn new(g: &'a mut Graphics, points: [(f32, f32); 3]) -> Self {
+        DrawTriangle {
+            g,
+            points,
+            color: None,
+            depth: None,
+        }
+    }
+
+    /// Sets the color of the triangle.
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Sets the depth of the triangle.
+    pub fn depth(mut self, depth: f32) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    fn commit(&mut self) {
+        let color = self.color.unwrap_or(self.g.color);
+        let depth = self.depth.unwrap_or(self.g.depth);
+
+        self.g.push_command(
+            None,
+            self.points
+                .iter()
+                .map(|&pos| Vertex {
+                    pos,
+                    color,
+                    uv: (0., 0.),
+                })
+                .collect(),
+            vec![0, 1, 2],
+            depth,
+        );
+    }
+}
+
+impl Drop for DrawTriangle<'_> {
+    fn drop(&mut self) {
+        self.commit();
+    }
+}
+
+/// An arc to be drawn.
+///
+/// This is a builder struct that allows you to specify extra parameters for the
+/// arc via method chaining. The arc is commited to the [`Graphics`] struct
+/// when [`DrawArc`] is dropped.
+///
+/// By default, the arc is drawn as a ring segment 1 pixel wide. Use [`width`]
+/// to draw a thicker ring or [`fill`] to draw a pie slice instead.
+///
+/// This struct is created using the [`draw_arc`] method on [`Graphics`].
+///
+/// [`width`]: DrawArc::width
+/// [`fill`]: DrawArc::fill
+/// [`draw_arc`]: Graphics::draw_arc
+pub struct DrawArc<'a> {
+    g: &'a mut Graphics,
+    center: (f32, f32),
+    radius: f32,
+    angles: (f32, f32),
+    width: f32,
+    fill: bool,
+    segments: Option<u32>,
+    color: Option<Color>,
+    depth: Option<f32>,
+}
+
+impl<'a> DrawArc<'a> {
+    pub(super) fn new(
+        g: &'a mut Graphics,
+        cx: f32,
+        cy: f32,
+        radius: f32,
+        start_angle: f32,
+        end_angle: f32,
+    ) -> Self {
+        DrawArc {
+            g,
+            center: (cx, cy),
+            radius,
+            angles: (start_angle, end_angle),
+            width: 1.,
+            fill: false,
+            segments: None,
+            color: None,
+            depth: None,
+        }
+    }
+
+    /// Sets the width of the ring, measured inwards from the radius.
+    pub fn width(mut self, width: f32) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Sets whether the arc should be filled in as a pie slice.
+    pub fn fill(mut self, fill: bool) -> Self {
+        self.fill = fill;
+        self
+    }
+
+    /// Sets the number of segments used to approximate the arc.
+    ///
+    /// By default, the number of segments is chosen based on the radius and
+    /// angle of the arc.
+    pub fn segments(mut self, segments: u32) -> Self {
+        self.segments = Some(segments);
+        self
+    }
+
+    /// Sets the color of the arc.
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Sets the depth of the arc.
+    pub fn depth(mut self, depth: f32) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    fn commit(&mut self) {
+        use std::f32::consts::TAU;
+
+        let (cx, cy) = self.center;
+        let (start, end) = self.angles;
+        let span = (end - start).clamp(-TAU, TAU);
+        if span == 0. || !span.is_finite() || self.radius <= 0. {
+            return;
+        }
+        let full = span.abs() >= TAU;
+        let segments = self
+            .segments
+            .unwrap_or_else(|| (self.radius.sqrt() * 6. * span.abs() / TAU).ceil() as u32)
+            .max(if full { 3 } else { 1 });
+        let color = self.color.unwrap_or(self.g.color);
+        let depth = self.depth.unwrap_or(self.g.depth);
+
+        // A full circle reuses its first point instead of duplicating it.
+        let points = if full { segments } else { segments + 1 };
+        let point = |i: u32, r: f32| {
+            let angle = start + span * i as f32 / segments as f32;
+            Vertex {
+                pos: (cx + r * angle.cos(), cy + r * angle.sin()),
+                color,
+                uv: (0., 0.),
+            }
+        };
+
+        let mut verts = Vec::new();
+        let mut indices = Vec::new();
+
+        if self.fill {
+            verts.push(Vertex {
+                pos: (cx, cy),
+                color,
+                uv: (0., 0.),
+            });
+            verts.extend((0..points).map(|i| point(i, self.radius)));
+            for i in 0..segments {
+                let a = i % points + 1;
+                let b = (i + 1) % points + 1;
+                indices.extend([0, a as u16, b as u16]);
+            }
+        } else {
+            let inner = (self.radius - self.width).max(0.);
+            for i in 0..points {
+                verts.push(point(i, self.radius));
+                verts.push(point(i, inner));
+            }
+            for i in 0..segments {
+                let a = (i % points * 2) as u16;
+                let b = ((i + 1) % points * 2) as u16;
+                indices.extend([a, a + 1, b, b, a + 1, b + 1]);
+            }
+        }
+
+        self.g.push_command(None, verts, indices, depth);
+    }
+}
+
+impl Drop for DrawArc<'_> {
+    fn drop(&mut self) {
+        self.commit();
+    }
+}
+
+/// A path to be drawn.
+///
+/// This is a builder struct that allows you to specify extra parameters for the
+/// path via method chaining. The path is commited to the [`Graphics`] struct
+/// when [`DrawPath`] is dropped.
+///
+/// By default, the path is drawn as an open line strip 1 pixel wide. Corners
+/// are mitered, except for sharp corners, which are beveled instead.
+///
+/// This struct is created using the [`draw_path`] method on [`Graphics`].
+///
+/// [`draw_path`]: Graphics::draw_path
+pub struct DrawPath<'a> {
+    g: &'a mut Graphics,
+    points: &'a [(f32, f32)],
+    width: f32,
+    closed: bool,
+    color: Option<Color>,
+    depth: Option<f32>,
+}
+
+impl<'a> DrawPath<'a> {
+    pub(super) fn new(g: &'a mut Graphics, points: &'a [(f32, f32)]) -> Self {
+        DrawPath {
+            g,
+            points,
+            width: 1.,
+            closed: false,
+            color: None,
+            depth: None,
+        }
+    }
+
+    /// Sets the width of the path.
+    pub fn width(mut self, width: f32) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Sets whether the last point should be connected back to the first.
+    pub fn closed(mut self, closed: bool) -> Self {
+        self.closed = closed;
+        self
+    }
+
+    /// Sets the color of the path.
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Sets the depth of the path.
+    pub fn depth(mut self, depth: f32) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    fn commit(&mut self) {
+        // Corners whose miter would be longer than this many times the half
+        // width are beveled instead.
+        const MITER_LIMIT: f32 = 4.;
+
+        let mut points = self.points.to_vec();
+        points.dedup();
+        if self.closed && points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        let n = points.len();
+        if n < 2 || self.width <= 0. || (self.closed && n < 3) {
+            return;
+        }
+
+        let color = self.color.unwrap_or(self.g.color);
+        let depth = self.depth.unwrap_or(self.g.depth);
+        let hw = self.width / 2.;
+
+        let dir = |a: (f32, f32), b: (f32, f32)| {
+            let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+            let len = (dx * dx + dy * dy).sqrt();
+            (dx / len, dy / len)
+        };
+        let vertex = |(x, y): (f32, f32), (nx, ny): (f32, f32), scale: f32| Vertex {
+            pos: (x + nx * scale, y + ny * scale),
+            color,
+            uv: (0., 0.),
+        };
+
+        let mut verts = Vec::new();
+        let mut indices = Vec::new();
+
+        // The indices of the left and right vertices where the incoming and
+        // outgoing segments of each point attach.
+        let mut ends = Vec::with_capacity(n);
+        for i in 0..n {
+            let p = points[i];
+            let prev = if i > 0 {
+                Some(points[i - 1])
+            } else if self.closed {
+                Some(points[n - 1])
+            } else {
+                None
+            };
+            let next = if i + 1 < n {
+                Some(points[i + 1])
+            } else if self.closed {
+                Some(points[0])
+            } else {
+                None
+            };
+            let d_in = prev.map(|prev| dir(prev, p));
+            let d_out = next.map(|next| dir(p, next));
+
+            let base = verts.len() as u16;
+            match (d_in, d_out) {
+                (Some(d_in), Some(d_out)) => {
+                    let n_in = (-d_in.1, d_in.0);
+                    let n_out = (-d_out.1, d_out.0);
+                    let (mx, my) = (n_in.0 + n_out.0, n_in.1 + n_out.1);
+                    let m_len = (mx * mx + my * my).sqrt();
+                    let cos = if m_len > 0. {
+                        (mx * n_out.0 + my * n_out.1) / m_len
+                    } else {
+                        0.
+                    };
+                    if cos > 1. / MITER_LIMIT {
+                        let m = (mx / m_len, my / m_len);
+                        verts.push(vertex(p, m, hw / cos));
+                        verts.push(vertex(p, m, -hw / cos));
+                        ends.push((base, base + 1, base, base + 1));
+                    } else {
+                        verts.push(vertex(p, n_in, hw));
+                        verts.push(vertex(p, n_in, -hw));
+                        verts.push(vertex(p, n_out, hw));
+                        verts.push(vertex(p, n_out, -hw));
+                        verts.push(vertex(p, (0., 0.), 0.));
+                        // Fill the gap on the outer side of the corner.
+                        let cross = d_in.0 * d_out.1 - d_in.1 * d_out.0;
+                        if cross > 0. {
+                            indices.extend([base + 4, base + 1, base + 3]);
+                        } else {
+                            indices.extend([base + 4, base, base + 2]);
+                        }
+                        ends.push((base, base + 1, base + 2, base + 3));
+                    }
+                }
+                (Some(d), None) | (None, Some(d)) => {
+                    let normal = (-d.1, d.0);
+                    verts.push(vertex(p, normal, hw));
+                    verts.push(vertex(p, normal, -hw));
+                    ends.push((base, base + 1, base, base + 1));
+                }
+                (None, None) => unreachable!(),
+            }
+        }
+
+        let segments = if self.closed { n } else { n - 1 };
+        for i in 0..segments {
+            let (_, _, a_left, a_right) = ends[i];
+            let (b_left, b_right, _, _) = ends[(i + 1) % n];
+            indices.extend([a_left, a_right, b_left, b_left, a_right, b_right]);
+        }
+
+        self.g.push_command(None, verts, indices, depth);
+    }
+}
+
+impl Drop for DrawPath<'_> {
+    fn drop(&mut self) {
+        self.commit();
+    }
+}
+
 /// A sprite to be drawn.
 ///
 /// This is a builder struct that allows you to specify extra parameters for the
@@ -115,10 +742,13 @@ pub struct DrawSprite<'a> {
     pos: (f32, f32),
     sprite: ResourceHandle<Sprite>,
     size: Option<(f32, f32)>,
+    anchor: Option<(f32, f32)>,
     source_pos: Option<(f32, f32)>,
     source_size: Option<(f32, f32)>,
     color: Option<Color>,
     depth: Option<f32>,
+    uniforms: Vec<(&'static str, UniformValue)>,
+    snap: bool,
 }
 
 impl<'a> DrawSprite<'a> {
@@ -128,19 +758,37 @@ impl<'a> DrawSprite<'a> {
             sprite,
             pos: (x, y),
             size: None,
+            anchor: None,
             source_pos: None,
             source_size: None,
             color: None,
             depth: None,
+            uniforms: Vec::new(),
+            snap: false,
         }
     }
 
+    /// Sets whether the sprite is moved to line up with whole pixels once the
+    /// camera and scale have been applied, e.g. for the glyphs of text.
+    pub(crate) fn snap_to_pixel(mut self, snap: bool) -> Self {
+        self.snap = snap;
+        self
+    }
+
     /// Sets the size of the sprite.
     pub fn size(mut self, w: f32, h: f32) -> Self {
         self.size = Some((w, h));
         self
     }
 
+    /// Sets the anchor of the sprite, overriding the sprite's own anchor.
+    ///
+    /// See [`Sprite::set_anchor`] for more information.
+    pub fn anchor(mut self, x: f32, y: f32) -> Self {
+        self.anchor = Some((x, y));
+        self
+    }
+
     /// Sets the source position of the sprite.
     pub fn source_pos(mut self, x: f32, y: f32) -> Self {
         self.source_pos = Some((x, y));
@@ -177,13 +825,27 @@ impl<'a> DrawSprite<'a> {
         self
     }
 
+    /// Overrides the value of a uniform of the active shader for this sprite.
+    ///
+    /// Uniforms that are not declared by the active shader are ignored, with a
+    /// warning. See [`Graphics::set_shader`] and [`Graphics::set_uniform`] for
+    /// the uniforms of the default shader.
+    ///
+    /// Sprites with different uniform values cannot be batched together, so
+    /// this is best reserved for effects applied to a small number of sprites.
+    pub fn uniform(mut self, name: &'static str, value: impl Into<UniformValue>) -> Self {
+        self.uniforms.push((name, value.into()));
+        self
+    }
+
     fn commit(&mut self) -> Option<()> {
         let sprite = self.g.resource_manager.get(self.sprite)?;
         let w = sprite.width as f32;
         let h = sprite.height as f32;
 
-        let (dx, dy) = self.pos;
         let (dw, dh) = self.size.or(self.source_size).unwrap_or((w, h));
+        let (ax, ay) = self.anchor.unwrap_or(sprite.anchor);
+        let (dx, dy) = (self.pos.0 - ax * dw, self.pos.1 - ay * dh);
         let (sx, sy) = self
             .source_pos
             .map(|(sx, sy)| (sx / w, sy / h))
@@ -192,12 +854,14 @@ impl<'a> DrawSprite<'a> {
             .source_size
             .map(|(sw, sh)| (sw / w, sh / h))
             .unwrap_or((1., 1.));
+        let (rx, ry, rw, rh) = sprite.region;
+        let (sx, sy, sw, sh) = (rx + sx * rw, ry + sy * rh, sw * rw, sh * rh);
         let color = self.color.unwrap_or(self.g.color);
         let depth = self.depth.unwrap_or(self.g.depth);
 
-        self.g.draw_commands.push(DrawCommand {
-            sprite: Some(self.sprite),
-            verts: vec![
+        let command = self.g.push_command(
+            Some(self.sprite),
+            vec![
                 Vertex {
                     pos: (dx, dy),
                     color,
@@ -219,9 +883,19 @@ impl<'a> DrawSprite<'a> {
                     uv: (sx, sy + sh),
                 },
             ],
-            indices: vec![0, 3, 1, 1, 3, 2],
+            vec![0, 3, 1, 1, 3, 2],
             depth,
-        });
+        );
+        command.state.uniforms.append(&mut self.uniforms);
+        if self.snap {
+            // Move the whole sprite by the rounding of its first corner, so
+            // that its size is kept.
+            let (x, y) = command.verts[0].pos;
+            let (ox, oy) = (x.round() - x, y.round() - y);
+            for vert in &mut command.verts {
+                *vert = vert.map_pos(|x, y| (x + ox, y + oy));
+            }
+        }
 
         Some(())
     }
@@ -233,6 +907,109 @@ impl Drop for DrawSprite<'_> {
     }
 }
 
+/// A nine-patch sprite to be drawn.
+///
+/// This is a builder struct that allows you to specify extra parameters for the
+/// nine-patch via method chaining. The nine-patch is commited to the
+/// [`Graphics`] struct when [`DrawNinePatch`] is dropped.
+///
+/// This struct is created using the [`draw_nine_patch`] method on
+/// [`Graphics`].
+///
+/// [`draw_nine_patch`]: Graphics::draw_nine_patch
+pub struct DrawNinePatch<'a> {
+    g: &'a mut Graphics,
+    rect: Rect,
+    sprite: ResourceHandle<Sprite>,
+    insets: Insets,
+    color: Option<Color>,
+    depth: Option<f32>,
+}
+
+impl<'a> DrawNinePatch<'a> {
+    pub(super) fn new(
+        g: &'a mut Graphics,
+        rect: Rect,
+        sprite: ResourceHandle<Sprite>,
+        insets: Insets,
+    ) -> Self {
+        DrawNinePatch {
+            g,
+            rect,
+            sprite,
+            insets,
+            color: None,
+            depth: None,
+        }
+    }
+
+    /// Sets the color of the nine-patch.
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Sets the depth of the nine-patch.
+    pub fn depth(mut self, depth: f32) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    fn commit(&mut self) -> Option<()> {
+        let sprite = self.g.resource_manager.get(self.sprite)?;
+        let w = sprite.width as f32;
+        let h = sprite.height as f32;
+        let (rx, ry, rw, rh) = sprite.region;
+        let Insets {
+            left,
+            right,
+            top,
+            bottom,
+        } = self.insets;
+        let Rect {
+            x: dx,
+            y: dy,
+            w: dw,
+            h: dh,
+        } = self.rect;
+
+        // Scale the borders down if they do not fit into the rectangle.
+        let sx = (dw / (left + right)).min(1.);
+        let sy = (dh / (top + bottom)).min(1.);
+        let xs = [dx, dx + left * sx, dx + dw - right * sx, dx + dw];
+        let ys = [dy, dy + top * sy, dy + dh - bottom * sy, dy + dh];
+        let us = [0., left, w - right, w].map(|u| rx + u / w * rw);
+        let vs = [0., top, h - bottom, h].map(|v| ry + v / h * rh);
+        let color = self.color.unwrap_or(self.g.color);
+        let depth = self.depth.unwrap_or(self.g.depth);
+
+        let mut verts = Vec::with_capacity(16);
+        for (&y, &v) in ys.iter().zip(&vs) {
+            for (&x, &u) in xs.iter().zip(&us) {
+                verts.push(Vertex::new((x, y), color, (u, v)));
+            }
+        }
+        let mut indices = Vec::with_capacity(54);
+        for row in 0..3 {
+            for col in 0..3 {
+                let i = row * 4 + col;
+                indices.extend([i, i + 4, i + 1, i + 1, i + 4, i + 5]);
+            }
+        }
+
+        self.g
+            .push_command(Some(self.sprite), verts, indices, depth);
+
+        Some(())
+    }
+}
+
+impl Drop for DrawNinePatch<'_> {
+    fn drop(&mut self) {
+        self.commit();
+    }
+}
+
 /// Text to be drawn.
 ///
 /// This is a builder struct that allows you to specify extra parameters for the
@@ -251,6 +1028,8 @@ pub struct DrawText<'a> {
     size: Option<f32>,
     color: Option<Color>,
     depth: Option<f32>,
+    options: TextOptions,
+    snap: Option<bool>,
 }
 
 #[cfg(feature = "text")]
@@ -264,6 +1043,8 @@ impl<'a> DrawText<'a> {
             size: None,
             color: None,
             depth: None,
+            options: TextOptions::default(),
+            snap: None,
         }
     }
 
@@ -291,6 +1072,71 @@ impl<'a> DrawText<'a> {
         self
     }
 
+    /// Sets the horizontal alignment of the text relative to its position.
+    /// Each line of the text is aligned independently.
+    ///
+    /// If a [maximum width](Self::max_width) is set, lines are aligned within
+    /// the region between the position and the maximum width instead.
+    ///
+    /// The default value is [`TextAlign::Left`].
+    pub fn align(mut self, align: TextAlign) -> Self {
+        self.options.align = align;
+        self
+    }
+
+    /// Sets the maximum width of the text, wrapping lines that would be wider
+    /// between words. Words wider than the maximum width are broken between
+    /// characters. Whitespace at the end of a line does not count towards its
+    /// width.
+    ///
+    /// Use [`Font::measure_text_wrapped`] to measure the wrapped text.
+    pub fn max_width(mut self, max_width: f32) -> Self {
+        self.options.max_width = Some(max_width);
+        self
+    }
+
+    /// Sets the distance between the baselines of consecutive lines of the
+    /// text, in pixels.
+    ///
+    /// This takes precedence over [`line_spacing`](Self::line_spacing) if both
+    /// are set. By default, the line height of the font is used.
+    pub fn line_height(mut self, line_height: f32) -> Self {
+        self.options.line_height = Some(line_height);
+        self
+    }
+
+    /// Sets the distance between the baselines of consecutive lines of the
+    /// text as a multiple of the line height of the font, e.g. `1.5` for one
+    /// and a half line spacing.
+    ///
+    /// This has no effect if [`line_height`](Self::line_height) is set. The
+    /// default value is `1`.
+    pub fn line_spacing(mut self, line_spacing: f32) -> Self {
+        self.options.line_spacing = Some(line_spacing);
+        self
+    }
+
+    /// Sets all layout options of the text at once, e.g. to draw text with the
+    /// same options it was measured with using [`Font::measure_text_with`].
+    pub fn options(mut self, options: TextOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sets whether the glyphs of the text are rounded to whole pixels, which
+    /// keeps stationary text crisp but makes slowly moving text move in steps
+    /// instead of smoothly.
+    ///
+    /// Glyphs are rounded in screen pixels, after the camera and
+    /// [scale](Graphics::set_scale) have been applied. A rotated camera still
+    /// leaves them between pixels.
+    ///
+    /// By default, the value set with [`Graphics::set_snap_text`] is used.
+    pub fn snap_to_pixel(mut self, snap: bool) -> Self {
+        self.snap = Some(snap);
+        self
+    }
+
     fn commit(&mut self) {
         let (x, y) = self.pos;
         let text = self.text;
@@ -298,7 +1144,19 @@ impl<'a> DrawText<'a> {
         let size = self.size.unwrap_or(24.);
         let color = self.color.unwrap_or(self.g.color);
         let depth = self.depth.unwrap_or(self.g.depth);
-        crate::text::draw_text(self.g, x, y, text, font, size, color, depth);
+        let snap = self.snap.unwrap_or(self.g.snap_text);
+        crate::text::draw_text(
+            self.g,
+            x,
+            y,
+            text,
+            font,
+            size,
+            color,
+            depth,
+            &self.options,
+            snap,
+        );
     }
 }
 
@@ -385,3 +1243,86 @@ impl Drop for DrawGlyph<'_> {
         self.commit();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the draw command of a rectangle covering the same area as all
+    /// other rectangles.
+    fn rect(color: Color, depth: f32, seq: u32) -> DrawCommand {
+        let corners = [(0., 0.), (1., 0.), (1., 1.), (0., 1.)];
+        DrawCommand {
+            state: DrawState {
+                sprite: None,
+                blend_mode: BlendMode::Alpha,
+                scissor: None,
+                shader: None,
+                uniforms: Vec::new(),
+            },
+            verts: corners
+                .iter()
+                .map(|&pos| Vertex::new(pos, color, (0., 0.)))
+                .collect(),
+            indices: vec![0, 1, 2, 0, 2, 3],
+            layer: 0,
+            depth,
+            seq,
+        }
+    }
+
+    /// Sorts the given draw commands like [`Graphics::end`] does and returns
+    /// the color of the rectangle drawn last, i.e. on top.
+    fn top_color(mut draw_commands: Vec<DrawCommand>) -> Color {
+        draw_commands.sort_unstable_by_key(|draw_command| draw_command.sort_key());
+        draw_commands.last().unwrap().verts[0].color
+    }
+
+    #[test]
+    fn same_depth_keeps_submission_order() {
+        let first = rect(Color::BLUE, 0.5, 0);
+        let second = rect(Color::RED, 0.5, 1);
+        assert_eq!(top_color(vec![first, second]), Color::RED);
+
+        // Many commands at the same depth still keep their order, even though
+        // the sort is unstable.
+        let mut draw_commands: Vec<_> = (0..100)
+            .rev()
+            .map(|seq| rect(Color::BLUE, 0.5, seq))
+            .collect();
+        draw_commands.push(rect(Color::RED, 0.5, 100));
+        draw_commands.swap(0, 100);
+        assert_eq!(top_color(draw_commands), Color::RED);
+    }
+
+    #[test]
+    fn lesser_depth_is_drawn_on_top() {
+        let first = rect(Color::RED, 0.25, 0);
+        let second = rect(Color::BLUE, 0.5, 1);
+        assert_eq!(top_color(vec![first, second]), Color::RED);
+    }
+
+    #[cfg(all(feature = "null-backend", feature = "text"))]
+    #[test]
+    fn snapped_text_lines_up_with_scaled_pixels() {
+        use crate::assets::ResourceManager;
+        use crate::graphics::{Context, Gl};
+
+        let resource_manager = ResourceManager::new();
+        let mut g = Graphics::new(&Rc::new(Context::new()), Gl::new(), &resource_manager);
+        g.set_scale(1.5);
+        let whole = |g: &Graphics| {
+            g.draw_commands
+                .iter()
+                .flat_map(|draw_command| &draw_command.verts)
+                .all(|vert| vert.pos.0.fract() == 0. && vert.pos.1.fract() == 0.)
+        };
+
+        g.draw_text(1., 1., "hi").size(16.).snap_to_pixel(false);
+        assert!(!whole(&g));
+        g.draw_commands.clear();
+        g.draw_text(1., 1., "hi").size(16.).snap_to_pixel(true);
+        assert!(!g.draw_commands.is_empty());
+        assert!(whole(&g));
+    }
+}
@@ -0,0 +1,325 @@
+//! Raw OpenGL functionality that is not exposed by `fugu`.
+
+use std::ffi::{c_void, CStr};
+
+pub const FRAMEBUFFER_SRGB: u32 = 0x8DB9;
+pub const MAX_TEXTURE_SIZE: u32 = 0x0D33;
+pub const MAX_VIEWPORT_DIMS: u32 = 0x0D3A;
+pub const MAX_TEXTURE_IMAGE_UNITS: u32 = 0x8872;
+pub const VERSION: u32 = 0x1F02;
+pub const MAJOR_VERSION: u32 = 0x821B;
+pub const MINOR_VERSION: u32 = 0x821C;
+pub const SCISSOR_TEST: u32 = 0x0C11;
+pub const FRAMEBUFFER_BINDING: u32 = 0x8CA6;
+
+const TEXTURE_BINDING_2D: u32 = 0x8069;
+const ACTIVE_TEXTURE: u32 = 0x84E0;
+const TEXTURE0: u32 = 0x84C0;
+
+const FRAMEBUFFER: u32 = 0x8D40;
+const COLOR_ATTACHMENT0: u32 = 0x8CE0;
+const FRAMEBUFFER_COMPLETE: u32 = 0x8CD5;
+const TEXTURE_2D: u32 = 0x0DE1;
+const RGBA: u32 = 0x1908;
+const RGBA8: i32 = 0x8058;
+const UNSIGNED_BYTE: u32 = 0x1401;
+const COLOR_BUFFER_BIT: u32 = 0x4000;
+const FRONT_AND_BACK: u32 = 0x0408;
+const LINE: u32 = 0x1B01;
+const FILL: u32 = 0x1B02;
+
+macro_rules! gl_functions {
+    (
+        required {
+            $($name:ident = $symbol:literal: fn($($arg:ty),*) $(-> $ret:ty)?;)*
+        }
+        optional {
+            $($opt_name:ident = $opt_symbol:literal: fn($($opt_arg:ty),*) $(-> $opt_ret:ty)?;)*
+        }
+    ) => {
+        pub struct Gl {
+            $($name: unsafe extern "system" fn($($arg),*) $(-> $ret)?,)*
+            $($opt_name: Option<unsafe extern "system" fn($($opt_arg),*) $(-> $opt_ret)?>,)*
+        }
+
+        impl Gl {
+            /// Loads the required OpenGL functions using the given loader.
+            ///
+            /// Optional functions that cannot be loaded, e.g. because they are
+            /// not part of OpenGL ES, are left unset.
+            ///
+            /// # Panics
+            ///
+            /// Panics if any of the required functions cannot be loaded.
+            pub fn load(mut loader: impl FnMut(&str) -> *const c_void) -> Gl {
+                let mut load = |name: &str, required: bool| {
+                    let ptr = loader(name);
+                    assert!(
+                        !required || !ptr.is_null(),
+                        "failed to load OpenGL function {}",
+                        name
+                    );
+                    ptr
+                };
+
+                // SAFETY: The required pointers are non-null, optional pointers
+                // are checked, and the signatures match the OpenGL
+                // specification.
+                unsafe {
+                    Gl {
+                        $($name: std::mem::transmute::<
+                            *const c_void,
+                            unsafe extern "system" fn($($arg),*) $(-> $ret)?,
+                        >(load($symbol, true)),)*
+                        $($opt_name: {
+                            let ptr = load($opt_symbol, false);
+                            if ptr.is_null() {
+                                None
+                            } else {
+                                Some(std::mem::transmute::<
+                                    *const c_void,
+                                    unsafe extern "system" fn($($opt_arg),*) $(-> $opt_ret)?,
+                                >(ptr))
+                            }
+                        },)*
+                    }
+                }
+            }
+        }
+    };
+}
+
+gl_functions! {
+    required {
+        enable = "glEnable": fn(u32);
+        disable = "glDisable": fn(u32);
+        get_integerv = "glGetIntegerv": fn(u32, *mut i32);
+        get_string = "glGetString": fn(u32) -> *const u8;
+        viewport = "glViewport": fn(i32, i32, i32, i32);
+        scissor = "glScissor": fn(i32, i32, i32, i32);
+        clear_color = "glClearColor": fn(f32, f32, f32, f32);
+        clear = "glClear": fn(u32);
+        gen_textures = "glGenTextures": fn(i32, *mut u32);
+        delete_textures = "glDeleteTextures": fn(i32, *const u32);
+        bind_texture = "glBindTexture": fn(u32, u32);
+        active_texture = "glActiveTexture": fn(u32);
+        tex_image_2d = "glTexImage2D": fn(u32, i32, i32, i32, i32, i32, u32, u32, *const c_void);
+        gen_framebuffers = "glGenFramebuffers": fn(i32, *mut u32);
+        delete_framebuffers = "glDeleteFramebuffers": fn(i32, *const u32);
+        bind_framebuffer_raw = "glBindFramebuffer": fn(u32, u32);
+        framebuffer_texture_2d = "glFramebufferTexture2D": fn(u32, u32, u32, u32, i32);
+        check_framebuffer_status = "glCheckFramebufferStatus": fn(u32) -> u32;
+        read_pixels_raw = "glReadPixels": fn(i32, i32, i32, i32, u32, u32, *mut c_void);
+    }
+    optional {
+        polygon_mode = "glPolygonMode": fn(u32, u32);
+    }
+}
+
+impl Gl {
+    pub fn set_enabled(&self, cap: u32, enabled: bool) {
+        // SAFETY: The function pointers were loaded for the current context.
+        unsafe {
+            if enabled {
+                (self.enable)(cap);
+            } else {
+                (self.disable)(cap);
+            }
+        }
+    }
+
+    /// Queries the given parameter, which must consist of at most 4 values.
+    pub fn get_integers(&self, pname: u32) -> [i32; 4] {
+        let mut values = [0; 4];
+        // SAFETY: The buffer is large enough for any parameter we query.
+        unsafe {
+            (self.get_integerv)(pname, values.as_mut_ptr());
+        }
+        values
+    }
+
+    /// Returns the 2D texture bound to the given texture unit.
+    ///
+    /// The active texture unit is restored afterwards, so that it still
+    /// matches the state `fugu` expects.
+    pub fn texture_binding(&self, unit: u32) -> u32 {
+        let prev_unit = self.get_integers(ACTIVE_TEXTURE)[0] as u32;
+        // SAFETY: The function pointers were loaded for the current context.
+        unsafe {
+            (self.active_texture)(TEXTURE0 + unit);
+            let texture = self.get_integers(TEXTURE_BINDING_2D)[0] as u32;
+            (self.active_texture)(prev_unit);
+            texture
+        }
+    }
+
+    /// Returns the major and minor version of the current context.
+    pub fn version(&self) -> (i32, i32) {
+        (
+            self.get_integers(MAJOR_VERSION)[0],
+            self.get_integers(MINOR_VERSION)[0],
+        )
+    }
+
+    /// Returns true if the current context is an OpenGL ES context.
+    pub fn is_es(&self) -> bool {
+        // SAFETY: The function pointers were loaded for the current context,
+        // and the returned string is null-terminated if it is not null.
+        unsafe {
+            let version = (self.get_string)(VERSION);
+            !version.is_null()
+                && CStr::from_ptr(version.cast())
+                    .to_bytes()
+                    .starts_with(b"OpenGL ES")
+        }
+    }
+
+    /// Sets whether polygons are rasterized as outlines instead of being
+    /// filled. Does nothing if the context doesn't support it, e.g. on OpenGL
+    /// ES.
+    pub fn set_wireframe(&self, wireframe: bool) {
+        if let Some(polygon_mode) = self.polygon_mode {
+            // SAFETY: The function pointer was loaded for the current context.
+            unsafe {
+                polygon_mode(FRONT_AND_BACK, if wireframe { LINE } else { FILL });
+            }
+        }
+    }
+
+    /// Sets the viewport, with the origin at the bottom-left corner.
+    pub fn set_viewport(&self, x: i32, y: i32, width: i32, height: i32) {
+        // SAFETY: The function pointers were loaded for the current context.
+        unsafe {
+            (self.viewport)(x, y, width, height);
+        }
+    }
+
+    /// Sets the scissor rectangle, with the origin at the bottom-left corner.
+    ///
+    /// The scissor test must be enabled separately.
+    pub fn set_scissor(&self, x: i32, y: i32, width: i32, height: i32) {
+        // SAFETY: The function pointers were loaded for the current context.
+        unsafe {
+            (self.scissor)(x, y, width, height);
+        }
+    }
+
+    /// Clears the color buffer of the currently bound framebuffer.
+    pub fn clear_color_buffer(&self, (r, g, b, a): (f32, f32, f32, f32)) {
+        // SAFETY: The function pointers were loaded for the current context.
+        unsafe {
+            (self.clear_color)(r, g, b, a);
+            (self.clear)(COLOR_BUFFER_BIT);
+        }
+    }
+
+    /// Creates a framebuffer with an RGBA8 texture of the given size as its
+    /// color attachment, cleared to transparent black. Returns the
+    /// framebuffer and the texture.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the framebuffer is incomplete.
+    pub fn create_framebuffer(&self, width: u32, height: u32) -> (u32, u32) {
+        let data = vec![0_u8; width as usize * height as usize * 4];
+        let prev_texture = self.get_integers(TEXTURE_BINDING_2D)[0] as u32;
+        let mut texture = 0;
+        // SAFETY: The function pointers were loaded for the current context
+        // and the data is large enough for the texture.
+        unsafe {
+            (self.gen_textures)(1, &mut texture);
+            (self.bind_texture)(TEXTURE_2D, texture);
+            (self.tex_image_2d)(
+                TEXTURE_2D,
+                0,
+                RGBA8,
+                width as i32,
+                height as i32,
+                0,
+                RGBA,
+                UNSIGNED_BYTE,
+                data.as_ptr().cast(),
+            );
+            (self.bind_texture)(TEXTURE_2D, prev_texture);
+        }
+        (self.create_framebuffer_for(texture), texture)
+    }
+
+    /// Creates a framebuffer with the given texture as its color attachment.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the framebuffer is incomplete.
+    pub fn create_framebuffer_for(&self, texture: u32) -> u32 {
+        let prev_framebuffer = self.get_integers(FRAMEBUFFER_BINDING)[0] as u32;
+        let mut framebuffer = 0;
+        // SAFETY: The function pointers were loaded for the current context.
+        unsafe {
+            (self.gen_framebuffers)(1, &mut framebuffer);
+            (self.bind_framebuffer_raw)(FRAMEBUFFER, framebuffer);
+            (self.framebuffer_texture_2d)(FRAMEBUFFER, COLOR_ATTACHMENT0, TEXTURE_2D, texture, 0);
+            let status = (self.check_framebuffer_status)(FRAMEBUFFER);
+            (self.bind_framebuffer_raw)(FRAMEBUFFER, prev_framebuffer);
+            assert_eq!(status, FRAMEBUFFER_COMPLETE, "incomplete framebuffer");
+        }
+        framebuffer
+    }
+
+    /// Deletes a framebuffer and texture created by [`create_framebuffer`].
+    ///
+    /// [`create_framebuffer`]: Self::create_framebuffer
+    pub fn delete_framebuffer(&self, framebuffer: u32, texture: u32) {
+        self.delete_framebuffer_only(framebuffer);
+        // SAFETY: The function pointers were loaded for the current context.
+        unsafe {
+            (self.delete_textures)(1, &texture);
+        }
+    }
+
+    /// Deletes a framebuffer created by [`create_framebuffer_for`], leaving
+    /// its texture intact.
+    ///
+    /// [`create_framebuffer_for`]: Self::create_framebuffer_for
+    pub fn delete_framebuffer_only(&self, framebuffer: u32) {
+        // SAFETY: The function pointers were loaded for the current context.
+        unsafe {
+            (self.delete_framebuffers)(1, &framebuffer);
+        }
+    }
+
+    /// Binds the given framebuffer, or the default framebuffer if `0`.
+    pub fn bind_framebuffer(&self, framebuffer: u32) {
+        // SAFETY: The function pointers were loaded for the current context.
+        unsafe {
+            (self.bind_framebuffer_raw)(FRAMEBUFFER, framebuffer);
+        }
+    }
+
+    /// Reads the RGBA8 pixels of the currently bound framebuffer, with the
+    /// top row first.
+    pub fn read_pixels(&self, width: u32, height: u32) -> Vec<u8> {
+        let stride = width as usize * 4;
+        let mut data = vec![0_u8; stride * height as usize];
+        // SAFETY: The function pointers were loaded for the current context
+        // and the buffer is large enough for the pixels. Rows of RGBA8 pixels
+        // are always 4-byte aligned, matching the default pack alignment.
+        unsafe {
+            (self.read_pixels_raw)(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                RGBA,
+                UNSIGNED_BYTE,
+                data.as_mut_ptr().cast(),
+            );
+        }
+
+        // OpenGL returns the bottom row first.
+        let mut flipped = Vec::with_capacity(data.len());
+        for row in data.chunks_exact(stride.max(1)).rev() {
+            flipped.extend_from_slice(row);
+        }
+        flipped
+    }
+}
@@ -0,0 +1,195 @@
+//! A rendering backend that issues no graphics calls, enabled by the
+//! `null-backend` feature.
+//!
+//! With this backend, [`App::run`](crate::App::run) and
+//! [`App::run_headless`](crate::App::run_headless) neither open a window nor
+//! create an OpenGL context. Everything else works as usual: draws are
+//! recorded, sorted, and batched by [`Graphics::end`](super::Graphics::end),
+//! but nothing is uploaded or drawn. This makes it possible to test drawing
+//! logic on machines without a GPU or display, e.g. in CI, by checking the
+//! [render statistics](super::Graphics::stats) of a frame.
+//!
+//! Images read back with
+//! [`render_to_image`](super::Graphics::render_to_image) are always
+//! transparent.
+
+use std::cell::Cell;
+
+use fugu::{
+    BlendState, BufferKind, BufferLayout, BufferUsage, ImageFilter, ImageFormat, ImageUniform,
+    ImageWrap, PassAction, Uniform, VertexAttribute,
+};
+
+use super::gl;
+
+/// A graphics context that does nothing. Stands in for `fugu::Context`.
+pub struct Context(());
+
+/// A shader that does nothing. Stands in for `fugu::Shader`.
+pub struct Shader(());
+
+/// A pipeline that does nothing. Stands in for `fugu::Pipeline`.
+pub struct Pipeline(());
+
+/// A buffer that stores nothing. Stands in for `fugu::Buffer`.
+pub struct Buffer(());
+
+/// An image that stores nothing. Stands in for `fugu::Image`.
+pub struct Image(());
+
+impl Context {
+    pub(crate) fn new() -> Context {
+        Context(())
+    }
+
+    /// Does nothing. See `fugu::Context::create_shader`.
+    pub fn create_shader(
+        &self,
+        _vert: &str,
+        _frag: &str,
+        _uniforms: &[Uniform],
+        _images: &[ImageUniform],
+    ) -> Shader {
+        Shader(())
+    }
+
+    /// Does nothing. See `fugu::Context::create_pipeline`.
+    pub fn create_pipeline(
+        &self,
+        _shader: Shader,
+        _layouts: &[BufferLayout],
+        _attributes: &[VertexAttribute],
+    ) -> Pipeline {
+        Pipeline(())
+    }
+
+    /// Does nothing. See `fugu::Context::create_buffer`.
+    pub fn create_buffer(&self, _kind: BufferKind, _usage: BufferUsage, _size: usize) -> Buffer {
+        Buffer(())
+    }
+
+    /// Does nothing. See `fugu::Context::create_image_with_data`.
+    pub fn create_image_with_data(
+        &self,
+        _width: u32,
+        _height: u32,
+        _format: ImageFormat,
+        _filter: ImageFilter,
+        _wrap: ImageWrap,
+        _data: &[u8],
+    ) -> Image {
+        Image(())
+    }
+
+    /// Does nothing. See `fugu::Context::set_blend`.
+    pub fn set_blend(&self, _state: BlendState) {}
+
+    /// Does nothing. See `fugu::Context::begin_default_pass`.
+    pub fn begin_default_pass(&self, _action: PassAction) {}
+
+    /// Does nothing. See `fugu::Context::end_render_pass`.
+    pub fn end_render_pass(&self) {}
+
+    /// Does nothing. See `fugu::Context::set_pipeline`.
+    pub fn set_pipeline(&self, _pipeline: &Pipeline) {}
+
+    /// Does nothing. See `fugu::Context::set_vertex_buffer`.
+    pub fn set_vertex_buffer(&self, _buffer: &Buffer) {}
+
+    /// Does nothing. See `fugu::Context::set_index_buffer`.
+    pub fn set_index_buffer(&self, _buffer: &Buffer) {}
+
+    /// Does nothing. See `fugu::Context::set_uniforms`.
+    pub fn set_uniforms<T>(&self, _uniforms: T) {}
+
+    /// Does nothing. See `fugu::Context::set_images`.
+    pub fn set_images(&self, _images: &[&Image]) {}
+
+    /// Does nothing. See `fugu::Context::draw`.
+    pub fn draw(&self, _start: usize, _count: usize, _instances: usize) {}
+
+    /// Does nothing. See `fugu::Context::set_viewport`.
+    pub fn set_viewport(&self, _x: u32, _y: u32, _width: u32, _height: u32) {}
+}
+
+impl Buffer {
+    /// Does nothing. See `fugu::Buffer::update`.
+    pub fn update<T>(&self, _data: &[T]) {}
+}
+
+impl Image {
+    /// Does nothing. See `fugu::Image::update_part`.
+    pub fn update_part(&self, _x: u32, _y: u32, _width: u32, _height: u32, _data: &[u8]) {}
+}
+
+/// Stands in for the raw OpenGL functions of [`gl::Gl`], reporting typical
+/// OpenGL 3.3 limits.
+pub(crate) struct Gl {
+    /// The last framebuffer and texture that were created, so that they are
+    /// distinct like real ones.
+    last_id: Cell<u32>,
+}
+
+impl Gl {
+    pub fn new() -> Gl {
+        Gl {
+            last_id: Cell::new(0),
+        }
+    }
+
+    fn next_id(&self) -> u32 {
+        self.last_id.set(self.last_id.get() + 1);
+        self.last_id.get()
+    }
+
+    pub fn set_enabled(&self, _cap: u32, _enabled: bool) {}
+
+    pub fn get_integers(&self, pname: u32) -> [i32; 4] {
+        match pname {
+            gl::MAX_TEXTURE_SIZE => [8192, 0, 0, 0],
+            gl::MAX_TEXTURE_IMAGE_UNITS => [16, 0, 0, 0],
+            gl::MAX_VIEWPORT_DIMS => [16384, 16384, 0, 0],
+            gl::MAJOR_VERSION => [3, 0, 0, 0],
+            gl::MINOR_VERSION => [3, 0, 0, 0],
+            _ => [0; 4],
+        }
+    }
+
+    pub fn texture_binding(&self, _unit: u32) -> u32 {
+        self.next_id()
+    }
+
+    pub fn version(&self) -> (i32, i32) {
+        (3, 3)
+    }
+
+    pub fn is_es(&self) -> bool {
+        false
+    }
+
+    pub fn set_wireframe(&self, _wireframe: bool) {}
+
+    pub fn set_viewport(&self, _x: i32, _y: i32, _width: i32, _height: i32) {}
+
+    pub fn set_scissor(&self, _x: i32, _y: i32, _width: i32, _height: i32) {}
+
+    pub fn clear_color_buffer(&self, _color: (f32, f32, f32, f32)) {}
+
+    pub fn create_framebuffer(&self, _width: u32, _height: u32) -> (u32, u32) {
+        (self.next_id(), self.next_id())
+    }
+
+    pub fn create_framebuffer_for(&self, _texture: u32) -> u32 {
+        self.next_id()
+    }
+
+    pub fn delete_framebuffer(&self, _framebuffer: u32, _texture: u32) {}
+
+    pub fn delete_framebuffer_only(&self, _framebuffer: u32) {}
+
+    pub fn bind_framebuffer(&self, _framebuffer: u32) {}
+
+    pub fn read_pixels(&self, width: u32, height: u32) -> Vec<u8> {
+        vec![0; width as usize * height as usize * 4]
+    }
+}
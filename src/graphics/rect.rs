@@ -0,0 +1,45 @@
+/// An axis-aligned rectangle.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Rect {
+    /// The x coordinate of the top-left corner of the rectangle.
+    pub x: f32,
+    /// The y coordinate of the top-left corner of the rectangle.
+    pub y: f32,
+    /// The width of the rectangle.
+    pub w: f32,
+    /// The height of the rectangle.
+    pub h: f32,
+}
+
+impl Rect {
+    /// Creates a new rectangle with the given top-left corner and size.
+    pub const fn new(x: f32, y: f32, w: f32, h: f32) -> Rect {
+        Rect { x, y, w, h }
+    }
+
+    /// Returns true if the given point lies within the rectangle.
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x < self.x + self.w && y >= self.y && y < self.y + self.h
+    }
+
+    /// Returns true if the rectangle overlaps the given rectangle.
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.x + other.w
+            && other.x < self.x + self.w
+            && self.y < other.y + other.h
+            && other.y < self.y + self.h
+    }
+
+    /// Returns the overlapping area of the rectangle and the given rectangle,
+    /// or `None` if they don't overlap.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        if !self.intersects(other) {
+            return None;
+        }
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let w = (self.x + self.w).min(other.x + other.w) - x;
+        let h = (self.y + self.h).min(other.y + other.h) - y;
+        Some(Rect::new(x, y, w, h))
+    }
+}
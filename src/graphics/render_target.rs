@@ -0,0 +1,36 @@
+use super::Sprite;
+use crate::assets::ResourceHandle;
+
+/// An offscreen image that can be drawn to instead of the screen, e.g. for
+/// post-processing effects, minimaps, or caching static parts of a scene.
+///
+/// Create a render target with [`Graphics::create_render_target`] and select
+/// it with [`Graphics::set_render_target`]. Its contents can be drawn like any
+/// other sprite using the handle returned by [`sprite`](Self::sprite).
+///
+/// [`Graphics::create_render_target`]: super::Graphics::create_render_target
+/// [`Graphics::set_render_target`]: super::Graphics::set_render_target
+pub struct RenderTarget {
+    pub(super) framebuffer: u32,
+    pub(super) sprite: ResourceHandle<Sprite>,
+    pub(super) width: u32,
+    pub(super) height: u32,
+}
+
+impl RenderTarget {
+    /// Returns a handle to the sprite showing the contents of the render
+    /// target.
+    pub fn sprite(&self) -> ResourceHandle<Sprite> {
+        self.sprite
+    }
+
+    /// Returns the width of the render target.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of the render target.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
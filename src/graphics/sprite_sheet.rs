@@ -0,0 +1,143 @@
+use std::borrow::Cow;
+
+use hashbrown::HashMap;
+
+use super::{Rect, Sprite};
+use crate::assets::ResourceHandle;
+
+/// A sprite made up of equally sized frames laid out in a grid, e.g. the frames
+/// of an animation, with optional named regions.
+///
+/// Frames are numbered in row-major order, starting at the top-left. Draw a
+/// frame with [`Graphics::draw_sprite_frame`] and a named region with
+/// [`Graphics::draw_sprite_region`].
+///
+/// Sprite sheets can be loaded from an atlas description by registering a
+/// custom loader that loads the sprite through
+/// [`LoadContext::load_dependency`] and adds the described regions with
+/// [`add_region`](Self::add_region).
+///
+/// [`Graphics::draw_sprite_frame`]: super::Graphics::draw_sprite_frame
+/// [`Graphics::draw_sprite_region`]: super::Graphics::draw_sprite_region
+/// [`LoadContext::load_dependency`]: crate::assets::LoadContext::load_dependency
+pub struct SpriteSheet {
+    sprite: ResourceHandle<Sprite>,
+    cell_size: (u32, u32),
+    margin: u32,
+    spacing: u32,
+    regions: HashMap<Cow<'static, str>, Rect>,
+}
+
+impl SpriteSheet {
+    /// Creates a new [`SpriteSheet`] dividing the given sprite into cells of
+    /// the given size, without any margin or spacing.
+    pub fn new(sprite: ResourceHandle<Sprite>, cell_width: u32, cell_height: u32) -> Self {
+        SpriteSheet {
+            sprite,
+            cell_size: (cell_width, cell_height),
+            margin: 0,
+            spacing: 0,
+            regions: HashMap::new(),
+        }
+    }
+
+    /// Sets the number of pixels between the edges of the sprite and the
+    /// outermost cells.
+    ///
+    /// The default value is `0`.
+    pub fn with_margin(mut self, margin: u32) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Sets the number of pixels between adjacent cells.
+    ///
+    /// The default value is `0`.
+    pub fn with_spacing(mut self, spacing: u32) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Adds a named region of the sprite, in pixels, replacing any region with
+    /// the same name.
+    pub fn add_region(&mut self, name: impl Into<Cow<'static, str>>, rect: Rect) {
+        self.regions.insert(name.into(), rect);
+    }
+
+    /// Returns the named region with the given name, if any.
+    pub fn region(&self, name: &str) -> Option<Rect> {
+        self.regions.get(name).copied()
+    }
+
+    /// Returns a handle to the sprite of the sprite sheet.
+    pub fn sprite(&self) -> ResourceHandle<Sprite> {
+        self.sprite
+    }
+
+    /// Returns the size of each cell.
+    pub fn cell_size(&self) -> (u32, u32) {
+        self.cell_size
+    }
+
+    /// Returns the number of columns and rows of cells that fit into the given
+    /// sprite, which should be the sprite of the sprite sheet.
+    pub fn grid_size(&self, sprite: &Sprite) -> (u32, u32) {
+        let fit = |size: u32, cell: u32| {
+            let available = size.saturating_sub(2 * self.margin) + self.spacing;
+            available / (cell + self.spacing).max(1)
+        };
+        (
+            fit(sprite.width(), self.cell_size.0),
+            fit(sprite.height(), self.cell_size.1),
+        )
+    }
+
+    /// Returns the number of frames that fit into the given sprite, which
+    /// should be the sprite of the sprite sheet.
+    pub fn frame_count(&self, sprite: &Sprite) -> u32 {
+        let (cols, rows) = self.grid_size(sprite);
+        cols * rows
+    }
+
+    /// Returns the rectangle of the frame with the given index in the given
+    /// sprite, which should be the sprite of the sprite sheet, in pixels.
+    ///
+    /// Indices past the last frame wrap around to the first frame, so a
+    /// frame counter can be passed directly to loop an animation.
+    pub fn frame_rect(&self, sprite: &Sprite, index: u32) -> Rect {
+        let (cols, rows) = self.grid_size(sprite);
+        let index = index % (cols * rows).max(1);
+        let (x, y) = grid_cell(index, cols, self.cell_size, self.margin, self.spacing);
+        let (cw, ch) = self.cell_size;
+        Rect::new(x as f32, y as f32, cw as f32, ch as f32)
+    }
+}
+
+/// Returns the position of the top-left corner of the cell with the given
+/// index in a grid with the given number of columns, in row-major order.
+pub(crate) fn grid_cell(
+    index: u32,
+    cols: u32,
+    (cell_width, cell_height): (u32, u32),
+    margin: u32,
+    spacing: u32,
+) -> (u32, u32) {
+    let (col, row) = (index % cols.max(1), index / cols.max(1));
+    (
+        margin + col * (cell_width + spacing),
+        margin + row * (cell_height + spacing),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_cells_are_row_major() {
+        let cells: Vec<_> = (0..4).map(|i| grid_cell(i, 3, (8, 4), 1, 2)).collect();
+        assert_eq!(cells, [(1, 1), (11, 1), (21, 1), (1, 7)]);
+        // A grid without columns does not divide by zero.
+        assert_eq!(grid_cell(5, 0, (8, 4), 0, 0), (0, 20));
+    }
+}
@@ -1,5 +1,9 @@
 //! Types relating to user input.
 
+use std::time::Duration;
+
+use crate::graphics::Graphics;
+
 /// Symbolic names for virtual key codes.
 #[repr(u8)]
 #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
@@ -95,10 +99,158 @@ pub enum KeyCode {
     /// The '9' key on the numeric keypad.
     Num9,
 
+    /// The '+' key on the numeric keypad.
+    NumAdd,
+    /// The '-' key on the numeric keypad.
+    NumSubtract,
+    /// The '*' key on the numeric keypad.
+    NumMultiply,
+    /// The '/' key on the numeric keypad.
+    NumDivide,
+    /// The '.' key on the numeric keypad.
+    NumDecimal,
+    /// The Enter key on the numeric keypad.
+    NumEnter,
+
     Left,
     Right,
     Up,
     Down,
+
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+
+    /// The '-' key.
+    Minus,
+    /// The '=' key.
+    Equals,
+    /// The '[' key.
+    LeftBracket,
+    /// The ']' key.
+    RightBracket,
+    /// The ';' key.
+    Semicolon,
+    /// The apostrophe key.
+    Apostrophe,
+    /// The ',' key.
+    Comma,
+    /// The '.' key.
+    Period,
+    /// The '/' key.
+    Slash,
+    /// The backslash key.
+    Backslash,
+    /// The grave accent key.
+    Grave,
+
+    CapsLock,
+    NumLock,
+    ScrollLock,
+    PrintScreen,
+    Pause,
+    /// The left Windows, Command, or Super key.
+    LeftSuper,
+    /// The right Windows, Command, or Super key.
+    RightSuper,
+    /// The context menu key.
+    Menu,
+}
+
+/// A set of modifier keys.
+///
+/// Modifiers can be combined using the `|` operator, e.g.
+/// `Modifiers::CONTROL | Modifiers::SHIFT`. Left and right modifier keys are
+/// treated the same.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Modifiers {
+    /// Whether either Control key is included.
+    pub control: bool,
+    /// Whether either Shift key is included.
+    pub shift: bool,
+    /// Whether either Alt key is included.
+    pub alt: bool,
+}
+
+#[allow(missing_docs)]
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers {
+        control: false,
+        shift: false,
+        alt: false,
+    };
+    pub const CONTROL: Modifiers = Modifiers {
+        control: true,
+        ..Modifiers::NONE
+    };
+    pub const SHIFT: Modifiers = Modifiers {
+        shift: true,
+        ..Modifiers::NONE
+    };
+    pub const ALT: Modifiers = Modifiers {
+        alt: true,
+        ..Modifiers::NONE
+    };
+}
+
+impl std::ops::BitOr for Modifiers {
+    type Output = Modifiers;
+
+    fn bitor(self, rhs: Modifiers) -> Modifiers {
+        Modifiers {
+            control: self.control || rhs.control,
+            shift: self.shift || rhs.shift,
+            alt: self.alt || rhs.alt,
+        }
+    }
+}
+
+/// A key being pressed or released.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct KeyEvent {
+    /// The key that was pressed or released.
+    pub key: KeyCode,
+    /// Whether the key was pressed (`true`) or released (`false`).
+    pub pressed: bool,
+    /// The time at which the event occurred, relative to the start of the
+    /// application.
+    ///
+    /// This has a resolution of one millisecond.
+    pub timestamp: Duration,
+}
+
+/// A point of contact on a touch screen.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Touch {
+    /// An identifier for the touch that stays the same for as long as the touch
+    /// is held down.
+    pub id: i64,
+    /// The position of the touch in pixels, relative to the top-left corner of
+    /// the window.
+    pub position: (f32, f32),
+    /// The position of the touch, normalized to the range `[0, 1]`.
+    pub normalized_position: (f32, f32),
+    /// The pressure of the touch, normalized to the range `[0, 1]`.
+    pub pressure: f32,
+}
+
+/// Mouse buttons.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+#[non_exhaustive]
+#[allow(missing_docs)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
 }
 
 /// An interface for querying user input. Accessible from [`App`](crate::App) by
@@ -107,7 +259,22 @@ pub struct Input {
     pub(crate) keys_down: Vec<KeyCode>,
     pub(crate) keys_pressed: Vec<KeyCode>,
     pub(crate) keys_released: Vec<KeyCode>,
+    pub(crate) key_events: Vec<KeyEvent>,
+    pub(crate) combos_pressed: Vec<(Modifiers, KeyCode)>,
+    pub(crate) frame_timestamp: Duration,
     pub(crate) chars_pressed: Vec<char>,
+    /// Key presses, including repeats, with the number of characters typed
+    /// before each, so that [`TextInput`] can apply them in order.
+    pub(crate) text_keys: Vec<(usize, KeyCode)>,
+    pub(crate) touches: Vec<Touch>,
+    pub(crate) mouse_position: (f32, f32),
+    pub(crate) mouse_delta: (f32, f32),
+    pub(crate) mouse_down: Vec<MouseButton>,
+    pub(crate) mouse_pressed: Vec<MouseButton>,
+    pub(crate) mouse_released: Vec<MouseButton>,
+    /// The left stick navigation state of each connected gamepad, mirrored
+    /// from [`Gamepads`] every frame.
+    pub(crate) sticks: Vec<StickState>,
 }
 
 impl Input {
@@ -116,13 +283,77 @@ impl Input {
             keys_down: Vec::new(),
             keys_pressed: Vec::new(),
             keys_released: Vec::new(),
+            key_events: Vec::new(),
+            combos_pressed: Vec::new(),
+            frame_timestamp: Duration::ZERO,
             chars_pressed: Vec::new(),
+            text_keys: Vec::new(),
+            touches: Vec::new(),
+            mouse_position: (0., 0.),
+            mouse_delta: (0., 0.),
+            mouse_down: Vec::new(),
+            mouse_pressed: Vec::new(),
+            mouse_released: Vec::new(),
+            sticks: Vec::new(),
         }
     }
 
     pub(crate) fn update(&mut self) {
         self.keys_pressed.clear();
         self.keys_released.clear();
+        self.key_events.clear();
+        self.combos_pressed.clear();
+        self.chars_pressed.clear();
+        self.text_keys.clear();
+        self.mouse_pressed.clear();
+        self.mouse_released.clear();
+        self.mouse_delta = (0., 0.);
+    }
+
+    pub(crate) fn press_key(&mut self, key: KeyCode, timestamp: Duration) {
+        self.key_events.push(KeyEvent {
+            key,
+            pressed: true,
+            timestamp,
+        });
+        self.combos_pressed.push((self.modifiers(), key));
+        self.text_keys.push((self.chars_pressed.len(), key));
+        if !self.keys_down.contains(&key) {
+            self.keys_down.push(key);
+        }
+        if !self.keys_pressed.contains(&key) {
+            self.keys_pressed.push(key);
+        }
+    }
+
+    /// Records a key press repeated by holding the key down, which only
+    /// affects [`TextInput`].
+    pub(crate) fn repeat_key(&mut self, key: KeyCode) {
+        self.text_keys.push((self.chars_pressed.len(), key));
+    }
+
+    pub(crate) fn release_key(&mut self, key: KeyCode, timestamp: Duration) {
+        self.key_events.push(KeyEvent {
+            key,
+            pressed: false,
+            timestamp,
+        });
+        self.keys_down.retain(|&k| k != key);
+        self.keys_released.push(key);
+    }
+
+    pub(crate) fn press_mouse(&mut self, button: MouseButton) {
+        if !self.mouse_down.contains(&button) {
+            self.mouse_down.push(button);
+        }
+        if !self.mouse_pressed.contains(&button) {
+            self.mouse_pressed.push(button);
+        }
+    }
+
+    pub(crate) fn release_mouse(&mut self, button: MouseButton) {
+        self.mouse_down.retain(|&b| b != button);
+        self.mouse_released.push(button);
     }
 
     /// Returns true if the specified key is currently down.
@@ -140,6 +371,27 @@ impl Input {
         self.keys_released.contains(&key)
     }
 
+    /// Returns the modifier keys that are currently down.
+    pub fn modifiers(&self) -> Modifiers {
+        let down = |left, right| self.is_key_down(left) || self.is_key_down(right);
+        Modifiers {
+            control: down(KeyCode::LeftControl, KeyCode::RightControl),
+            shift: down(KeyCode::LeftShift, KeyCode::RightShift),
+            alt: down(KeyCode::LeftAlt, KeyCode::RightAlt),
+        }
+    }
+
+    /// Returns true if the specified key was pressed since the last update
+    /// while exactly the specified modifier keys were already down, e.g. for
+    /// keyboard shortcuts like Ctrl+S.
+    ///
+    /// Unlike checking [`modifiers`](Self::modifiers) and
+    /// [`is_key_pressed`](Self::is_key_pressed) separately, this only considers
+    /// the modifiers that were down at the moment the key was pressed.
+    pub fn is_combo_pressed(&self, modifiers: Modifiers, key: KeyCode) -> bool {
+        self.combos_pressed.contains(&(modifiers, key))
+    }
+
     /// Returns an iterator over all keys that are currently down.
     pub fn get_keys_down(&self) -> impl Iterator<Item = KeyCode> + '_ {
         self.keys_down.iter().copied()
@@ -157,9 +409,787 @@ impl Input {
         self.keys_released.iter().copied()
     }
 
-    /// Drains and returns an iterator over all characters that have been
-    /// pressed.
-    pub fn get_chars_pressed(&mut self) -> impl Iterator<Item = char> + '_ {
-        self.chars_pressed.drain(..)
+    /// Returns an iterator over all key presses and releases since the last
+    /// update, in the order they occurred.
+    ///
+    /// Unlike [`get_keys_pressed`] and [`get_keys_released`], this includes
+    /// the time at which each event occurred, which makes it possible to
+    /// measure input timing more precisely than the frame rate allows.
+    ///
+    /// [`get_keys_pressed`]: Self::get_keys_pressed
+    /// [`get_keys_released`]: Self::get_keys_released
+    pub fn key_events(&self) -> impl Iterator<Item = KeyEvent> + '_ {
+        self.key_events.iter().copied()
+    }
+
+    /// Returns the time at which the specified key was pressed if it was
+    /// pressed since the last update, relative to the start of the
+    /// application.
+    ///
+    /// If the key was pressed multiple times, the time of the first press is
+    /// returned.
+    pub fn key_pressed_timestamp(&self, key: KeyCode) -> Option<Duration> {
+        self.key_events
+            .iter()
+            .find(|e| e.key == key && e.pressed)
+            .map(|e| e.timestamp)
+    }
+
+    /// Returns the time at which the events of the current frame finished
+    /// being received, relative to the start of the application.
+    ///
+    /// Key events received this frame occurred before this time, so the
+    /// difference to their [timestamps](Self::key_pressed_timestamp) is how
+    /// long before the frame they occurred.
+    pub fn frame_timestamp(&self) -> Duration {
+        self.frame_timestamp
+    }
+
+    /// Returns the position of the mouse cursor in window coordinates, i.e.
+    /// in pixels relative to the top-left corner of the window.
+    pub fn mouse_position(&self) -> (f32, f32) {
+        self.mouse_position
+    }
+
+    /// Returns how far the mouse moved since the last update, in pixels.
+    ///
+    /// Unlike the [position](Self::mouse_position), this keeps changing in
+    /// [relative mouse mode](crate::window::Window::set_relative_mouse), e.g.
+    /// for turning a camera.
+    pub fn mouse_delta(&self) -> (f32, f32) {
+        self.mouse_delta
+    }
+
+    /// Returns true if the specified mouse button is currently down.
+    pub fn is_mouse_down(&self, button: MouseButton) -> bool {
+        self.mouse_down.contains(&button)
+    }
+
+    /// Returns true if the specified mouse button was pressed since the last
+    /// update.
+    pub fn is_mouse_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_pressed.contains(&button)
+    }
+
+    /// Returns true if the specified mouse button was released since the last
+    /// update.
+    pub fn is_mouse_released(&self, button: MouseButton) -> bool {
+        self.mouse_released.contains(&button)
+    }
+
+    /// Returns the position of the mouse cursor in world coordinates, i.e. in
+    /// the same coordinates that are passed to the drawing methods of the
+    /// given [`Graphics`].
+    ///
+    /// See [`Graphics::screen_to_world`] for more information.
+    pub fn mouse_world_position(&self, g: &Graphics) -> (f32, f32) {
+        let (x, y) = self.mouse_position;
+        g.screen_to_world(x, y)
+    }
+
+    /// Returns an iterator over all touches that are currently held down.
+    pub fn touches(&self) -> impl Iterator<Item = Touch> + '_ {
+        self.touches.iter().copied()
+    }
+
+    /// Returns the touch with the given id if it is currently held down.
+    pub fn touch(&self, id: i64) -> Option<Touch> {
+        self.touches.iter().copied().find(|touch| touch.id == id)
+    }
+
+    /// Returns the direction the left stick of the gamepad at the given index
+    /// is held in, if any. See [`Gamepad::stick_direction`].
+    pub fn stick_direction(&self, id: usize) -> Option<Direction> {
+        self.sticks.get(id).and_then(|stick| stick.direction)
+    }
+
+    /// Returns the direction the left stick of the gamepad at the given index
+    /// started being held in during the last update, if any. See
+    /// [`Gamepad::stick_just_moved`].
+    pub fn stick_just_moved(&self, id: usize) -> Option<Direction> {
+        self.sticks.get(id).and_then(|stick| stick.just_moved)
+    }
+
+    /// Returns the direction to step in if the left stick of the gamepad at
+    /// the given index was just moved or has been held long enough to repeat.
+    /// See [`Gamepad::stick_repeated`].
+    pub fn stick_repeated(&self, id: usize) -> Option<Direction> {
+        self.sticks.get(id).and_then(|stick| stick.repeated)
+    }
+
+    /// Returns an iterator over all characters that were typed since the last
+    /// update, in the order they were typed.
+    ///
+    /// Unlike [`get_keys_pressed`](Self::get_keys_pressed), this takes the
+    /// keyboard layout and modifiers into account, so it is suitable for text
+    /// entry.
+    pub fn get_chars_pressed(&self) -> impl Iterator<Item = char> + '_ {
+        self.chars_pressed.iter().copied()
+    }
+}
+
+/// Gamepad buttons, named after their positions on an Xbox controller.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+#[non_exhaustive]
+#[allow(missing_docs)]
+pub enum GamepadButton {
+    A,
+    B,
+    X,
+    Y,
+    Back,
+    Guide,
+    Start,
+    LeftStick,
+    RightStick,
+    LeftShoulder,
+    RightShoulder,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+/// Analog gamepad axes.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub(crate) enum GamepadAxis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+/// The state of the left stick of a gamepad when used for navigation.
+#[derive(Clone, Copy, Default, Debug)]
+pub(crate) struct StickState {
+    direction: Option<Direction>,
+    just_moved: Option<Direction>,
+    repeated: Option<Direction>,
+}
+
+/// The state of a single connected gamepad. Returned by the methods of
+/// [`Gamepads`].
+#[derive(Clone, Debug)]
+pub struct Gamepad {
+    pub(crate) id: u32,
+    index: usize,
+    buttons_down: Vec<GamepadButton>,
+    buttons_pressed: Vec<GamepadButton>,
+    buttons_released: Vec<GamepadButton>,
+    axes: [f32; 6],
+    deadzone: f32,
+    stick: StickState,
+    stick_repeat: DirectionRepeat,
+}
+
+impl Gamepad {
+    fn new(id: u32, index: usize, deadzone: f32, stick_repeat: DirectionRepeat) -> Gamepad {
+        Gamepad {
+            id,
+            index,
+            buttons_down: Vec::new(),
+            buttons_pressed: Vec::new(),
+            buttons_released: Vec::new(),
+            axes: [0.; 6],
+            deadzone,
+            stick: StickState::default(),
+            stick_repeat,
+        }
+    }
+
+    /// Thresholds the left stick into a direction and advances its repeat
+    /// timer by the given time in seconds.
+    fn update_stick(&mut self, dt: f32, diagonals: bool) {
+        let (x, y) = (
+            self.axes[GamepadAxis::LeftX as usize],
+            self.axes[GamepadAxis::LeftY as usize],
+        );
+        let direction = Direction::from_axes(x, y, self.deadzone, diagonals);
+        let repeated = self.stick_repeat.update(direction, dt);
+        self.stick = StickState {
+            direction,
+            just_moved: if self.stick_repeat.just_moved() {
+                direction
+            } else {
+                None
+            },
+            repeated,
+        };
+    }
+
+    fn update(&mut self) {
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+    }
+
+    pub(crate) fn press_button(&mut self, button: GamepadButton) {
+        if !self.buttons_down.contains(&button) {
+            self.buttons_down.push(button);
+        }
+        if !self.buttons_pressed.contains(&button) {
+            self.buttons_pressed.push(button);
+        }
+    }
+
+    pub(crate) fn release_button(&mut self, button: GamepadButton) {
+        self.buttons_down.retain(|&b| b != button);
+        self.buttons_released.push(button);
+    }
+
+    pub(crate) fn set_axis(&mut self, axis: GamepadAxis, value: f32) {
+        self.axes[axis as usize] = value;
+    }
+
+    /// Returns the index of the gamepad, which stays the same for as long as
+    /// it is connected. See [`Gamepads::gamepad`].
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns true if the specified button is currently down.
+    pub fn is_button_down(&self, button: GamepadButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    /// Returns true if the specified button was pressed since the last update.
+    pub fn is_button_pressed(&self, button: GamepadButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    /// Returns true if the specified button was released since the last
+    /// update.
+    pub fn is_button_released(&self, button: GamepadButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    /// Returns the position of the left stick, with each axis in the range
+    /// `[-1, 1]` and positive `y` pointing down.
+    ///
+    /// The deadzone set by [`Gamepads::set_deadzone`] is applied.
+    pub fn left_stick(&self) -> (f32, f32) {
+        self.stick(GamepadAxis::LeftX, GamepadAxis::LeftY)
+    }
+
+    /// Returns the position of the right stick, with each axis in the range
+    /// `[-1, 1]` and positive `y` pointing down.
+    ///
+    /// The deadzone set by [`Gamepads::set_deadzone`] is applied.
+    pub fn right_stick(&self) -> (f32, f32) {
+        self.stick(GamepadAxis::RightX, GamepadAxis::RightY)
+    }
+
+    /// Returns how far the left trigger is pressed, in the range `[0, 1]`.
+    pub fn left_trigger(&self) -> f32 {
+        self.axes[GamepadAxis::LeftTrigger as usize]
+    }
+
+    /// Returns how far the right trigger is pressed, in the range `[0, 1]`.
+    pub fn right_trigger(&self) -> f32 {
+        self.axes[GamepadAxis::RightTrigger as usize]
+    }
+
+    /// Returns the direction the left stick is held in, if it is outside of
+    /// the deadzone.
+    ///
+    /// Only the four cardinal directions are returned unless enabled with
+    /// [`Gamepads::set_stick_diagonals`].
+    pub fn stick_direction(&self) -> Option<Direction> {
+        self.stick.direction
+    }
+
+    /// Returns the direction the left stick started being held in during the
+    /// last update, if any.
+    ///
+    /// Holding the stick in the same direction only reports it once, so this
+    /// is suitable for moving through a menu one entry at a time.
+    pub fn stick_just_moved(&self) -> Option<Direction> {
+        self.stick.just_moved
+    }
+
+    /// Returns the direction to step in, if the left stick was just moved or
+    /// has been held in the same direction long enough to repeat.
+    ///
+    /// The repeat timing is set with [`Gamepads::set_stick_repeat`].
+    pub fn stick_repeated(&self) -> Option<Direction> {
+        self.stick.repeated
+    }
+
+    fn stick(&self, x: GamepadAxis, y: GamepadAxis) -> (f32, f32) {
+        let (x, y) = (self.axes[x as usize], self.axes[y as usize]);
+        let len = (x * x + y * y).sqrt();
+        if len <= self.deadzone {
+            return (0., 0.);
+        }
+        // Rescale so that the output starts at zero right outside of the
+        // deadzone instead of jumping.
+        let scale = ((len - self.deadzone) / (1. - self.deadzone)).min(1.) / len;
+        (x * scale, y * scale)
+    }
+}
+
+/// An interface for querying connected gamepads. Accessible from
+/// [`App`](crate::App) by default.
+pub struct Gamepads {
+    /// The connected gamepads by index. Disconnecting a gamepad leaves its
+    /// slot empty, so that the indices of the others stay the same.
+    gamepads: Vec<Option<Gamepad>>,
+    deadzone: f32,
+    stick_diagonals: bool,
+    stick_repeat: (f32, f32),
+}
+
+impl Gamepads {
+    pub(crate) fn new() -> Gamepads {
+        let repeat = DirectionRepeat::default();
+        Gamepads {
+            gamepads: Vec::new(),
+            deadzone: 0.15,
+            stick_diagonals: false,
+            stick_repeat: (repeat.delay, repeat.interval),
+        }
+    }
+
+    pub(crate) fn update(&mut self) {
+        self.gamepads.iter_mut().flatten().for_each(Gamepad::update);
+    }
+
+    /// Updates the stick navigation state of all gamepads once their events
+    /// for the frame have been processed, and mirrors it into `input`.
+    pub(crate) fn update_sticks(&mut self, dt: f32, input: &mut Input) {
+        input.sticks.clear();
+        for gamepad in &mut self.gamepads {
+            input.sticks.push(match gamepad {
+                Some(gamepad) => {
+                    gamepad.update_stick(dt, self.stick_diagonals);
+                    gamepad.stick
+                }
+                None => StickState::default(),
+            });
+        }
+    }
+
+    pub(crate) fn connect(&mut self, id: u32) {
+        if self.gamepads.iter().flatten().any(|e| e.id == id) {
+            return;
+        }
+        // Reuse the first slot left by a disconnected gamepad.
+        let index = match self.gamepads.iter().position(Option::is_none) {
+            Some(index) => index,
+            None => {
+                self.gamepads.push(None);
+                self.gamepads.len() - 1
+            }
+        };
+        let (delay, interval) = self.stick_repeat;
+        let stick_repeat = DirectionRepeat::new(delay, interval);
+        self.gamepads[index] = Some(Gamepad::new(id, index, self.deadzone, stick_repeat));
+    }
+
+    pub(crate) fn disconnect(&mut self, id: u32) {
+        for slot in &mut self.gamepads {
+            if slot.as_ref().map(|e| e.id) == Some(id) {
+                *slot = None;
+            }
+        }
+        while let Some(None) = self.gamepads.last() {
+            self.gamepads.pop();
+        }
+    }
+
+    pub(crate) fn get_mut(&mut self, id: u32) -> Option<&mut Gamepad> {
+        self.gamepads.iter_mut().flatten().find(|e| e.id == id)
+    }
+
+    /// Returns an iterator over all connected gamepads, ordered by their
+    /// [indices](Gamepad::index).
+    pub fn gamepads(&self) -> impl Iterator<Item = &Gamepad> + '_ {
+        self.gamepads.iter().flatten()
+    }
+
+    /// Returns the connected gamepad with the given index, if any.
+    ///
+    /// Gamepads are assigned the lowest index that is not in use when they
+    /// are connected, and keep it until they are disconnected, so e.g. the
+    /// second player's gamepad stays at index `1` even if the first player's
+    /// is disconnected.
+    pub fn gamepad(&self, index: usize) -> Option<&Gamepad> {
+        self.gamepads.get(index).and_then(Option::as_ref)
+    }
+
+    /// Sets the radius of the deadzone of the analog sticks of all gamepads,
+    /// relative to their full range.
+    ///
+    /// Stick positions within the deadzone are reported as `(0, 0)`, which
+    /// hides the drift of sticks that do not fully return to the center. The
+    /// default value is `0.15`.
+    pub fn set_deadzone(&mut self, deadzone: f32) {
+        self.deadzone = deadzone.clamp(0., 0.99);
+        for gamepad in self.gamepads.iter_mut().flatten() {
+            gamepad.deadzone = self.deadzone;
+        }
+    }
+
+    /// Sets whether the left stick of all gamepads reports diagonal
+    /// [directions](Gamepad::stick_direction) in addition to the four cardinal
+    /// ones. Disabled by default.
+    pub fn set_stick_diagonals(&mut self, diagonals: bool) {
+        self.stick_diagonals = diagonals;
+    }
+
+    /// Sets the initial delay and the interval in seconds after which holding
+    /// the left stick of any gamepad in the same direction
+    /// [repeats](Gamepad::stick_repeated).
+    ///
+    /// The default values are `0.4` and `0.1` respectively.
+    pub fn set_stick_repeat(&mut self, delay: f32, interval: f32) {
+        self.stick_repeat = (delay, interval);
+        for gamepad in self.gamepads.iter_mut().flatten() {
+            gamepad.stick_repeat.delay = delay;
+            gamepad.stick_repeat.interval = interval;
+        }
+    }
+}
+
+/// A discrete direction, as produced by a directional pad or a thresholded
+/// analog stick.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+#[allow(missing_docs)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl Direction {
+    /// Thresholds the given analog axes into a direction.
+    ///
+    /// Axes are expected to be in the range `[-1, 1]`, with positive `y`
+    /// pointing down. Returns `None` if the stick is within the deadzone. If
+    /// `diagonals` is `false`, only the four cardinal directions are returned.
+    pub fn from_axes(x: f32, y: f32, deadzone: f32, diagonals: bool) -> Option<Direction> {
+        use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+
+        if (x * x + y * y).sqrt() <= deadzone {
+            return None;
+        }
+
+        let angle = y.atan2(x);
+        if diagonals {
+            Some(match ((angle / FRAC_PI_4).round() as i32).rem_euclid(8) {
+                0 => Direction::Right,
+                1 => Direction::DownRight,
+                2 => Direction::Down,
+                3 => Direction::DownLeft,
+                4 => Direction::Left,
+                5 => Direction::UpLeft,
+                6 => Direction::Up,
+                _ => Direction::UpRight,
+            })
+        } else {
+            Some(match ((angle / FRAC_PI_2).round() as i32).rem_euclid(4) {
+                0 => Direction::Right,
+                1 => Direction::Down,
+                2 => Direction::Left,
+                _ => Direction::Up,
+            })
+        }
+    }
+}
+
+/// A helper for turning a held [`Direction`] into discrete, repeating steps,
+/// e.g. for navigating menus.
+///
+/// A step is emitted as soon as a new direction is held, then again after the
+/// initial delay, and then repeatedly at the given interval for as long as the
+/// direction is held.
+#[derive(Clone, Debug)]
+pub struct DirectionRepeat {
+    delay: f32,
+    interval: f32,
+    current: Option<Direction>,
+    timer: f32,
+    just_moved: bool,
+}
+
+impl Default for DirectionRepeat {
+    fn default() -> Self {
+        Self::new(0.4, 0.1)
+    }
+}
+
+impl DirectionRepeat {
+    /// Creates a new `DirectionRepeat` with the given initial delay and repeat
+    /// interval in seconds.
+    ///
+    /// The default values are `0.4` and `0.1` respectively.
+    pub fn new(delay: f32, interval: f32) -> Self {
+        Self {
+            delay,
+            interval,
+            current: None,
+            timer: 0.,
+            just_moved: false,
+        }
+    }
+
+    /// Updates the helper with the currently held direction and the time in
+    /// seconds since the last update.
+    ///
+    /// Returns the direction if a step should be taken this update.
+    pub fn update(&mut self, direction: Option<Direction>, dt: f32) -> Option<Direction> {
+        self.just_moved = direction.is_some() && direction != self.current;
+        self.current = direction;
+
+        if self.just_moved {
+            self.timer = self.delay;
+            return direction;
+        }
+
+        direction.and_then(|direction| {
+            self.timer -= dt;
+            if self.timer <= 0. {
+                self.timer += self.interval.max(f32::EPSILON);
+                Some(direction)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Returns the currently held direction.
+    pub fn direction(&self) -> Option<Direction> {
+        self.current
+    }
+
+    /// Returns true if a new direction started being held during the last
+    /// update.
+    pub fn just_moved(&self) -> bool {
+        self.just_moved
+    }
+}
+
+/// A helper for editing a single line of text using keyboard input, e.g. for
+/// a text field.
+///
+/// Call [`update`] every frame while the text field is focused. Typed
+/// characters are inserted at the caret, which can be moved with the arrow,
+/// Home, and End keys. Backspace and Delete remove characters around the
+/// caret, and Enter submits the text. Holding down an editing key repeats it.
+/// Keys and characters are applied in the order they were typed, even within
+/// a single frame.
+///
+/// [`update`]: Self::update
+#[derive(Clone, Debug, Default)]
+pub struct TextInput {
+    text: String,
+    caret: usize,
+    max_len: Option<usize>,
+}
+
+impl TextInput {
+    /// Creates a new, empty `TextInput`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns the current text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replaces the current text and moves the caret to the end.
+    ///
+    /// The text is truncated to the maximum length if there is one.
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+        if let Some(max_len) = self.max_len {
+            self.truncate(max_len);
+        }
+        self.caret = self.text.len();
+    }
+
+    /// Returns the byte index of the caret in the text.
+    pub fn caret(&self) -> usize {
+        self.caret
+    }
+
+    /// Moves the caret to the given byte index in the text, which is clamped
+    /// to the length of the text and moved back to the nearest character
+    /// boundary.
+    ///
+    /// This can be used together with `Font::text_index_at` to place the
+    /// caret where the user clicked.
+    pub fn set_caret(&mut self, index: usize) {
+        let mut index = index.min(self.text.len());
+        while !self.text.is_char_boundary(index) {
+            index -= 1;
+        }
+        self.caret = index;
+    }
+
+    /// Sets the maximum length of the text in characters, or `None` for no
+    /// limit.
+    ///
+    /// The current text is truncated if it is longer than the new maximum.
+    ///
+    /// The default value is `None`.
+    pub fn set_max_len(&mut self, max_len: Option<usize>) {
+        self.max_len = max_len;
+        if let Some(max_len) = max_len {
+            self.truncate(max_len);
+        }
+    }
+
+    /// Updates the text using the given input, consuming any characters and
+    /// editing keys that have been pressed.
+    ///
+    /// Returns true if Enter was pressed.
+    pub fn update(&mut self, input: &mut Input) -> bool {
+        let mut chars = input.chars_pressed.drain(..);
+        let mut typed = 0;
+        for (typed_before, key) in input.text_keys.drain(..) {
+            for c in chars.by_ref().take(typed_before.saturating_sub(typed)) {
+                self.insert(c);
+            }
+            typed = typed.max(typed_before);
+            self.apply_key(key);
+        }
+        for c in chars {
+            self.insert(c);
+        }
+
+        input.is_key_pressed(KeyCode::Enter)
+    }
+
+    fn insert(&mut self, c: char) {
+        let full = self
+            .max_len
+            .map_or(false, |max_len| self.text.chars().count() >= max_len);
+        if !c.is_control() && !full {
+            self.text.insert(self.caret, c);
+            self.caret += c.len_utf8();
+        }
+    }
+
+    fn apply_key(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Backspace => {
+                if let Some(c) = self.text[..self.caret].chars().next_back() {
+                    self.caret -= c.len_utf8();
+                    self.text.remove(self.caret);
+                }
+            }
+            KeyCode::Delete if self.caret < self.text.len() => {
+                self.text.remove(self.caret);
+            }
+            KeyCode::Left => {
+                if let Some(c) = self.text[..self.caret].chars().next_back() {
+                    self.caret -= c.len_utf8();
+                }
+            }
+            KeyCode::Right => {
+                if let Some(c) = self.text[self.caret..].chars().next() {
+                    self.caret += c.len_utf8();
+                }
+            }
+            KeyCode::Home => self.caret = 0,
+            KeyCode::End => self.caret = self.text.len(),
+            _ => {}
+        }
+    }
+
+    fn truncate(&mut self, max_len: usize) {
+        if let Some((i, _)) = self.text.char_indices().nth(max_len) {
+            self.text.truncate(i);
+            self.caret = self.caret.min(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_input_applies_keys_in_typed_order() {
+        let mut input = Input::new();
+        let mut text_input = TextInput::new();
+
+        // "ab", Backspace, "c", all within one frame.
+        input.chars_pressed.extend(['a', 'b']);
+        input.press_key(KeyCode::Backspace, Duration::ZERO);
+        input.chars_pressed.push('c');
+        text_input.update(&mut input);
+        assert_eq!(text_input.text(), "ac");
+
+        // Holding Backspace repeats it.
+        input.update();
+        input.repeat_key(KeyCode::Backspace);
+        input.repeat_key(KeyCode::Backspace);
+        text_input.update(&mut input);
+        assert_eq!(text_input.text(), "");
+        assert_eq!(text_input.caret(), 0);
+    }
+
+    #[test]
+    fn gamepads_keep_their_index() {
+        let mut gamepads = Gamepads::new();
+        for id in [10, 11, 12] {
+            gamepads.connect(id);
+        }
+        gamepads.disconnect(10);
+        let ids = |gamepads: &Gamepads| {
+            (0..3)
+                .map(|i| gamepads.gamepad(i).map(|e| e.id))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&gamepads), [None, Some(11), Some(12)]);
+        assert_eq!(gamepads.gamepad(2).unwrap().index(), 2);
+
+        // Reconnecting fills the first free slot.
+        gamepads.connect(13);
+        assert_eq!(ids(&gamepads), [Some(13), Some(11), Some(12)]);
+        gamepads.disconnect(12);
+        gamepads.disconnect(11);
+        gamepads.connect(14);
+        assert_eq!(ids(&gamepads), [Some(13), Some(14), None]);
+    }
+
+    #[test]
+    fn stick_moves_once_then_repeats() {
+        let mut input = Input::new();
+        let mut gamepads = Gamepads::new();
+        gamepads.set_stick_repeat(0.4, 0.1);
+        gamepads.connect(7);
+        let mut frame = |gamepads: &mut Gamepads, x: f32| {
+            gamepads.get_mut(7).unwrap().set_axis(GamepadAxis::LeftX, x);
+            gamepads.update_sticks(0.25, &mut input);
+            (
+                input.stick_direction(0),
+                input.stick_just_moved(0),
+                input.stick_repeated(0),
+            )
+        };
+
+        let right = Some(Direction::Right);
+        // Within the deadzone.
+        assert_eq!(frame(&mut gamepads, 0.1), (None, None, None));
+        assert_eq!(frame(&mut gamepads, 0.9), (right, right, right));
+        // Held, but the initial delay has not passed yet.
+        assert_eq!(frame(&mut gamepads, 0.9), (right, None, None));
+        assert_eq!(frame(&mut gamepads, 0.9), (right, None, right));
+        assert_eq!(frame(&mut gamepads, 0.9), (right, None, right));
+        // Moving to another direction is reported right away.
+        let left = Some(Direction::Left);
+        assert_eq!(frame(&mut gamepads, -0.9), (left, left, left));
+        assert_eq!(frame(&mut gamepads, 0.), (None, None, None));
+        assert_eq!(input.stick_direction(1), None);
     }
 }
@@ -28,20 +28,21 @@
 //!
 //! ```rust
 //! use pufferfish::graphics::{Color, Graphics};
-//! use pufferfish::App;
+//! use pufferfish::{App, RunError};
 //!
 //! struct State {
 //!     // Your game state...
 //! }
 //!
-//! fn main() {
+//! fn main() -> Result<(), RunError> {
 //!     App::new()
 //!         .with_title("Hello World")
 //!         .add_state(State::new()) // Add your state
 //!         .add_init_callback(init) // Add your callbacks
 //!         .add_frame_callback(update)
 //!         .add_frame_callback(draw)
-//!         .run();
+//!         .run()?;
+//!     Ok(())
 //! }
 //!
 //! fn init(state: &mut State) {
@@ -55,23 +56,28 @@
 //! // Request arbitrary state through the callback's type signature
 //! fn draw(state: &State, g: &Graphics) {
 //!     g.clear(Color::BLACK);
-//!     g.begin();
 //!     // Draw code here...
-//!     g.end();
 //! }
 //! ```
 
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 #![warn(missing_docs)]
+// The null backend leaves the event handling of the SDL backend unused.
+#![cfg_attr(feature = "null-backend", allow(dead_code))]
 
 mod app;
 pub use app::*;
 
+#[cfg(feature = "aseprite")]
+pub mod aseprite;
 pub mod assets;
 pub mod experimental;
 pub mod graphics;
 pub mod input;
 #[cfg(feature = "text")]
 pub mod text;
+pub mod time;
+pub mod tween;
+pub mod window;
 
 mod util;
@@ -1,17 +1,24 @@
 //! Types related to fonts and text rendering.
 
 use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use etagere::euclid::Size2D;
-use etagere::{AllocId, AtlasAllocator};
+use etagere::{AllocId, Allocation, AtlasAllocator, Size};
 use fontdue::layout::{GlyphPosition, GlyphRasterConfig, Layout, LayoutSettings, TextStyle};
 use fontdue::Metrics;
+use fugu::ImageFilter;
 use hashbrown::HashMap;
 
 use crate::assets::ResourceHandle;
 use crate::graphics::{Color, Graphics, Sprite};
 
 const ATLAS_SIZE: u32 = 2048;
+const LAYOUT_CACHE_SIZE: usize = 64;
+const DEFAULT_ATLAS_BUDGET: usize = 4 * ATLAS_SIZE as usize * ATLAS_SIZE as usize * 4;
 
 /// A TrueType/OpenType font, owning an immutable copy of the font data.
 pub struct Font {
@@ -23,13 +30,29 @@ struct FontInner {
     font: fontdue::Font,
     sprites: Vec<ResourceHandle<Sprite>>,
     allocators: Vec<AtlasAllocator>,
-    glyphs: HashMap<GlyphRasterConfig, Option<(usize, AllocId)>>,
+    glyphs: HashMap<GlyphRasterConfig, CachedGlyph>,
+    eviction_queue: EvictionQueue,
     draw_commands: Vec<DrawCommand>,
+    atlas_budget: Option<usize>,
+    premultiplied: bool,
+    options: FontOptions,
+    layout_cache: LayoutCache,
 }
 
 impl Font {
     /// Creates a new font from the given data.
     pub fn new(data: impl AsRef<[u8]>) -> Self {
+        Self::new_with_options(data, FontOptions::default())
+    }
+
+    /// Creates a new font from the given data with the given options, e.g. to
+    /// sample a pixel font without blurring it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the atlas size is zero.
+    pub fn new_with_options(data: impl AsRef<[u8]>, options: FontOptions) -> Self {
+        assert!(options.atlas_size > 0, "invalid atlas size 0");
         Self {
             layout: RefCell::new(Layout::new(
                 fontdue::layout::CoordinateSystem::PositiveYDown,
@@ -39,11 +62,65 @@ impl Font {
                 sprites: Vec::new(),
                 allocators: Vec::new(),
                 glyphs: HashMap::new(),
+                eviction_queue: EvictionQueue::default(),
                 draw_commands: Vec::new(),
+                atlas_budget: Some(DEFAULT_ATLAS_BUDGET),
+                premultiplied: false,
+                options,
+                layout_cache: LayoutCache::default(),
             },
         }
     }
 
+    /// Sets the maximum amount of memory in bytes that the font's glyph atlases
+    /// may use, or `None` for no limit.
+    ///
+    /// When a glyph does not fit into the existing atlases and allocating
+    /// another one would exceed the budget, the least recently drawn glyphs are
+    /// discarded to make room for it instead. Glyphs drawn during the current
+    /// frame are never discarded, so if they alone fill the budget, another
+    /// atlas is allocated anyway and a warning is printed. At least one atlas
+    /// is always allocated regardless of the budget.
+    ///
+    /// The default value is 64 MiB, i.e. four atlases of the default size.
+    /// Earlier versions did not limit the atlases, which can be restored by
+    /// passing `None`.
+    pub fn set_atlas_budget(&mut self, budget: Option<usize>) {
+        self.inner.atlas_budget = budget;
+    }
+
+    /// Sets whether the font's glyph atlases store premultiplied alpha, for
+    /// drawing text with [`BlendMode::Premultiplied`].
+    ///
+    /// Changing this discards all cached glyphs.
+    ///
+    /// The default value is `false`.
+    pub fn set_premultiplied(&mut self, premultiplied: bool) {
+        if self.inner.premultiplied != premultiplied {
+            self.inner.premultiplied = premultiplied;
+            self.inner.glyphs.clear();
+            self.inner
+                .allocators
+                .iter_mut()
+                .for_each(AtlasAllocator::clear);
+        }
+    }
+
+    /// Returns the amount of memory in bytes currently used by the font's glyph
+    /// atlases.
+    pub fn atlas_memory(&self) -> usize {
+        atlas_memory(&self.inner.allocators)
+    }
+
+    /// Discards all cached text layouts.
+    ///
+    /// The layouts of the most recently drawn texts are cached so that drawing
+    /// the same text at the same size again, e.g. every frame, is faster. The
+    /// cache is bounded, but clearing it frees its memory entirely.
+    pub fn clear_layout_cache(&mut self) {
+        self.inner.layout_cache.clear();
+    }
+
     /// Returns the metrics of the given glyph in the given font or `None`
     /// if the given glyph does not exist.
     pub fn measure_glyph(&self, c: char, size: f32) -> Option<GlyphMetrics> {
@@ -61,7 +138,51 @@ impl Font {
 
     /// Measures and returns the width and height of the given text in the given
     /// font.
+    ///
+    /// The width spans the drawn glyphs of all lines, so leading and trailing
+    /// whitespace is not included. The height spans all lines, using the same
+    /// line height as [`DrawText`].
+    ///
+    /// [`DrawText`]: crate::graphics::commands::DrawText
     pub fn measure_text(&self, text: &str, size: f32) -> (f32, f32) {
+        self.measure_text_with(text, size, &TextOptions::default())
+    }
+
+    /// Measures and returns the width and height of the given text in the given
+    /// font when wrapped to the given maximum width, e.g. for sizing a dialog
+    /// box. See [`DrawText::max_width`].
+    ///
+    /// [`DrawText::max_width`]: crate::graphics::commands::DrawText::max_width
+    pub fn measure_text_wrapped(&self, text: &str, size: f32, max_width: f32) -> (f32, f32) {
+        let options = TextOptions {
+            max_width: Some(max_width),
+            ..Default::default()
+        };
+        self.measure_text_with(text, size, &options)
+    }
+
+    /// Measures and returns the width and height of the given text in the given
+    /// font when laid out with the given options.
+    pub fn measure_text_with(&self, text: &str, size: f32, options: &TextOptions) -> (f32, f32) {
+        let Font { layout, inner } = self;
+        let mut layout = layout.borrow_mut();
+
+        // Only count glyphs that are drawn, so that leading whitespace and
+        // negative bearings don't shift the extents away from the drawn text.
+        let (x_min, x_max) = layout_text(&mut layout, &inner.font, text, size, options)
+            .iter()
+            .filter(|glyph| glyph.char_data.rasterize())
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), glyph| {
+                (min.min(glyph.x), max.max(glyph.x + glyph.width as f32))
+            });
+
+        ((x_max - x_min).max(0.), layout.height())
+    }
+
+    /// Returns the byte index in the given text of the character boundary
+    /// closest to the given horizontal offset from the start of the text, e.g.
+    /// for placing a caret where the user clicked.
+    pub fn text_index_at(&self, text: &str, size: f32, x: f32) -> usize {
         let Font { layout, inner } = self;
         let mut layout = layout.borrow_mut();
 
@@ -71,14 +192,12 @@ impl Font {
             &TextStyle::new(text, size, 0),
         );
 
-        let (x_min, x_max) = layout
+        layout
             .glyphs()
             .iter()
-            .fold((0_f32, 0_f32), |(min, max), glyph| {
-                (min.min(glyph.x), max.max(glyph.x + glyph.width as f32))
-            });
-
-        (x_max - x_min, layout.height())
+            .find(|glyph| x < glyph.x + glyph.width as f32 / 2.)
+            .map(|glyph| glyph.byte_offset)
+            .unwrap_or(text.len())
     }
 
     /// Returns the metrics of the given font.
@@ -103,6 +222,202 @@ impl Font {
     }
 }
 
+/// Options affecting how a [`Font`] stores its glyphs. Passed to
+/// [`Font::new_with_options`].
+#[derive(Clone, Copy)]
+pub struct FontOptions {
+    /// The width and height of each glyph atlas, in pixels. It is reduced to
+    /// the maximum texture size if necessary. Glyphs larger than it are not
+    /// drawn, and a warning is printed the first time one is skipped. Must not
+    /// be zero.
+    ///
+    /// The default value is `2048`.
+    pub atlas_size: u32,
+    /// The filter used when sampling the glyph atlases. Use
+    /// [`ImageFilter::Nearest`] for crisp pixel fonts.
+    ///
+    /// The default value is [`ImageFilter::Linear`].
+    pub filter: ImageFilter,
+}
+
+impl Default for FontOptions {
+    fn default() -> Self {
+        FontOptions {
+            atlas_size: ATLAS_SIZE,
+            filter: ImageFilter::Linear,
+        }
+    }
+}
+
+/// The horizontal alignment of text. See [`DrawText::align`].
+///
+/// [`DrawText::align`]: crate::graphics::commands::DrawText::align
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum TextAlign {
+    /// Each line starts at the position of the text.
+    Left,
+    /// Each line is centered on the position of the text.
+    Center,
+    /// Each line ends at the position of the text.
+    Right,
+}
+
+impl Default for TextAlign {
+    fn default() -> Self {
+        TextAlign::Left
+    }
+}
+
+/// Options affecting the layout of text. Set with the methods on [`DrawText`]
+/// when drawing, or passed to [`Font::measure_text_with`] when measuring.
+///
+/// [`DrawText`]: crate::graphics::commands::DrawText
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct TextOptions {
+    /// The horizontal alignment of the text. See [`DrawText::align`].
+    ///
+    /// [`DrawText::align`]: crate::graphics::commands::DrawText::align
+    pub align: TextAlign,
+    /// The maximum width of the text. See [`DrawText::max_width`].
+    ///
+    /// [`DrawText::max_width`]: crate::graphics::commands::DrawText::max_width
+    pub max_width: Option<f32>,
+    /// The absolute line height of the text. See [`DrawText::line_height`].
+    ///
+    /// [`DrawText::line_height`]: crate::graphics::commands::DrawText::line_height
+    pub line_height: Option<f32>,
+    /// The relative line spacing of the text. See
+    /// [`DrawText::line_spacing`].
+    ///
+    /// [`DrawText::line_spacing`]: crate::graphics::commands::DrawText::line_spacing
+    pub line_spacing: Option<f32>,
+}
+
+impl Hash for TextOptions {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.align.hash(state);
+        self.max_width.map(f32::to_bits).hash(state);
+        self.line_height.map(f32::to_bits).hash(state);
+        self.line_spacing.map(f32::to_bits).hash(state);
+    }
+}
+
+/// Wraps the given text to the given maximum width by inserting line breaks,
+/// breaking lines between words where possible.
+///
+/// Whitespace at the end of a line does not count towards its width and is
+/// dropped where a line is broken. Words wider than the maximum width are
+/// broken between characters instead.
+fn wrap_text(font: &fontdue::Font, text: &str, size: f32, max_width: f32) -> String {
+    // Matches the advances used by the layout.
+    let advance = |c: char| font.metrics(c, size).advance_width.ceil();
+
+    let mut wrapped = String::with_capacity(text.len());
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            wrapped.push('\n');
+        }
+
+        let mut width = 0.;
+        let mut rest = line;
+        while !rest.is_empty() {
+            // Split off the next run of either whitespace or non-whitespace.
+            let is_space = rest.starts_with(char::is_whitespace);
+            let end = rest
+                .find(|c: char| c.is_whitespace() != is_space)
+                .unwrap_or(rest.len());
+            let (word, next) = rest.split_at(end);
+            rest = next;
+
+            if is_space {
+                wrapped.push_str(word);
+                width += word.chars().map(advance).sum::<f32>();
+                continue;
+            }
+
+            let word_width = word.chars().map(advance).sum::<f32>();
+            let line_end = wrapped.trim_end_matches(|c: char| c != '\n' && c.is_whitespace());
+            let at_line_start = line_end.is_empty() || line_end.ends_with('\n');
+            if !at_line_start && width + word_width > max_width {
+                wrapped.truncate(line_end.len());
+                wrapped.push('\n');
+                width = 0.;
+            }
+
+            if word_width <= max_width {
+                wrapped.push_str(word);
+                width += word_width;
+            } else {
+                for c in word.chars() {
+                    let c_width = advance(c);
+                    if width > 0. && width + c_width > max_width {
+                        wrapped.push('\n');
+                        width = 0.;
+                    }
+                    wrapped.push(c);
+                    width += c_width;
+                }
+            }
+        }
+    }
+    wrapped
+}
+
+/// Lays out the given text at the origin and returns the positioned glyphs.
+fn layout_text(
+    layout: &mut Layout,
+    font: &fontdue::Font,
+    text: &str,
+    size: f32,
+    options: &TextOptions,
+) -> Vec<GlyphPosition> {
+    let wrapped;
+    let text = match options.max_width {
+        Some(max_width) => {
+            wrapped = wrap_text(font, text, size, max_width);
+            &wrapped
+        }
+        None => text,
+    };
+
+    // fontdue only supports line heights relative to the font's, so convert
+    // absolute line heights. It rounds the font's line height up internally.
+    let line_height = match (options.line_height, options.line_spacing) {
+        (Some(line_height), _) => font
+            .horizontal_line_metrics(size)
+            .map_or(1., |metrics| line_height / metrics.new_line_size.ceil()),
+        (None, Some(line_spacing)) => line_spacing,
+        (None, None) => 1.,
+    };
+
+    layout.reset(&LayoutSettings {
+        line_height,
+        ..Default::default()
+    });
+    layout.append(std::slice::from_ref(font), &TextStyle::new(text, size, 0));
+
+    let mut glyphs = layout.glyphs().clone();
+    if let (Some(lines), false) = (layout.lines(), options.align == TextAlign::Left) {
+        // Shift each line by its own width, so lines are aligned independently.
+        for line in lines {
+            let line = &mut glyphs[line.glyph_start..=line.glyph_end];
+            let width = line.iter().fold(0_f32, |width, glyph| {
+                width.max(glyph.x + glyph.width as f32)
+            });
+            let region = options.max_width.unwrap_or(0.);
+            let offset = match options.align {
+                TextAlign::Left => 0.,
+                TextAlign::Center => (region - width) / 2.,
+                TextAlign::Right => region - width,
+            };
+            for glyph in line {
+                glyph.x += offset;
+            }
+        }
+    }
+    glyphs
+}
+
 /// Font metrics. Returned by the [`measure_font`] method on [`Graphics`].
 ///
 /// [`measure_font`]: crate::graphics::Graphics::measure_font
@@ -137,6 +452,85 @@ pub struct GlyphMetrics {
     pub advance: f32,
 }
 
+/// A least-recently-used cache of text layouts, positioned at the origin.
+#[derive(Default)]
+struct LayoutCache {
+    layouts: HashMap<u64, CachedLayout>,
+    clock: u64,
+}
+
+struct CachedLayout {
+    text: String,
+    size: f32,
+    options: TextOptions,
+    glyphs: Vec<GlyphPosition>,
+    last_used: u64,
+}
+
+impl LayoutCache {
+    fn key(text: &str, size: f32, options: &TextOptions) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        size.to_bits().hash(&mut hasher);
+        options.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Removes and returns the layout of the given text, if it is cached.
+    fn take(&mut self, text: &str, size: f32, options: &TextOptions) -> Option<CachedLayout> {
+        let key = Self::key(text, size, options);
+        match self.layouts.get(&key) {
+            Some(layout)
+                if layout.text == text && layout.size == size && layout.options == *options =>
+            {
+                self.layouts.remove(&key)
+            }
+            _ => None,
+        }
+    }
+
+    /// Inserts the given layout, evicting the least recently used layout if
+    /// the cache is full.
+    fn insert(&mut self, mut layout: CachedLayout) {
+        let key = Self::key(&layout.text, layout.size, &layout.options);
+        if self.layouts.len() >= LAYOUT_CACHE_SIZE && !self.layouts.contains_key(&key) {
+            let oldest = self
+                .layouts
+                .iter()
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(&k, _)| k);
+            if let Some(oldest) = oldest {
+                self.layouts.remove(&oldest);
+            }
+        }
+        self.clock += 1;
+        layout.last_used = self.clock;
+        self.layouts.insert(key, layout);
+    }
+
+    fn clear(&mut self) {
+        self.layouts = HashMap::new();
+    }
+}
+
+/// A rasterized glyph, or `None` if it has no pixels, e.g. for whitespace.
+struct CachedGlyph {
+    entry: Option<(usize, AllocId)>,
+    last_used: u64,
+}
+
+/// The glyphs that may be evicted during a frame, with the least recently used
+/// glyph last.
+///
+/// The queue is sorted when the first glyph of a frame is evicted and reused
+/// for the rest of the frame, rather than sorting all glyphs for every
+/// evicted one.
+#[derive(Default)]
+struct EvictionQueue {
+    clock: Option<u64>,
+    keys: Vec<GlyphRasterConfig>,
+}
+
 struct DrawCommand {
     x: f32,
     y: f32,
@@ -157,34 +551,41 @@ pub(crate) fn draw_text(
     size: f32,
     color: Color,
     depth: f32,
+    options: &TextOptions,
+    snap: bool,
 ) {
     if let Some(mut font) = g.resource_manager.get_mut(font) {
         let Font { layout, inner } = &mut *font;
-        let mut layout = layout.borrow_mut();
 
-        layout.reset(&LayoutSettings {
-            x,
-            y,
-            ..Default::default()
-        });
-        layout.append(
-            std::slice::from_ref(&inner.font),
-            &TextStyle::new(text, size, 0),
-        );
+        // Layouts are cached at the origin and offset when drawing.
+        let cached = inner
+            .layout_cache
+            .take(text, size, options)
+            .unwrap_or_else(|| CachedLayout {
+                text: text.to_string(),
+                size,
+                options: *options,
+                glyphs: layout_text(&mut layout.borrow_mut(), &inner.font, text, size, options),
+                last_used: 0,
+            });
 
         inner.draw_commands.clear();
-        for glyph in layout.glyphs() {
-            draw_char(g, glyph, inner, size);
+        for glyph in &cached.glyphs {
+            draw_char(g, glyph, x, y, inner, size);
         }
+        inner.layout_cache.insert(cached);
 
+        // Group glyphs by atlas to reduce batch breaks, keeping the layout
+        // order within each atlas. Since the glyphs are submitted one after
+        // another, the text as a whole still stays in submission order relative
+        // to other draws at the same depth.
         if inner.sprites.len() > 1 {
-            inner
-                .draw_commands
-                .sort_unstable_by(|a, b| usize::cmp(&a.sprite, &b.sprite));
+            inner.draw_commands.sort_by_key(|cmd| cmd.sprite);
         }
 
         for cmd in &inner.draw_commands {
             g.draw_sprite(cmd.x, cmd.y, inner.sprites[cmd.sprite])
+                .snap_to_pixel(snap)
                 .source_rect(cmd.sx, cmd.sy, cmd.sw, cmd.sh)
                 .color(color)
                 .depth(depth);
@@ -192,62 +593,98 @@ pub(crate) fn draw_text(
     }
 }
 
+fn atlas_size(g: &Graphics, options: &FontOptions) -> u32 {
+    options.atlas_size.min(g.max_texture_size())
+}
+
 fn push_atlas(
     g: &mut Graphics,
     sprites: &mut Vec<ResourceHandle<Sprite>>,
     allocators: &mut Vec<AtlasAllocator>,
+    options: &FontOptions,
 ) {
+    let size = atlas_size(g, options);
     let sprite = g.resource_manager.allocate();
     g.resource_manager.set(
         sprite,
         Sprite::new(
             &g.ctx,
-            ATLAS_SIZE,
-            ATLAS_SIZE,
+            size,
+            size,
             fugu::ImageFormat::Rgba8,
-            fugu::ImageFilter::Linear,
+            options.filter,
             fugu::ImageWrap::Clamp,
-            vec![0; ATLAS_SIZE as usize * ATLAS_SIZE as usize * 4],
+            vec![0; size as usize * size as usize * 4],
         ),
     );
     sprites.push(sprite);
-    allocators.push(AtlasAllocator::new(Size2D::new(
-        ATLAS_SIZE as _,
-        ATLAS_SIZE as _,
-    )));
+    allocators.push(AtlasAllocator::new(Size2D::new(size as _, size as _)));
 }
 
+fn atlas_memory(allocators: &[AtlasAllocator]) -> usize {
+    allocators
+        .iter()
+        .map(|e| e.size().area() as usize * 4)
+        .sum()
+}
+
+#[allow(clippy::too_many_arguments)]
 fn insert_glyph(
     g: &mut Graphics,
     metrics: &Metrics,
     data: &[u8],
     sprites: &mut Vec<ResourceHandle<Sprite>>,
     allocators: &mut Vec<AtlasAllocator>,
+    glyphs: &mut HashMap<GlyphRasterConfig, CachedGlyph>,
+    eviction_queue: &mut EvictionQueue,
+    clock: u64,
+    budget: Option<usize>,
+    premultiplied: bool,
+    options: &FontOptions,
 ) -> Option<(usize, AllocId)> {
+    let atlas_size = atlas_size(g, options);
     if metrics.width == 0 || metrics.height == 0 {
         None
-    } else if metrics.width > ATLAS_SIZE as _ || metrics.height > ATLAS_SIZE as _ {
-        panic!("glyph bigger than atlas");
+    } else if metrics.width > atlas_size as _ || metrics.height > atlas_size as _ {
+        warn_oversized_glyph(metrics, atlas_size);
+        None
     } else {
+        let size = Size2D::new(metrics.width as _, metrics.height as _);
         // TODO: maybe use a heuristic to optimize choosing which atlas to use
-        let (i, alloc) = allocators
+        let found = allocators
             .iter_mut()
             .enumerate()
-            .find_map(|(i, e)| {
-                e.allocate(Size2D::new(metrics.width as _, metrics.height as _))
-                    .map(|alloc| (i, alloc))
-            })
-            .unwrap_or_else(|| {
+            .find_map(|(i, e)| e.allocate(size).map(|alloc| (i, alloc)));
+        let atlas_bytes = atlas_size as usize * atlas_size as usize * 4;
+        let within_budget = allocators.is_empty()
+            || atlas_memory(allocators) + atlas_bytes <= budget.unwrap_or(usize::MAX);
+        let found = match found {
+            None if !within_budget => evict_glyphs(allocators, glyphs, eviction_queue, clock, size),
+            found => found,
+        };
+        let (i, alloc) = match found {
+            Some(found) => found,
+            None => {
+                if !within_budget {
+                    // Evicting every glyph not drawn this frame didn't make
+                    // room, and the rest may not have been rendered yet, so
+                    // exceed the budget rather than overwrite them.
+                    warn_atlas_budget();
+                }
                 let i = allocators.len();
-                push_atlas(g, sprites, allocators);
-                let alloc = allocators[i]
-                    .allocate(Size2D::new(metrics.width as _, metrics.height as _))
-                    .unwrap();
-                (i, alloc)
-            });
+                push_atlas(g, sprites, allocators, options);
+                (i, allocators[i].allocate(size).unwrap())
+            }
+        };
         let data = data
             .iter()
-            .flat_map(|&x| [255, 255, 255, x])
+            .flat_map(|&x| {
+                if premultiplied {
+                    [x, x, x, x]
+                } else {
+                    [255, 255, 255, x]
+                }
+            })
             .collect::<Vec<_>>();
         g.resource_manager
             .get(sprites[i])
@@ -264,6 +701,66 @@ fn insert_glyph(
     }
 }
 
+/// Warns about exceeding the atlas budget of a font, the first time it happens.
+fn warn_atlas_budget() {
+    static WARNED: AtomicBool = AtomicBool::new(false);
+    if !WARNED.swap(true, Ordering::Relaxed) {
+        eprintln!("warning: exceeding the font atlas budget to fit the glyphs drawn this frame");
+    }
+}
+
+/// Warns about a glyph that is too large for the atlases, the first time one is
+/// skipped.
+fn warn_oversized_glyph(metrics: &Metrics, atlas_size: u32) {
+    static WARNED: AtomicBool = AtomicBool::new(false);
+    if !WARNED.swap(true, Ordering::Relaxed) {
+        eprintln!(
+            "warning: skipping a {}x{} glyph that does not fit into a {}x{} font atlas",
+            metrics.width, metrics.height, atlas_size, atlas_size
+        );
+    }
+}
+
+/// Evicts the least recently used glyphs until a glyph of the given size fits
+/// into one of the atlases, and returns its allocation.
+///
+/// The clock is the current frame. Glyphs used during it are kept, since draws
+/// that have not been rendered yet may refer to them.
+fn evict_glyphs(
+    allocators: &mut [AtlasAllocator],
+    glyphs: &mut HashMap<GlyphRasterConfig, CachedGlyph>,
+    queue: &mut EvictionQueue,
+    clock: u64,
+    size: Size,
+) -> Option<(usize, Allocation)> {
+    let evictable = |e: &CachedGlyph| e.entry.is_some() && e.last_used < clock;
+    if queue.clock != Some(clock) {
+        let mut candidates = glyphs
+            .iter()
+            .filter(|(_, e)| evictable(e))
+            .map(|(&key, e)| (e.last_used, key))
+            .collect::<Vec<_>>();
+        candidates.sort_unstable_by_key(|&(last_used, _)| Reverse(last_used));
+        queue.keys = candidates.into_iter().map(|(_, key)| key).collect();
+        queue.clock = Some(clock);
+    }
+
+    while let Some(key) = queue.keys.pop() {
+        // The glyph may have been drawn or discarded since the queue was
+        // sorted.
+        if !glyphs.get(&key).map_or(false, evictable) {
+            continue;
+        }
+        if let Some((i, id)) = glyphs.remove(&key).and_then(|e| e.entry) {
+            allocators[i].deallocate(id);
+            if let Some(alloc) = allocators[i].allocate(size) {
+                return Some((i, alloc));
+            }
+        }
+    }
+    None
+}
+
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn draw_glyph(
     g: &mut Graphics,
@@ -281,12 +778,17 @@ pub(crate) fn draw_glyph(
             sprites,
             allocators,
             glyphs,
+            eviction_queue,
+            atlas_budget,
+            premultiplied,
+            options,
             ..
         } = &mut font.inner;
 
         if sprites.is_empty() {
-            push_atlas(g, sprites, allocators);
+            push_atlas(g, sprites, allocators, options);
         }
+        let clock = g.frame();
 
         let key = GlyphRasterConfig {
             glyph_index: font.lookup_glyph_index(c),
@@ -294,12 +796,38 @@ pub(crate) fn draw_glyph(
             font_hash: font.file_hash(),
         };
 
-        let entry = glyphs.entry(key).or_insert_with(|| {
-            let (metrics, data) = font.rasterize(c, size);
-            insert_glyph(g, &metrics, &data, sprites, allocators)
-        });
+        let entry = match glyphs.get_mut(&key) {
+            Some(cached) => {
+                cached.last_used = clock;
+                cached.entry
+            }
+            None => {
+                let (metrics, data) = font.rasterize(c, size);
+                let entry = insert_glyph(
+                    g,
+                    &metrics,
+                    &data,
+                    sprites,
+                    allocators,
+                    glyphs,
+                    eviction_queue,
+                    clock,
+                    *atlas_budget,
+                    *premultiplied,
+                    options,
+                );
+                glyphs.insert(
+                    key,
+                    CachedGlyph {
+                        entry,
+                        last_used: clock,
+                    },
+                );
+                entry
+            }
+        };
 
-        if let &mut Some((i, id)) = entry {
+        if let Some((i, id)) = entry {
             let rect = allocators[i].get(id);
             g.draw_sprite(x, y, sprites[i])
                 .source_rect(
@@ -317,33 +845,69 @@ pub(crate) fn draw_glyph(
 fn draw_char(
     g: &mut Graphics,
     glyph: &GlyphPosition,
+    x: f32,
+    y: f32,
     FontInner {
         font,
         sprites,
         allocators,
         glyphs,
+        eviction_queue,
         draw_commands,
+        atlas_budget,
+        premultiplied,
+        options,
+        ..
     }: &mut FontInner,
     size: f32,
 ) {
     if sprites.is_empty() {
-        push_atlas(g, sprites, allocators);
+        push_atlas(g, sprites, allocators, options);
     }
+    let clock = g.frame();
 
-    let entry = glyphs.entry(glyph.key).or_insert_with(|| {
-        let c = glyph.parent;
-        let (metrics, data) = glyph
-            .char_data
-            .rasterize()
-            .then(|| font.rasterize(c, size))?;
-        insert_glyph(g, &metrics, &data, sprites, allocators)
-    });
+    let entry = match glyphs.get_mut(&glyph.key) {
+        Some(cached) => {
+            cached.last_used = clock;
+            cached.entry
+        }
+        None => {
+            let c = glyph.parent;
+            let entry = glyph
+                .char_data
+                .rasterize()
+                .then(|| font.rasterize(c, size))
+                .and_then(|(metrics, data)| {
+                    insert_glyph(
+                        g,
+                        &metrics,
+                        &data,
+                        sprites,
+                        allocators,
+                        glyphs,
+                        eviction_queue,
+                        clock,
+                        *atlas_budget,
+                        *premultiplied,
+                        options,
+                    )
+                });
+            glyphs.insert(
+                glyph.key,
+                CachedGlyph {
+                    entry,
+                    last_used: clock,
+                },
+            );
+            entry
+        }
+    };
 
-    if let &mut Some((i, id)) = entry {
+    if let Some((i, id)) = entry {
         let rect = allocators[i].get(id);
         draw_commands.push(DrawCommand {
-            x: glyph.x,
-            y: glyph.y,
+            x: x + glyph.x,
+            y: y + glyph.y,
             sx: rect.min.x as _,
             sy: rect.min.y as _,
             sw: rect.size().width as _,
@@ -352,3 +916,161 @@ fn draw_char(
         });
     }
 }
+
+// Drawing text needs a graphics context, which only the null backend can create
+// without a window.
+#[cfg(all(test, feature = "null-backend"))]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::assets::ResourceManager;
+    use crate::graphics::{Context, Gl};
+
+    const SIZE: f32 = 16.;
+
+    fn font() -> Font {
+        Font::new(include_bytes!("graphics/monogram.otf"))
+    }
+
+    /// Returns a graphics context without a window, with the given font
+    /// loaded.
+    fn graphics(font: Font) -> (Graphics, ResourceHandle<Font>) {
+        let resource_manager = ResourceManager::new();
+        let g = Graphics::new(&Rc::new(Context::new()), Gl::new(), &resource_manager);
+        let font = resource_manager.allocate_with(|| font);
+        (g, font)
+    }
+
+    /// Returns the extents of the glyphs [`draw_text`] draws for the given
+    /// text at the origin as `(x_min, y_min, x_max, y_max)`.
+    fn drawn_extents(
+        g: &mut Graphics,
+        font: ResourceHandle<Font>,
+        text: &str,
+    ) -> (f32, f32, f32, f32) {
+        let options = TextOptions::default();
+        draw_text(
+            g,
+            0.,
+            0.,
+            text,
+            font,
+            SIZE,
+            Color::WHITE,
+            0.,
+            &options,
+            false,
+        );
+        let font = g.resource_manager.get(font).unwrap();
+        font.inner.draw_commands.iter().fold(
+            (
+                f32::INFINITY,
+                f32::INFINITY,
+                f32::NEG_INFINITY,
+                f32::NEG_INFINITY,
+            ),
+            |(x_min, y_min, x_max, y_max), cmd| {
+                (
+                    x_min.min(cmd.x),
+                    y_min.min(cmd.y),
+                    x_max.max(cmd.x + cmd.sw),
+                    y_max.max(cmd.y + cmd.sh),
+                )
+            },
+        )
+    }
+
+    #[test]
+    fn measure_leading_whitespace() {
+        let (mut g, handle) = graphics(font());
+        let (x_min, _, x_max, _) = drawn_extents(&mut g, handle, "  hi");
+        assert!(x_min > 0.);
+
+        let font = g.resource_manager.get(handle).unwrap();
+        let (width, height) = font.measure_text("  hi", SIZE);
+        assert_eq!(width, x_max - x_min);
+        assert_eq!((width, height), font.measure_text("hi", SIZE));
+    }
+
+    #[test]
+    fn measure_multiple_lines() {
+        let (mut g, handle) = graphics(font());
+        let text = "a\nbb\nccc";
+        let (x_min, y_min, x_max, y_max) = drawn_extents(&mut g, handle, text);
+
+        let font = g.resource_manager.get(handle).unwrap();
+        // The widest line determines the width.
+        let (width, height) = font.measure_text(text, SIZE);
+        assert_eq!(width, x_max - x_min);
+        assert_eq!(width, font.measure_text("ccc", SIZE).0);
+
+        // Each line takes up the same height, and the drawn glyphs stay
+        // within it.
+        let line_height = font.measure_text("a", SIZE).1;
+        assert_eq!(height, 3. * line_height);
+        assert!(y_min >= 0. && y_max <= height);
+        assert!(y_max > 2. * line_height);
+    }
+
+    #[test]
+    fn atlas_budget_evicts_old_glyphs() {
+        let options = FontOptions {
+            atlas_size: 16,
+            ..Default::default()
+        };
+        let mut font = Font::new_with_options(include_bytes!("graphics/monogram.otf"), options);
+        font.set_atlas_budget(Some(16 * 16 * 4));
+        let (mut g, handle) = graphics(font);
+
+        // One glyph per frame, more than fit into the atlas.
+        for c in ('a'..='z').chain('A'..='Z') {
+            g.begin_frame();
+            let text = c.to_string();
+            draw_text(
+                &mut g,
+                0.,
+                0.,
+                &text,
+                handle,
+                SIZE,
+                Color::WHITE,
+                0.,
+                &TextOptions::default(),
+                false,
+            );
+        }
+
+        let font = g.resource_manager.get(handle).unwrap();
+        assert_eq!(font.atlas_memory(), 16 * 16 * 4);
+        let cached = font.inner.glyphs.values().filter(|e| e.entry.is_some());
+        assert!(cached.count() < 52);
+        assert!(font.inner.glyphs.values().any(|e| e.last_used == g.frame()));
+    }
+
+    #[test]
+    fn oversized_glyphs_are_skipped() {
+        let options = FontOptions {
+            atlas_size: 2,
+            ..Default::default()
+        };
+        let font = Font::new_with_options(include_bytes!("graphics/monogram.otf"), options);
+        let (mut g, handle) = graphics(font);
+        draw_text(
+            &mut g,
+            0.,
+            0.,
+            "M",
+            handle,
+            SIZE,
+            Color::WHITE,
+            0.,
+            &TextOptions::default(),
+            false,
+        );
+
+        let font = g.resource_manager.get(handle).unwrap();
+        assert!(font.inner.draw_commands.is_empty());
+        assert!(font.inner.glyphs.values().all(|e| e.entry.is_none()));
+    }
+}
@@ -0,0 +1,130 @@
+//! Types relating to frame timing.
+
+use std::time::{Duration, Instant};
+
+/// The maximum amount of time that fixed timesteps are run for in one frame.
+const MAX_ACCUMULATED: Duration = Duration::from_millis(250);
+
+/// The maximum number of fixed timesteps that are run in one frame, for fixed
+/// timesteps so short that even [`MAX_ACCUMULATED`] holds too many of them.
+const MAX_FIXED_STEPS: u32 = 100;
+
+/// How long before a deadline [`sleep_until`] stops sleeping and starts
+/// spinning, since the operating system may oversleep.
+const SPIN_THRESHOLD: Duration = Duration::from_millis(2);
+
+/// An interface for querying frame timing. Accessible from [`App`](crate::App)
+/// by default.
+pub struct Time {
+    start: Instant,
+    last_frame: Instant,
+    delta: Duration,
+    frame_count: u64,
+    fixed_delta: Duration,
+    accumulator: Duration,
+}
+
+impl Time {
+    pub(crate) fn new(fixed_delta: Duration) -> Time {
+        debug_assert!(fixed_delta > Duration::ZERO);
+        let now = Instant::now();
+        Time {
+            start: now,
+            last_frame: now,
+            delta: Duration::ZERO,
+            frame_count: 0,
+            fixed_delta,
+            accumulator: Duration::ZERO,
+        }
+    }
+
+    pub(crate) fn update(&mut self, now: Instant) {
+        self.delta = now - self.last_frame;
+        self.last_frame = now;
+        self.frame_count += 1;
+        // Cap the time to catch up on so that a long frame does not cause an
+        // ever-growing number of fixed timesteps.
+        self.accumulator = (self.accumulator + self.delta).min(MAX_ACCUMULATED);
+    }
+
+    /// Consumes and returns the number of fixed timesteps that have elapsed,
+    /// up to [`MAX_FIXED_STEPS`]. Any further elapsed timesteps are dropped.
+    pub(crate) fn take_fixed_steps(&mut self) -> u32 {
+        let steps = self.accumulator.as_nanos() / self.fixed_delta.as_nanos();
+        self.accumulator = Duration::from_nanos(
+            (self.accumulator.as_nanos() % self.fixed_delta.as_nanos()) as u64,
+        );
+        steps.min(MAX_FIXED_STEPS as u128) as u32
+    }
+
+    /// Returns the time in seconds between the start of the previous frame and
+    /// the start of the current frame.
+    ///
+    /// This is `0` during the first frame.
+    pub fn delta(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// Returns the time in seconds between the start of the application and
+    /// the start of the current frame.
+    pub fn elapsed(&self) -> f32 {
+        (self.last_frame - self.start).as_secs_f32()
+    }
+
+    /// Returns the time in seconds between fixed timesteps, as set by
+    /// [`App::with_fixed_timestep`](crate::App::with_fixed_timestep).
+    ///
+    /// This is the amount of time that fixed callbacks should advance the
+    /// simulation by.
+    pub fn fixed_delta(&self) -> f32 {
+        self.fixed_delta.as_secs_f32()
+    }
+
+    /// Returns how far the current frame is between the last fixed timestep
+    /// and the next, from `0.0` to `1.0`.
+    ///
+    /// This is useful for interpolating between the previous and current state
+    /// of the simulation when rendering, which makes movement appear smooth
+    /// even when the frame rate and fixed rate differ.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator.as_secs_f64() / self.fixed_delta.as_secs_f64()) as f32
+    }
+
+    /// Returns the number of the current frame, starting at `1` for the first
+    /// frame.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+}
+
+/// Blocks the current thread until the given instant, sleeping for most of the
+/// time and spinning for the rest to wake up accurately.
+pub(crate) fn sleep_until(deadline: Instant) {
+    let now = Instant::now();
+    if deadline > now + SPIN_THRESHOLD {
+        std::thread::sleep(deadline - now - SPIN_THRESHOLD);
+    }
+    while Instant::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_steps_are_capped() {
+        let mut time = Time::new(Duration::from_nanos(1));
+        let now = time.last_frame;
+        time.update(now + Duration::from_millis(100));
+        assert_eq!(time.take_fixed_steps(), MAX_FIXED_STEPS);
+        assert_eq!(time.alpha(), 0.);
+
+        let mut time = Time::new(Duration::from_millis(10));
+        let now = time.last_frame;
+        time.update(now + Duration::from_millis(25));
+        assert_eq!(time.take_fixed_steps(), 2);
+        assert!((time.alpha() - 0.5).abs() < 1e-6);
+    }
+}
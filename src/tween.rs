@@ -0,0 +1,195 @@
+//! Easing functions and a simple tweening driver.
+//!
+//! All easing functions take a progress `t` in the range `[0, 1]` and return
+//! the eased progress, which is `0` at `t = 0` and `1` at `t = 1` but may lie
+//! outside of `[0, 1]` in between (e.g. for [`ease_out_back`]).
+
+use std::f32::consts::PI;
+
+use crate::graphics::Color;
+
+/// An easing function.
+pub type Easing = fn(f32) -> f32;
+
+/// No easing.
+pub fn linear(t: f32) -> f32 {
+    t
+}
+
+/// Quadratic easing that starts slowly.
+pub fn ease_in_quad(t: f32) -> f32 {
+    t * t
+}
+
+/// Quadratic easing that ends slowly.
+pub fn ease_out_quad(t: f32) -> f32 {
+    1. - (1. - t) * (1. - t)
+}
+
+/// Quadratic easing that starts and ends slowly.
+pub fn ease_in_out_quad(t: f32) -> f32 {
+    if t < 0.5 {
+        2. * t * t
+    } else {
+        1. - (-2. * t + 2.).powi(2) / 2.
+    }
+}
+
+/// Cubic easing that starts slowly.
+pub fn ease_in_cubic(t: f32) -> f32 {
+    t * t * t
+}
+
+/// Cubic easing that ends slowly.
+pub fn ease_out_cubic(t: f32) -> f32 {
+    1. - (1. - t).powi(3)
+}
+
+/// Cubic easing that starts and ends slowly.
+pub fn ease_in_out_cubic(t: f32) -> f32 {
+    if t < 0.5 {
+        4. * t * t * t
+    } else {
+        1. - (-2. * t + 2.).powi(3) / 2.
+    }
+}
+
+/// Sinusoidal easing that starts and ends slowly.
+pub fn ease_in_out_sine(t: f32) -> f32 {
+    -((PI * t).cos() - 1.) / 2.
+}
+
+/// Easing that pulls back slightly before starting.
+pub fn ease_in_back(t: f32) -> f32 {
+    const C1: f32 = 1.70158;
+    const C3: f32 = C1 + 1.;
+    C3 * t * t * t - C1 * t * t
+}
+
+/// Easing that overshoots slightly before settling.
+pub fn ease_out_back(t: f32) -> f32 {
+    const C1: f32 = 1.70158;
+    const C3: f32 = C1 + 1.;
+    1. + C3 * (t - 1.).powi(3) + C1 * (t - 1.).powi(2)
+}
+
+/// Easing that bounces like a ball dropped on the floor.
+pub fn ease_out_bounce(t: f32) -> f32 {
+    const N1: f32 = 7.5625;
+    const D1: f32 = 2.75;
+    if t < 1. / D1 {
+        N1 * t * t
+    } else if t < 2. / D1 {
+        let t = t - 1.5 / D1;
+        N1 * t * t + 0.75
+    } else if t < 2.5 / D1 {
+        let t = t - 2.25 / D1;
+        N1 * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D1;
+        N1 * t * t + 0.984375
+    }
+}
+
+/// Easing that oscillates before settling, like a spring.
+pub fn ease_out_elastic(t: f32) -> f32 {
+    const C4: f32 = 2. * PI / 3.;
+    if t <= 0. {
+        0.
+    } else if t >= 1. {
+        1.
+    } else {
+        2_f32.powf(-10. * t) * ((t * 10. - 0.75) * C4).sin() + 1.
+    }
+}
+
+/// A value that can be linearly interpolated.
+pub trait Lerp: Copy {
+    /// Linearly interpolates between `self` and `other` by `t`, where `t = 0`
+    /// returns `self` and `t = 1` returns `other`.
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for (f32, f32) {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        (self.0.lerp(other.0, t), self.1.lerp(other.1, t))
+    }
+}
+
+impl Lerp for Color {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        Color::from_rgba(
+            self.r.lerp(other.r, t),
+            self.g.lerp(other.g, t),
+            self.b.lerp(other.b, t),
+            self.a.lerp(other.a, t),
+        )
+    }
+}
+
+/// Drives the interpolation of a value from a start value to an end value over
+/// a duration, using an [`Easing`] function.
+#[derive(Clone, Copy, Debug)]
+pub struct Tween<T> {
+    start: T,
+    end: T,
+    duration: f32,
+    elapsed: f32,
+    easing: Easing,
+}
+
+impl<T: Lerp> Tween<T> {
+    /// Creates a new tween from `start` to `end` lasting `duration` seconds,
+    /// using [`linear`] easing.
+    pub fn new(start: T, end: T, duration: f32) -> Self {
+        Self {
+            start,
+            end,
+            duration,
+            elapsed: 0.,
+            easing: linear,
+        }
+    }
+
+    /// Sets the easing function of the tween.
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    /// Advances the tween by `dt` seconds and returns the current value.
+    pub fn update(&mut self, dt: f32) -> T {
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+        self.value()
+    }
+
+    /// Returns the current value of the tween.
+    pub fn value(&self) -> T {
+        self.start.lerp(self.end, (self.easing)(self.progress()))
+    }
+
+    /// Returns the progress of the tween in the range `[0, 1]`, before easing.
+    pub fn progress(&self) -> f32 {
+        if self.duration > 0. {
+            self.elapsed / self.duration
+        } else {
+            1.
+        }
+    }
+
+    /// Returns true if the tween has reached its end value.
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    /// Restarts the tween from its start value.
+    pub fn reset(&mut self) {
+        self.elapsed = 0.;
+    }
+}
@@ -0,0 +1,159 @@
+//! Types relating to the application window.
+
+/// An interface for controlling the application window. Accessible from
+/// [`App`](crate::App) by default.
+///
+/// Changes requested through the [`Window`] are applied by the backend at the
+/// end of the current frame.
+pub struct Window {
+    pub(crate) mouse_warp: Option<(f32, f32)>,
+    pub(crate) quit_requested: bool,
+    pub(crate) scale_factor: f32,
+    pub(crate) cursor_visible: bool,
+    pub(crate) cursor_grabbed: bool,
+    pub(crate) relative_mouse: bool,
+}
+
+impl Window {
+    pub(crate) fn new() -> Window {
+        Window {
+            mouse_warp: None,
+            quit_requested: false,
+            scale_factor: 1.,
+            cursor_visible: true,
+            cursor_grabbed: false,
+            relative_mouse: false,
+        }
+    }
+
+    /// Quits the application at the end of the current frame, e.g. from a quit
+    /// menu item.
+    ///
+    /// Like closing the window, this executes the
+    /// [quit callbacks](crate::App::add_quit_callback), and the request is
+    /// dropped if any of them cancels it. If this is called from an init
+    /// callback, the application quits before the first frame.
+    pub fn request_quit(&mut self) {
+        self.quit_requested = true;
+    }
+
+    /// Returns the number of pixels per logical unit of the window's size, e.g.
+    /// `2.0` on many high-DPI screens.
+    ///
+    /// This is updated when the window is resized or moved to a screen with a
+    /// different scale. Pass it to [`Graphics::set_scale`] to draw in logical
+    /// units instead of pixels.
+    ///
+    /// [`Graphics::set_scale`]: crate::graphics::Graphics::set_scale
+    pub fn scale_factor(&self) -> f32 {
+        self.scale_factor
+    }
+
+    /// Sets whether the mouse cursor is shown while it is over the window.
+    ///
+    /// The default value is `true`.
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        self.cursor_visible = visible;
+    }
+
+    /// Returns whether the mouse cursor is shown while it is over the window.
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// Sets whether the mouse cursor is confined to the window.
+    ///
+    /// The default value is `false`.
+    pub fn set_cursor_grabbed(&mut self, grabbed: bool) {
+        self.cursor_grabbed = grabbed;
+    }
+
+    /// Returns whether the mouse cursor is confined to the window.
+    pub fn cursor_grabbed(&self) -> bool {
+        self.cursor_grabbed
+    }
+
+    /// Sets whether relative mouse mode is enabled, e.g. for a first-person
+    /// camera.
+    ///
+    /// In relative mode, the cursor is hidden and confined to the window, and
+    /// the mouse position stays where it was while
+    /// [`Input::mouse_delta`](crate::input::Input::mouse_delta) keeps
+    /// reporting motion.
+    ///
+    /// The default value is `false`.
+    pub fn set_relative_mouse(&mut self, relative: bool) {
+        self.relative_mouse = relative;
+    }
+
+    /// Returns whether relative mouse mode is enabled.
+    pub fn relative_mouse(&self) -> bool {
+        self.relative_mouse
+    }
+
+    /// Moves the mouse cursor to the given position in window coordinates, i.e.
+    /// the same coordinates as returned by
+    /// [`Input::mouse_position`](crate::input::Input::mouse_position).
+    ///
+    /// If this is called multiple times in a frame, only the last position is
+    /// used. Warping does not count towards
+    /// [`Input::mouse_delta`](crate::input::Input::mouse_delta).
+    pub fn warp_mouse(&mut self, x: f32, y: f32) {
+        self.mouse_warp = Some((x, y));
+    }
+}
+
+/// Events of the application window that occurred during the current frame.
+/// Accessible from [`App`](crate::App) by default.
+pub struct WindowEvents {
+    resized: Option<(u32, u32)>,
+    close_requested: bool,
+}
+
+impl WindowEvents {
+    pub(crate) fn new() -> WindowEvents {
+        WindowEvents {
+            resized: None,
+            close_requested: false,
+        }
+    }
+
+    pub(crate) fn update(&mut self) {
+        self.resized = None;
+        self.close_requested = false;
+    }
+
+    pub(crate) fn resize(&mut self, width: u32, height: u32) {
+        self.resized = Some((width, height));
+    }
+
+    pub(crate) fn request_close(&mut self) {
+        self.close_requested = true;
+    }
+
+    /// Returns the new size of the window in pixels if it was resized during
+    /// the current frame, e.g. for repositioning user interface elements.
+    ///
+    /// This also happens when the window is moved to a screen with a different
+    /// [scale factor](Window::scale_factor).
+    pub fn resized(&self) -> Option<(u32, u32)> {
+        self.resized
+    }
+
+    /// Returns true if the user requested to close the window during the
+    /// current frame.
+    ///
+    /// Unless the request is [vetoed](Self::veto_close), the application quits
+    /// once the frame callbacks of the current frame have been executed,
+    /// provided that all [quit callbacks](crate::App::add_quit_callback)
+    /// allow it.
+    pub fn close_requested(&self) -> bool {
+        self.close_requested
+    }
+
+    /// Cancels the request to close the window made during the current frame,
+    /// e.g. to show a prompt asking to save first.
+    pub fn veto_close(&mut self) {
+        self.close_requested = false;
+    }
+}